@@ -0,0 +1,192 @@
+use std::time::Duration;
+
+use futures_util::{stream, StreamExt};
+use reqwest::StatusCode;
+
+use crate::{bulk::HostSchedule, level::Level, modal::DifficultTable, options::ParseOptions};
+
+/// A link is reported broken if it doesn't answer within this delay
+const LINK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Result of [`DifficultTable::audit_links`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkAudit {
+    /// Number of links checked
+    pub checked: usize,
+    /// Broken links per level, in `levels` order, levels without broken links are omitted
+    pub broken: Vec<(Level, Vec<BrokenLink>)>,
+}
+
+/// One element link that failed, see [`LinkAudit`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrokenLink {
+    pub title: String,
+    pub md5: String,
+    /// `url`, `url_diff` or `url_pack`
+    pub field: &'static str,
+    pub url: String,
+    /// Status or error returned by the request
+    pub reason: String,
+}
+
+impl DifficultTable {
+    /// HEAD every element's `url`, `url_diff` and `url_pack`, reporting the broken ones per level, enabled by the `async` feature
+    ///
+    /// * `concurrency` - how many requests may be in flight at once, keep it low to be nice to the hosts
+    ///
+    /// Must be run inside a tokio runtime. Servers refusing HEAD requests are asked with GET instead. Requests to
+    /// the same host are spaced by the default [`ParseOptions::per_host_interval`]
+    pub async fn audit_links(&self, concurrency: usize) -> LinkAudit {
+        self.audit_links_with_options(concurrency, &ParseOptions::default())
+            .await
+    }
+
+    /// Same as [`DifficultTable::audit_links`], spacing requests to the same host by
+    /// [`ParseOptions::per_host_interval`]
+    pub async fn audit_links_with_options(
+        &self,
+        concurrency: usize,
+        options: &ParseOptions,
+    ) -> LinkAudit {
+        let schedule = HostSchedule::new(options.per_host_interval);
+        let client = reqwest::Client::builder()
+            .timeout(LINK_TIMEOUT)
+            .user_agent(crate::client::DEFAULT_USER_AGENT)
+            .build()
+            .unwrap_or_default();
+        let links: Vec<(usize, &'static str, &str)> = self
+            .contents
+            .iter()
+            .enumerate()
+            .flat_map(|(index, content)| {
+                [
+                    ("url", &content.url),
+                    ("url_diff", &content.url_diff),
+                    ("url_pack", &content.url_pack),
+                ]
+                .into_iter()
+                .filter(|(_, url)| !url.is_empty())
+                .map(move |(field, url)| (index, field, url.as_str()))
+            })
+            .collect();
+        let checked = links.len();
+        let mut failures: Vec<(usize, BrokenLink)> = stream::iter(links)
+            .map(|(index, field, url)| {
+                let (client, schedule) = (&client, &schedule);
+                async move {
+                    let reason = check_link(client, schedule, url).await.err()?;
+                    let content = &self.contents[index];
+                    Some((
+                        index,
+                        BrokenLink {
+                            title: content.title.clone(),
+                            md5: content.md5.clone(),
+                            field,
+                            url: url.to_string(),
+                            reason,
+                        },
+                    ))
+                }
+            })
+            .buffer_unordered(concurrency.max(1))
+            .filter_map(|failure| async move { failure })
+            .collect()
+            .await;
+        failures.sort_by_key(|(index, link)| (*index, link.field));
+        let broken = self
+            .levels
+            .iter()
+            .filter_map(|level| {
+                let links: Vec<BrokenLink> = failures
+                    .iter()
+                    .filter(|(index, _)| &self.contents[*index].level == level)
+                    .map(|(_, link)| link.clone())
+                    .collect();
+                (!links.is_empty()).then(|| (level.clone(), links))
+            })
+            .collect();
+        LinkAudit { checked, broken }
+    }
+}
+
+/// Check one link, returning why it's broken
+async fn check_link(
+    client: &reqwest::Client,
+    schedule: &HostSchedule,
+    url: &str,
+) -> Result<(), String> {
+    wait_turn(schedule, url).await;
+    let mut resp = client.head(url).send().await.map_err(|e| e.to_string())?;
+    if matches!(
+        resp.status(),
+        StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED
+    ) {
+        wait_turn(schedule, url).await;
+        resp = client.get(url).send().await.map_err(|e| e.to_string())?;
+    }
+    match resp.status() {
+        status if status.is_client_error() || status.is_server_error() => Err(status.to_string()),
+        _ => Ok(()),
+    }
+}
+
+async fn wait_turn(schedule: &HostSchedule, url: &str) {
+    if let Some(turn) = schedule.turn(url) {
+        tokio::time::sleep_until(turn.into()).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn should_report_broken_links_per_level() {
+        let mut table: DifficultTable =
+            serde_json::from_value(json!({ "name": "Satellite", "symbol": "sl" })).unwrap();
+        for (title, level, url) in [
+            ("a", "1", "http://127.0.0.1:1/a.zip"),
+            ("b", "1", ""),
+            ("c", "2", "not an url"),
+        ] {
+            table.add_element(
+                serde_json::from_value(json!({
+                    "title": title, "artist": "", "md5": title, "level": level, "url": url
+                }))
+                .unwrap(),
+            );
+        }
+        let audit = table.audit_links(2).await;
+        assert_eq!(audit.checked, 2);
+        assert_eq!(audit.broken.len(), 2);
+        assert_eq!(audit.broken[0].0, Level::from("1"));
+        assert_eq!(audit.broken[0].1[0].title, "a");
+        assert_eq!(audit.broken[0].1[0].field, "url");
+        assert_eq!(audit.broken[1].1[0].url, "not an url");
+    }
+
+    #[tokio::test]
+    async fn should_space_requests_to_the_same_host() {
+        let mut table: DifficultTable =
+            serde_json::from_value(json!({ "name": "Satellite", "symbol": "sl" })).unwrap();
+        for title in ["a", "b", "c"] {
+            table.add_element(
+                serde_json::from_value(json!({
+                    "title": title, "artist": "", "md5": title, "level": "1",
+                    "url": format!("http://127.0.0.1:1/{title}.zip"),
+                }))
+                .unwrap(),
+            );
+        }
+        let options = ParseOptions {
+            per_host_interval: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let started = std::time::Instant::now();
+        let audit = table.audit_links_with_options(3, &options).await;
+        assert_eq!(audit.broken[0].1.len(), 3);
+        assert!(started.elapsed() >= Duration::from_millis(190));
+    }
+}
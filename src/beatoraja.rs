@@ -0,0 +1,301 @@
+use std::{fs, io::Read, path::Path};
+
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    level::Level,
+    modal::{
+        DifficultTable, DifficultTableCourse, DifficultTableCourseTrophy, DifficultTableElement,
+    },
+    parser::ParseError,
+};
+
+/// One course in beatoraja's course definition format, as found in its `course/*.json` files
+///
+/// Serializing a `Vec<BeatorajaCourse>` gives a file beatoraja loads as is
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BeatorajaCourse {
+    pub name: String,
+    /// Charts of the course, in play order
+    pub hash: Vec<BeatorajaSong>,
+    /// Constraint names, e.g. `grade_mirror`, `gauge_lr2`
+    #[serde(default)]
+    pub constraint: Vec<String>,
+    #[serde(default)]
+    pub trophy: Vec<DifficultTableCourseTrophy>,
+    /// Whether the course is released (playable with a grade), true for table courses
+    #[serde(default)]
+    pub release: bool,
+}
+
+/// One chart of a [`BeatorajaCourse`], beatoraja matches it by sha256 first, then by md5
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BeatorajaSong {
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub md5: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub sha256: String,
+}
+
+impl DifficultTableCourse {
+    /// Convert this course into beatoraja's format
+    ///
+    /// Titles and sha256s are taken from the table's element of each md5 when there's one. The table format's
+    /// constraint names are beatoraja's own, they are kept as is
+    pub fn to_beatoraja(&self, table: &DifficultTable) -> BeatorajaCourse {
+        let hash = self
+            .md5
+            .iter()
+            .map(
+                |md5| match md5.parse().ok().and_then(|md5| table.find_by_md5(md5)) {
+                    Some(element) => BeatorajaSong {
+                        title: element.title.clone(),
+                        md5: md5.clone(),
+                        sha256: element.sha256.clone(),
+                    },
+                    None => BeatorajaSong {
+                        title: String::new(),
+                        md5: md5.clone(),
+                        sha256: String::new(),
+                    },
+                },
+            )
+            .collect();
+        BeatorajaCourse {
+            name: self.name.clone(),
+            hash,
+            constraint: self.constraints.clone(),
+            trophy: self.trophy.clone(),
+            release: true,
+        }
+    }
+}
+
+impl DifficultTable {
+    /// Every course converted by [`DifficultTableCourse::to_beatoraja`]
+    ///
+    /// # Example:
+    /// ```text
+    /// let courses = dth.beatoraja_courses();
+    /// fs::write(beatoraja_dir.join("course/satellite.json"), serde_json::to_string(&courses)?)?;
+    /// ```
+    pub fn beatoraja_courses(&self) -> Vec<BeatorajaCourse> {
+        self.courses
+            .iter()
+            .map(|course| course.to_beatoraja(self))
+            .collect()
+    }
+}
+
+/// beatoraja's cached copy of one table, a gzipped json
+#[derive(Deserialize)]
+struct CachedTable {
+    #[serde(default)]
+    url: String,
+    name: String,
+    /// The table's symbol
+    #[serde(default)]
+    tag: String,
+    /// One folder per level, named symbol followed by level
+    #[serde(default)]
+    folder: Vec<CachedFolder>,
+    #[serde(default, deserialize_with = "flatten_courses")]
+    course: Vec<BeatorajaCourse>,
+}
+
+#[derive(Deserialize)]
+struct CachedFolder {
+    name: String,
+    #[serde(default)]
+    songs: Vec<CachedSong>,
+}
+
+#[derive(Deserialize)]
+struct CachedSong {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    artist: String,
+    #[serde(default)]
+    md5: String,
+    #[serde(default)]
+    sha256: String,
+    #[serde(default)]
+    url: String,
+    #[serde(default, rename = "appendurl")]
+    url_diff: String,
+}
+
+/// Older beatoraja versions keep courses as a two-dimensional array
+fn flatten_courses<'de, D>(d: D) -> Result<Vec<BeatorajaCourse>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let mut courses = Vec::new();
+    for course in Vec::<Value>::deserialize(d)? {
+        match course {
+            Value::Array(group) => {
+                for course in group {
+                    courses.push(serde_json::from_value(course).map_err(serde::de::Error::custom)?);
+                }
+            }
+            course => {
+                courses.push(serde_json::from_value(course).map_err(serde::de::Error::custom)?)
+            }
+        }
+    }
+    Ok(courses)
+}
+
+/// Read one of beatoraja's cached tables (the `table/*.bmt` files of its installation), see
+/// [`parse_beatoraja_table`]
+///
+/// # Example:
+/// ```text
+/// for entry in fs::read_dir(beatoraja_dir.join("table"))? {
+///     let dth = read_beatoraja_table(entry?.path())?;
+///     println!("{}: {} charts", dth.name, dth.contents.len());
+/// }
+/// ```
+pub fn read_beatoraja_table(path: impl AsRef<Path>) -> Result<DifficultTable, ParseError> {
+    parse_beatoraja_table(&fs::read(path)?)
+}
+
+/// Convert one of beatoraja's cached tables back into a [`DifficultTable`], gzipped or not
+///
+/// The cache doesn't keep the header's data_url nor per element levels: levels are taken from folder names
+/// (stripped of the symbol), `level_order` follows the folders and `original_url` is the url beatoraja fetched
+pub fn parse_beatoraja_table(raw: &[u8]) -> Result<DifficultTable, ParseError> {
+    let raw = match raw {
+        [0x1f, 0x8b, ..] => {
+            let mut unzipped = Vec::new();
+            GzDecoder::new(raw).read_to_end(&mut unzipped)?;
+            unzipped
+        }
+        raw => raw.to_vec(),
+    };
+    let cached: CachedTable = serde_json::from_slice(&raw)?;
+    let mut header: DifficultTable = serde_json::from_value(json!({
+        "name": cached.name,
+        "symbol": cached.tag,
+        "tag": cached.tag,
+        "original_url": cached.url,
+    }))?;
+    let mut contents = Vec::new();
+    for folder in cached.folder {
+        let level = Level::from(
+            folder
+                .name
+                .strip_prefix(cached.tag.as_str())
+                .unwrap_or(&folder.name),
+        );
+        header.level_order.push(level.clone());
+        for song in folder.songs {
+            let element: DifficultTableElement = serde_json::from_value(json!({
+                "title": song.title,
+                "artist": song.artist,
+                "md5": song.md5,
+                "sha256": song.sha256,
+                "level": level,
+                "url": song.url,
+                "url_diff": song.url_diff,
+            }))?;
+            contents.push(element);
+        }
+    }
+    let courses = cached
+        .course
+        .into_iter()
+        .map(|course| DifficultTableCourse {
+            name: course.name,
+            constraints: course.constraint,
+            trophy: course.trophy,
+            md5: course.hash.into_iter().map(|song| song.md5).collect(),
+        })
+        .collect();
+    Ok(DifficultTable::from_parts(header, contents, courses))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{Compression, GzBuilder};
+
+    use super::*;
+
+    #[test]
+    pub fn should_convert_courses_to_beatoraja() {
+        let (aa, cc) = ("aa".repeat(16), "cc".repeat(32));
+        let mut table: DifficultTable = serde_json::from_value(json!({
+            "name": "Satellite",
+            "symbol": "sl",
+            "course": [[{
+                "name": "sl0",
+                "constraint": ["grade_mirror", "gauge_lr2"],
+                "trophy": [{ "name": "goldmedal", "missrate": 1.0, "scorerate": 70.0 }],
+                "md5": [aa, "missing"],
+            }]],
+        }))
+        .unwrap();
+        table.add_element(
+            serde_json::from_value(
+                json!({ "title": "a", "artist": "", "md5": aa.to_uppercase(), "sha256": cc, "level": "0" }),
+            )
+            .unwrap(),
+        );
+        let courses = serde_json::to_value(table.beatoraja_courses()).unwrap();
+        assert_eq!(
+            courses,
+            json!([{
+                "name": "sl0",
+                "hash": [
+                    { "title": "a", "md5": aa, "sha256": cc },
+                    { "title": "", "md5": "missing" },
+                ],
+                "constraint": ["grade_mirror", "gauge_lr2"],
+                "trophy": [{ "name": "goldmedal", "missrate": 1.0, "scorerate": 70.0 }],
+                "release": true,
+            }])
+        );
+    }
+
+    #[test]
+    pub fn should_read_beatoraja_cached_tables() {
+        let cc = "cc".repeat(32);
+        let cached = json!({
+            "url": "https://example.com/sl/table.html",
+            "name": "Satellite",
+            "tag": "sl",
+            "folder": [
+                { "name": "sl0", "songs": [{ "title": "a", "md5": "aa", "sha256": cc, "level": 12 }] },
+                { "name": "sl1", "songs": [{ "title": "b", "md5": "bb", "appendurl": "x" }] },
+            ],
+            "course": [[{ "name": "sl0", "hash": [{ "md5": "aa" }], "constraint": ["grade_mirror"] }]],
+        })
+        .to_string();
+        let mut encoder = GzBuilder::new()
+            .filename("satellite.bmt")
+            .write(Vec::new(), Compression::default());
+        encoder.write_all(cached.as_bytes()).unwrap();
+        let gzipped = encoder.finish().unwrap();
+
+        for raw in [cached.as_bytes(), &gzipped] {
+            let table = parse_beatoraja_table(raw).unwrap();
+            assert_eq!(table.symbol, "sl");
+            assert_eq!(table.original_url, "https://example.com/sl/table.html");
+            assert_eq!(table.levels, ["0", "1"].map(Level::from));
+            assert_eq!(
+                table.find_by_sha256(cc.parse().unwrap()).unwrap().level,
+                "0"
+            );
+            assert_eq!(table.contents[1].url_diff, "x");
+            assert_eq!(table.courses[0].md5, ["aa"]);
+        }
+        assert!(parse_beatoraja_table(&gzipped[..12]).is_err());
+    }
+}
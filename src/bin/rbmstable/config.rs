@@ -0,0 +1,150 @@
+use std::{
+    env,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+pub use rbmstable_parser::parse_interval;
+use rbmstable_parser::{LevelOrdering, ParseOptions, DEFAULT_USER_AGENT};
+use serde::Deserialize;
+
+/// Application directory name under the XDG base directories
+const APP_DIR: &str = "rbmstable";
+
+/// Settings read from `$XDG_CONFIG_HOME/rbmstable/config.toml`, or the file `RBMSTABLE_CONFIG` points at
+///
+/// # Example:
+/// ```text
+/// # tables used when a command's table list is omitted, urls or registry ids
+/// tables = ["satellite", "stella"]
+/// # defaults to $XDG_CACHE_HOME/rbmstable
+/// cache_dir = "/var/cache/rbmstable"
+/// # how long a parsed table is reused before being downloaded again
+/// cache_ttl = "6h"
+/// # used for both http and https requests
+/// proxy = "http://127.0.0.1:7890"
+/// # sent with every request, defaults to the library's user agent
+/// user_agent = "my-table-mirror/1.0 (admin@example.com)"
+/// # "sorted" (the default) or "appearance", to keep levels in the order they first appear in the body
+/// level_order = "appearance"
+/// ```
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub tables: Vec<String>,
+    pub cache_dir: Option<PathBuf>,
+    pub cache_ttl: Option<String>,
+    pub proxy: Option<String>,
+    pub user_agent: Option<String>,
+    pub level_order: LevelOrder,
+}
+
+/// How parsed tables' levels are ordered, see [`LevelOrdering`]
+#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LevelOrder {
+    #[default]
+    Sorted,
+    Appearance,
+}
+
+impl Config {
+    /// Load the config file, a missing file is the same as an empty one
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        let path = match env::var_os("RBMSTABLE_CONFIG") {
+            Some(path) => PathBuf::from(path),
+            None => config_dir().join("config.toml"),
+        };
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+        let data = fs::read_to_string(&path)?;
+        toml::from_str(&data).map_err(|e| format!("cannot load {}: {e}", path.display()).into())
+    }
+
+    pub fn cache_dir(&self) -> PathBuf {
+        self.cache_dir
+            .clone()
+            .unwrap_or_else(|| xdg_dir("XDG_CACHE_HOME", ".cache").join(APP_DIR))
+    }
+
+    /// Options every table is parsed with
+    pub fn parse_options(&self) -> ParseOptions {
+        ParseOptions {
+            level_ordering: match self.level_order {
+                LevelOrder::Sorted => LevelOrdering::Sorted,
+                LevelOrder::Appearance => LevelOrdering::Appearance,
+            },
+            ..Default::default()
+        }
+    }
+
+    /// User agent of every request
+    pub fn user_agent(&self) -> &str {
+        self.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT)
+    }
+
+    /// Parsed tables are reused for an hour unless configured otherwise
+    pub fn cache_ttl(&self) -> Result<Duration, String> {
+        match &self.cache_ttl {
+            Some(ttl) => parse_interval(ttl),
+            None => Ok(Duration::from_secs(60 * 60)),
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/rbmstable`
+pub fn config_dir() -> PathBuf {
+    xdg_dir("XDG_CONFIG_HOME", ".config").join(APP_DIR)
+}
+
+/// XDG base directory from `var`, or its default under the home directory
+fn xdg_dir(var: &str, home_default: &str) -> PathBuf {
+    match env::var_os(var) {
+        Some(dir) if Path::new(&dir).is_absolute() => PathBuf::from(dir),
+        _ => env::var_os("HOME")
+            .map(PathBuf::from)
+            .unwrap_or_default()
+            .join(home_default),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn should_parse_interval() {
+        assert_eq!(parse_interval("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_interval("1h"), Ok(Duration::from_secs(3600)));
+        assert!(parse_interval("h").is_err());
+        assert!(parse_interval("1w").is_err());
+    }
+
+    #[test]
+    pub fn should_reject_unknown_keys() {
+        let config: Config = toml::from_str(
+            r#"
+            tables = ["satellite"]
+            cache_ttl = "1d"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.tables, ["satellite"]);
+        assert_eq!(config.cache_ttl(), Ok(Duration::from_secs(24 * 60 * 60)));
+        assert!(toml::from_str::<Config>("cache = true").is_err());
+
+        let config: Config = toml::from_str(r#"level_order = "appearance""#).unwrap();
+        assert_eq!(
+            config.parse_options().level_ordering,
+            LevelOrdering::Appearance
+        );
+        assert!(toml::from_str::<Config>(r#"level_order = "random""#).is_err());
+
+        assert_eq!(Config::default().user_agent(), DEFAULT_USER_AGENT);
+        let config: Config = toml::from_str(r#"user_agent = "mirror/1.0""#).unwrap();
+        assert_eq!(config.user_agent(), "mirror/1.0");
+    }
+}
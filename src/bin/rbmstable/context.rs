@@ -0,0 +1,121 @@
+use std::{collections::HashMap, error::Error, fs, path::PathBuf, time::SystemTime};
+
+use rbmstable_parser::{DifficultTable, Parser, Registry, TableId};
+
+use crate::config::{self, Config};
+
+/// State shared by every subcommand: settings, known tables and the parsed table cache
+pub struct Context {
+    pub config: Config,
+    pub registry: Registry,
+    /// Local registry extension file
+    pub registry_path: PathBuf,
+    /// Ignore cached tables and download everything again
+    pub refresh: bool,
+    /// Shared by every download, configured with the proxy, user agent and parse settings
+    pub parser: Parser,
+}
+
+impl Context {
+    pub fn load(registry_path: Option<PathBuf>, refresh: bool) -> Result<Self, Box<dyn Error>> {
+        let config = Config::load()?;
+        let mut parser = Parser::builder()
+            .user_agent(config.user_agent())
+            .options(config.parse_options());
+        if let Some(proxy) = &config.proxy {
+            parser = parser.proxy(proxy);
+        }
+        let parser = parser.build()?;
+        let registry_path =
+            registry_path.unwrap_or_else(|| config::config_dir().join("registry.json"));
+        let mut registry = Registry::builtin();
+        if registry_path.exists() {
+            registry
+                .load_extension(&registry_path)
+                .map_err(|e| format!("cannot load {}: {e}", registry_path.display()))?;
+        }
+        Ok(Context {
+            config,
+            registry,
+            registry_path,
+            refresh,
+            parser,
+        })
+    }
+
+    /// Resolve a table given on the command line, which is either a registry id or an url
+    pub fn resolve_url(&self, table: &str) -> String {
+        match self.registry.get(table) {
+            Some(known) => known.url.clone(),
+            None => table.to_string(),
+        }
+    }
+
+    /// Tables given on the command line, or the configured default tables
+    pub fn tables_or_default<'a>(&'a self, tables: &'a [String]) -> &'a [String] {
+        if tables.is_empty() {
+            &self.config.tables
+        } else {
+            tables
+        }
+    }
+
+    /// Resolve and parse a table, reusing the cached copy when it's fresh enough
+    ///
+    /// Cached tables are stored under their [`DifficultTable::id`], a registry table's id being its registry id.
+    /// Urls are mapped to the id of the table they gave in `ids.json`, next to the cached tables. Returns the
+    /// resolved url alongside the table
+    pub fn parse(&self, table: &str) -> Result<(String, DifficultTable), Box<dyn Error>> {
+        let known_id = self.registry.get(table).map(|known| known.id.clone());
+        let url = self.resolve_url(table);
+        let cache_dir = self.config.cache_dir().join("tables");
+        let ids_path = cache_dir.join("ids.json");
+        let mut ids: HashMap<String, TableId> = fs::read_to_string(&ids_path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        let cached_id = known_id.clone().or_else(|| ids.get(&url).cloned());
+        if let Some(id) = cached_id.filter(|_| !self.refresh) {
+            if let Some(mut table) = self.load_cached(&cache_dir.join(format!("{id}.json")))? {
+                table.set_id(id);
+                return Ok((url, table));
+            }
+        }
+        let mut table = self
+            .parser
+            .parse(&url)
+            .map_err(|e| format!("cannot parse {url}: {e}"))?;
+        if let Some(id) = known_id {
+            table.set_id(id);
+        }
+        ids.insert(url.clone(), table.id());
+        // Failing to cache shouldn't fail the command
+        let cached = fs::create_dir_all(&cache_dir).and_then(|_| {
+            let cache_path = cache_dir.join(format!("{}.json", table.id()));
+            fs::write(cache_path, serde_json::to_string(&table)?)?;
+            fs::write(&ids_path, serde_json::to_string(&ids)?)
+        });
+        if let Err(e) = cached {
+            eprintln!("warning: cannot cache {url}: {e}");
+        }
+        Ok((url, table))
+    }
+
+    /// Cached copy of a table if it's fresh enough
+    ///
+    /// A copy that can't be read or decoded (e.g. written by an older version) is a cache miss
+    fn load_cached(&self, path: &PathBuf) -> Result<Option<DifficultTable>, Box<dyn Error>> {
+        let Ok(modified) = fs::metadata(path).and_then(|meta| meta.modified()) else {
+            return Ok(None);
+        };
+        let age = SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default();
+        if age > self.config.cache_ttl()? {
+            return Ok(None);
+        }
+        Ok(fs::read_to_string(path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok()))
+    }
+}
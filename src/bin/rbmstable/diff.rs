@@ -0,0 +1,27 @@
+use std::process::ExitCode;
+
+use clap::Args;
+use rbmstable_parser::diff;
+
+use crate::{context::Context, CliResult};
+
+#[derive(Args)]
+pub struct DiffArgs {
+    /// Old difficult table url or registry id
+    old: String,
+    /// New difficult table url or registry id
+    new: String,
+}
+
+/// Exits with 1 when the tables differ, like diff(1)
+pub fn run(args: DiffArgs, ctx: &Context) -> CliResult {
+    let (_, old) = ctx.parse(&args.old)?;
+    let (_, new) = ctx.parse(&args.new)?;
+    let changes = diff(&old, &new);
+    if changes.is_empty() {
+        println!("[{}] no changes", new.name);
+        return Ok(ExitCode::SUCCESS);
+    }
+    print!("{}", changes.report(&new));
+    Ok(ExitCode::FAILURE)
+}
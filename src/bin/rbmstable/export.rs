@@ -0,0 +1,94 @@
+use std::{
+    fs::File,
+    io::{self, Write as _},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use clap::{Args, ValueEnum};
+
+use crate::{context::Context, CliResult};
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// Difficult table url or registry id
+    table: String,
+    /// Export format
+    #[arg(long, alias = "format")]
+    to: ExportFormat,
+    /// Output file, prints to stdout when omitted
+    #[arg(long)]
+    out: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    /// One row per song
+    Csv,
+    /// The body json, i.e. every song
+    Json,
+    /// SQLite database, written into --out (which is required)
+    Sqlite,
+    /// The table's courses as a beatoraja course file, for its `course` directory
+    BeatorajaCourse,
+}
+
+pub fn run(args: ExportArgs, ctx: &Context) -> CliResult {
+    let (_, table) = ctx.parse(&args.table)?;
+    match args.to {
+        ExportFormat::Csv => table.to_csv(writer(&args.out)?)?,
+        ExportFormat::Json => writeln!(writer(&args.out)?, "{}", table.to_body_json())?,
+        ExportFormat::Sqlite => {
+            let out = args.out.ok_or("--out is required to export to sqlite")?;
+            table.write_sqlite(&mut rusqlite::Connection::open(out)?)?;
+        }
+        ExportFormat::BeatorajaCourse => writeln!(
+            writer(&args.out)?,
+            "{}",
+            serde_json::to_string(&table.beatoraja_courses())?
+        )?,
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn writer(out: &Option<PathBuf>) -> io::Result<Box<dyn io::Write>> {
+    Ok(match out {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout().lock()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rbmstable_parser::DifficultTable;
+    use serde_json::json;
+
+    #[test]
+    pub fn should_quote_csv_fields() {
+        let mut table: DifficultTable = serde_json::from_value(json!({
+            "name": "Satellite",
+            "symbol": "sl",
+            "data_url": "body.json",
+        }))
+        .unwrap();
+        table.contents = vec![serde_json::from_value(json!({
+            "title": "Hello, \"World\"",
+            "artist": "someone",
+            "md5": "00000000000000000000000000000000",
+            "level": "1",
+        }))
+        .unwrap()];
+        let mut out = Vec::new();
+        table.to_csv(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let rows: Vec<&str> = out.lines().collect();
+        assert_eq!(
+            rows[0],
+            "level,title,artist,md5,sha256,mode,diff_name,info,bms_id"
+        );
+        assert_eq!(
+            rows[1],
+            r#"1,"Hello, ""World""",someone,00000000000000000000000000000000,,,,,"#
+        );
+    }
+}
@@ -0,0 +1,40 @@
+use std::process::ExitCode;
+
+use clap::Args;
+
+use crate::{context::Context, CliResult};
+
+#[derive(Args)]
+pub struct FetchArgs {
+    /// Difficult table url or registry id
+    table: String,
+    /// Print the whole parsed table as json instead of a summary
+    #[arg(long)]
+    json: bool,
+}
+
+pub fn run(args: FetchArgs, ctx: &Context) -> CliResult {
+    let (url, table) = ctx.parse(&args.table)?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&table)?);
+        return Ok(ExitCode::SUCCESS);
+    }
+    println!("{} ({})", table.name, table.symbol);
+    println!("url: {url}");
+    if !table.last_update.is_empty() {
+        println!("last update: {}", table.last_update);
+    }
+    println!(
+        "{} song(s), {} level(s), {} course(s)",
+        table.contents.len(),
+        table.levels.len(),
+        table.courses.len()
+    );
+    for (level, elements) in table.contents_by_level() {
+        println!("  {}{level}: {}", table.symbol, elements.len());
+    }
+    for warning in &table.warnings {
+        println!("warning: {warning}");
+    }
+    Ok(ExitCode::SUCCESS)
+}
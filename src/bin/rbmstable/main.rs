@@ -0,0 +1,90 @@
+use std::{error::Error, path::PathBuf, process::ExitCode};
+
+use clap::{Parser, Subcommand};
+use context::Context;
+
+mod config;
+mod context;
+mod diff;
+mod export;
+mod fetch;
+mod matching;
+mod merge;
+mod registry;
+mod search;
+mod validate;
+mod watch;
+
+/// Result type shared by every subcommand
+///
+/// Subcommands report "soft" failures (e.g. validation findings) through the exit code, hard failures
+/// (e.g. unreadable files) through the error
+pub type CliResult = Result<ExitCode, Box<dyn Error>>;
+
+/// Command line companion of rbmstable-parser
+///
+/// Settings are read from $XDG_CONFIG_HOME/rbmstable/config.toml (or $RBMSTABLE_CONFIG), parsed tables are
+/// cached under $XDG_CACHE_HOME/rbmstable
+#[derive(Parser)]
+#[command(name = "rbmstable", version, about, long_about)]
+struct Cli {
+    /// Local registry extension file, defaults to $XDG_CONFIG_HOME/rbmstable/registry.json
+    #[arg(long, global = true)]
+    registry: Option<PathBuf>,
+    /// Download tables again instead of reusing cached ones
+    #[arg(long, global = true)]
+    refresh: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Fetch a difficult table and print a summary of it
+    Fetch(fetch::FetchArgs),
+    /// Validate a difficult table's header.json (and body.json) before publishing it
+    Validate(validate::ValidateArgs),
+    /// Merge several difficult tables into one hostable header.json + body.json
+    Merge(merge::MergeArgs),
+    /// Search songs by title across several difficult tables
+    Search(search::SearchArgs),
+    /// Print the songs added, removed and moved between two difficult tables
+    Diff(diff::DiffArgs),
+    /// Export a difficult table's contents to another format
+    Export(export::ExportArgs),
+    /// Poll difficult tables periodically and report what changed
+    Watch(watch::WatchArgs),
+    /// Inspect known difficult tables and manage the local registry extension file
+    Registry(registry::RegistryArgs),
+    /// Report which songs of a difficult table are in the local song library
+    Match(matching::MatchArgs),
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let ctx = match Context::load(cli.registry, cli.refresh) {
+        Ok(ctx) => ctx,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let result = match cli.command {
+        Command::Fetch(args) => fetch::run(args, &ctx),
+        Command::Validate(args) => validate::run(args, &ctx),
+        Command::Merge(args) => merge::run(args, &ctx),
+        Command::Search(args) => search::run(args, &ctx),
+        Command::Diff(args) => diff::run(args, &ctx),
+        Command::Export(args) => export::run(args, &ctx),
+        Command::Watch(args) => watch::run(args, &ctx),
+        Command::Registry(args) => registry::run(args, &ctx),
+        Command::Match(args) => matching::run(args, &ctx),
+    };
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use std::{
+    collections::HashSet,
+    fs,
+    io::{self, Write as _},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::Args;
+use md5::{Digest, Md5 as Md5Hasher};
+use rbmstable_parser::{DifficultTable, DifficultTableElement, Level, Md5, Sha256};
+use sha2::Sha256 as Sha256Hasher;
+
+use crate::{context::Context, CliResult};
+
+/// Chart file extensions scanned in the song folders
+const CHART_EXTENSIONS: [&str; 5] = ["bms", "bme", "bml", "pms", "bmson"];
+
+#[derive(Args)]
+pub struct MatchArgs {
+    /// Difficult table url or registry id
+    table: String,
+    /// Song folder scanned recursively for chart files, could be repeated
+    #[arg(long)]
+    songs: Vec<PathBuf>,
+    /// beatoraja's songdata.db
+    #[arg(long)]
+    db: Option<PathBuf>,
+    /// Print every missing song instead of only the per level summary
+    #[arg(long)]
+    verbose: bool,
+    /// Write the missing songs (level, md5, title) to this file
+    #[arg(long)]
+    missing: Option<PathBuf>,
+    /// Write the download urls of the missing songs to this file, one per line
+    ///
+    /// A song's package (url_pack) is preferred to its own url, a package shared by several songs is listed once
+    #[arg(long)]
+    download_list: Option<PathBuf>,
+}
+
+/// Hashes of every chart the user owns
+#[derive(Default)]
+struct Library {
+    md5: HashSet<Md5>,
+    sha256: HashSet<Sha256>,
+}
+
+impl Library {
+    fn owns(&self, element: &DifficultTableElement) -> bool {
+        element
+            .sha256_hash()
+            .is_some_and(|sha256| self.sha256.contains(&sha256))
+            || element
+                .md5_hash()
+                .is_some_and(|md5| self.md5.contains(&md5))
+    }
+
+    fn scan(&mut self, dir: &Path) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                self.scan(&path)?;
+                continue;
+            }
+            let is_chart = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| CHART_EXTENSIONS.contains(&ext.to_lowercase().as_str()));
+            if is_chart {
+                let data = fs::read(&path)?;
+                self.md5
+                    .insert(Md5::from_bytes(Md5Hasher::digest(&data).into()));
+                self.sha256
+                    .insert(Sha256::from_bytes(Sha256Hasher::digest(&data).into()));
+            }
+        }
+        Ok(())
+    }
+
+    fn load_songdata(&mut self, db: &Path) -> rusqlite::Result<()> {
+        let conn =
+            rusqlite::Connection::open_with_flags(db, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let mut stmt = conn.prepare("SELECT md5, sha256 FROM song")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, Option<String>>(0)?,
+                row.get::<_, Option<String>>(1)?,
+            ))
+        })?;
+        for row in rows {
+            let (md5, sha256) = row?;
+            self.md5.extend(md5.and_then(|md5| md5.parse::<Md5>().ok()));
+            self.sha256
+                .extend(sha256.and_then(|sha256| sha256.parse::<Sha256>().ok()));
+        }
+        Ok(())
+    }
+}
+
+/// Owned and missing songs of one level
+struct LevelReport<'a> {
+    level: &'a Level,
+    owned: usize,
+    missing: Vec<&'a DifficultTableElement>,
+}
+
+pub fn run(args: MatchArgs, ctx: &Context) -> CliResult {
+    if args.songs.is_empty() && args.db.is_none() {
+        return Err("at least one of --songs or --db is required".into());
+    }
+    let (_, table) = ctx.parse(&args.table)?;
+    let mut library = Library::default();
+    for dir in &args.songs {
+        library
+            .scan(dir)
+            .map_err(|e| format!("cannot scan {}: {e}", dir.display()))?;
+    }
+    if let Some(db) = &args.db {
+        library
+            .load_songdata(db)
+            .map_err(|e| format!("cannot read {}: {e}", db.display()))?;
+    }
+
+    let reports = match_levels(&table, &library);
+    let symbol = &table.symbol;
+    for report in &reports {
+        let total = report.owned + report.missing.len();
+        println!("{symbol}{}: {}/{total} owned", report.level, report.owned);
+        if args.verbose {
+            for element in &report.missing {
+                println!(
+                    "    {} / {} ({})",
+                    element.title, element.artist, element.md5
+                );
+            }
+        }
+    }
+    let owned: usize = reports.iter().map(|report| report.owned).sum();
+    println!("[{}] {owned}/{} owned", table.name, table.contents.len());
+    if let Some(path) = &args.missing {
+        let mut out = io::BufWriter::new(fs::File::create(path)?);
+        for report in &reports {
+            for element in &report.missing {
+                writeln!(
+                    out,
+                    "{symbol}{}\t{}\t{}",
+                    report.level, element.md5, element.title
+                )?;
+            }
+        }
+    }
+    if let Some(path) = &args.download_list {
+        let mut out = io::BufWriter::new(fs::File::create(path)?);
+        for url in download_list(&reports) {
+            writeln!(out, "{url}")?;
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Download urls of every missing song, in table order without duplicates
+///
+/// Songs with neither a url_pack nor a url are left out
+fn download_list<'a>(reports: &[LevelReport<'a>]) -> Vec<&'a str> {
+    let mut seen = HashSet::new();
+    reports
+        .iter()
+        .flat_map(|report| &report.missing)
+        .filter_map(|element| {
+            [&element.url_pack, &element.url]
+                .into_iter()
+                .find(|url| !url.is_empty())
+        })
+        .map(String::as_str)
+        .filter(|url| seen.insert(*url))
+        .collect()
+}
+
+/// Group table contents by level (in `levels` order) and split them into owned and missing ones
+fn match_levels<'a>(table: &'a DifficultTable, library: &Library) -> Vec<LevelReport<'a>> {
+    table
+        .contents_by_level()
+        .into_iter()
+        .map(|(level, elements)| {
+            let mut report = LevelReport {
+                level,
+                owned: 0,
+                missing: Vec::new(),
+            };
+            for element in elements {
+                if library.owns(element) {
+                    report.owned += 1;
+                } else {
+                    report.missing.push(element);
+                }
+            }
+            report
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    pub fn should_match_by_sha256_or_md5() {
+        let mut table: DifficultTable = serde_json::from_value(json!({
+            "name": "Satellite",
+            "symbol": "sl",
+            "data_url": "body.json",
+        }))
+        .unwrap();
+        let (a, b, c) = ("a".repeat(32), "b".repeat(64), "c".repeat(32));
+        table.contents = [
+            (a.as_str(), "", "0"),
+            ("", b.as_str(), "0"),
+            (c.as_str(), "", "1"),
+        ]
+        .iter()
+        .map(|(md5, sha256, level)| {
+            serde_json::from_value(json!({
+                "title": "",
+                "artist": "",
+                "md5": md5,
+                "sha256": sha256,
+                "level": level,
+            }))
+            .unwrap()
+        })
+        .collect();
+        table.levels = vec![Level::from("0"), Level::from("1")];
+        let library = Library {
+            md5: HashSet::from([a.to_uppercase().parse().unwrap()]),
+            sha256: HashSet::from([b.parse().unwrap()]),
+        };
+        let reports = match_levels(&table, &library);
+        assert_eq!(reports[0].owned, 2);
+        assert_eq!(reports[1].owned, 0);
+        assert_eq!(reports[1].missing[0].md5, c);
+    }
+
+    #[test]
+    pub fn should_list_each_package_once() {
+        let mut table: DifficultTable = serde_json::from_value(json!({
+            "name": "Satellite",
+            "symbol": "sl",
+            "data_url": "body.json",
+        }))
+        .unwrap();
+        table.contents = [
+            ("pack.zip", "a.zip"),
+            ("pack.zip", "b.zip"),
+            ("", "c.zip"),
+            ("", ""),
+        ]
+        .iter()
+        .map(|(url_pack, url)| {
+            serde_json::from_value(json!({
+                "title": "",
+                "artist": "",
+                "md5": "",
+                "level": "0",
+                "url": url,
+                "url_pack": url_pack,
+            }))
+            .unwrap()
+        })
+        .collect();
+        table.levels = vec![Level::from("0")];
+        let reports = match_levels(&table, &Library::default());
+        assert_eq!(download_list(&reports), ["pack.zip", "c.zip"]);
+    }
+}
@@ -0,0 +1,194 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use clap::{Args, ValueEnum};
+use rbmstable_parser::{DifficultTable, MergeStrategy};
+
+use crate::{context::Context, CliResult};
+
+#[derive(Args)]
+pub struct MergeArgs {
+    /// Difficult table urls or registry ids
+    #[arg(required = true)]
+    tables: Vec<String>,
+    /// Directory the merged table.html, header.json and body.json are written to
+    ///
+    /// Every song of body.json names the id of the table it was taken from in a `source_table` field
+    #[arg(long)]
+    out: PathBuf,
+    /// Merged table name, defaults to the source names joined by " + "
+    #[arg(long)]
+    name: Option<String>,
+    /// Merged table symbol, defaults to the source symbols concatenated
+    #[arg(long)]
+    symbol: Option<String>,
+    /// What to do with a song listed by several tables
+    #[arg(long, value_enum, default_value_t = Strategy::KeepBoth)]
+    strategy: Strategy,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Strategy {
+    /// Keep the song of the first table listing it
+    PreferLeft,
+    /// Keep the song of the last table listing it
+    PreferRight,
+    /// Keep every song, levels are prefixed by their table's symbol (e.g. `sl3`)
+    KeepBoth,
+}
+
+impl From<Strategy> for MergeStrategy {
+    fn from(strategy: Strategy) -> Self {
+        match strategy {
+            Strategy::PreferLeft => MergeStrategy::PreferLeft,
+            Strategy::PreferRight => MergeStrategy::PreferRight,
+            Strategy::KeepBoth => MergeStrategy::KeepBoth,
+        }
+    }
+}
+
+pub fn run(args: MergeArgs, ctx: &Context) -> CliResult {
+    let mut tables = Vec::with_capacity(args.tables.len());
+    for table in &args.tables {
+        tables.push(ctx.parse(table)?.1);
+    }
+    let mut merged = merge_tables(&tables, args.strategy.into());
+    if let Some(name) = args.name {
+        merged.name = name;
+    }
+    if let Some(symbol) = args.symbol {
+        merged.symbol = symbol;
+    }
+
+    merged.write_table_dir(&args.out)?;
+    println!(
+        "merged {} table(s), {} song(s) into {}",
+        tables.len(),
+        merged.contents.len(),
+        args.out.display()
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Element field naming the table a merged song was taken from, see [`DifficultTable::id`]
+const SOURCE_FIELD: &str = "source_table";
+
+/// Fold tables into one through [`DifficultTable::merge`], named after every source table
+///
+/// Every song records its source table in [`SOURCE_FIELD`]
+fn merge_tables(tables: &[DifficultTable], strategy: MergeStrategy) -> DifficultTable {
+    let sources: Vec<DifficultTable> = tables.iter().map(with_source).collect();
+    let (first, mut rest) = sources
+        .split_first()
+        .expect("at least one table is required");
+    let mut merged = first.clone();
+    if strategy == MergeStrategy::KeepBoth {
+        // Only the first header is kept and every table is merged into it, so that levels are prefixed once,
+        // a single table's included
+        merged.retain_elements(|_| false);
+        merged.courses.clear();
+        merged.symbol.clear();
+        rest = &sources;
+    }
+    for table in rest {
+        merged = merged.merge(table, strategy);
+    }
+    merged.name = tables
+        .iter()
+        .map(|table| table.name.as_str())
+        .collect::<Vec<_>>()
+        .join(" + ");
+    merged.symbol = tables.iter().map(|table| table.symbol.as_str()).collect();
+    merged
+}
+
+fn with_source(table: &DifficultTable) -> DifficultTable {
+    let id = table.id();
+    let mut table = table.clone();
+    for element in &mut table.contents {
+        element
+            .extra
+            .insert(SOURCE_FIELD.to_string(), id.as_str().into());
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use rbmstable_parser::{DifficultTableElement, Level};
+    use serde_json::json;
+
+    use super::*;
+
+    fn table(name: &str, symbol: &str, levels: &[&str]) -> DifficultTable {
+        let header = serde_json::from_value(json!({
+            "name": name,
+            "symbol": symbol,
+            "data_url": "body.json",
+        }))
+        .unwrap();
+        let contents = levels
+            .iter()
+            .map(|level| {
+                serde_json::from_value::<DifficultTableElement>(json!({
+                    "title": format!("{name}{level}"),
+                    "artist": "",
+                    "md5": format!("{name}{level}"),
+                    "level": level,
+                }))
+                .unwrap()
+            })
+            .collect();
+        DifficultTable::from_parts(header, contents, Vec::new())
+    }
+
+    #[test]
+    pub fn should_prefix_levels_with_symbol() {
+        let tables = [
+            table("Satellite", "sl", &["0", "1"]),
+            table("Insane", "★", &["1"]),
+            table("Overjoy", "★★", &["1"]),
+        ];
+        let merged = merge_tables(&tables, MergeStrategy::KeepBoth);
+        assert_eq!(merged.name, "Satellite + Insane + Overjoy");
+        assert_eq!(merged.symbol, "sl★★★");
+        let levels: Vec<String> = merged
+            .contents
+            .iter()
+            .map(|e| e.level.to_string())
+            .collect();
+        assert_eq!(levels, ["sl0", "sl1", "★1", "★★1"]);
+        let sources: Vec<&str> = merged
+            .contents
+            .iter()
+            .map(|e| e.extra[SOURCE_FIELD].as_str().unwrap())
+            .collect();
+        let ids = tables.each_ref().map(DifficultTable::id);
+        assert_eq!(
+            sources,
+            [&ids[0], &ids[0], &ids[1], &ids[2]].map(|id| id.as_str())
+        );
+
+        let single = merge_tables(&tables[..1], MergeStrategy::KeepBoth);
+        assert_eq!(single.levels, ["sl0", "sl1"].map(Level::from));
+        assert_eq!(single.contents[0].level, "sl0");
+    }
+
+    #[test]
+    pub fn should_merge_duplicates_by_strategy() {
+        let tables = [
+            table("Satellite", "sl", &["0", "1"]),
+            table("Satellite", "st", &["0"]),
+        ];
+        let merged = merge_tables(&tables, MergeStrategy::PreferLeft);
+        assert_eq!(merged.contents.len(), 2);
+        assert_eq!(
+            merged.contents[0].extra[SOURCE_FIELD],
+            tables[0].id().as_str()
+        );
+        let merged = merge_tables(&tables, MergeStrategy::PreferRight);
+        assert_eq!(
+            merged.contents[0].extra[SOURCE_FIELD],
+            tables[1].id().as_str()
+        );
+    }
+}
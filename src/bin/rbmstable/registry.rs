@@ -0,0 +1,75 @@
+use std::{fs, process::ExitCode};
+
+use clap::{Args, Subcommand};
+use rbmstable_parser::{KnownTable, TableId};
+
+use crate::{context::Context, CliResult};
+
+#[derive(Args)]
+pub struct RegistryArgs {
+    #[command(subcommand)]
+    command: RegistryCommand,
+}
+
+#[derive(Subcommand)]
+enum RegistryCommand {
+    /// List every known table
+    List,
+    /// Show one known table
+    Show {
+        /// Registry id, e.g. `satellite`
+        id: String,
+    },
+    /// Parse a table and remember it in the local registry extension file
+    Add {
+        /// Difficult table url
+        url: String,
+        /// Id the table is registered as, defaults to the table's derived id
+        #[arg(long)]
+        id: Option<String>,
+    },
+}
+
+pub fn run(args: RegistryArgs, ctx: &Context) -> CliResult {
+    let (registry, extension) = (&ctx.registry, ctx.registry_path.as_path());
+    match args.command {
+        RegistryCommand::List => {
+            for table in registry.tables() {
+                println!("{:<16} {} ({})", table.id, table.name, table.symbol);
+            }
+        }
+        RegistryCommand::Show { id } => {
+            let Some(table) = registry.get(&id) else {
+                eprintln!("error: no table is registered as `{id}`");
+                return Ok(ExitCode::FAILURE);
+            };
+            println!("id:     {}", table.id);
+            println!("name:   {}", table.name);
+            println!("symbol: {}", table.symbol);
+            println!("url:    {}", table.url);
+        }
+        RegistryCommand::Add { url, id } => {
+            let (url, dth) = ctx.parse(&url)?;
+            let table = KnownTable {
+                id: id.map_or_else(|| dth.id(), TableId::from),
+                name: dth.name,
+                symbol: dth.symbol,
+                url,
+            };
+            // Only the extension file is rewritten, built-in tables stay out of it
+            let mut tables: Vec<KnownTable> = if extension.exists() {
+                serde_json::from_str(&fs::read_to_string(extension)?)?
+            } else {
+                Vec::new()
+            };
+            tables.retain(|known| known.id != table.id);
+            println!("registered {} as `{}`", table.name, table.id);
+            tables.push(table);
+            if let Some(dir) = extension.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(extension, serde_json::to_string_pretty(&tables)?)?;
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
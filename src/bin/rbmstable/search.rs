@@ -0,0 +1,166 @@
+use std::process::ExitCode;
+
+use clap::Args;
+use rbmstable_parser::{DifficultTable, DifficultTableElement, Md5, Sha256, TableSet};
+
+use crate::{context::Context, CliResult};
+
+/// Minimal jaro-winkler similarity for a title to be considered as a fuzzy match
+const FUZZY_THRESHOLD: f64 = 0.85;
+
+#[derive(Args)]
+pub struct SearchArgs {
+    /// Title (or artist) fragment to look for, or a chart's md5 or sha256
+    query: String,
+    /// Comma separated difficult table urls or registry ids to search in, defaults to the configured tables
+    #[arg(long, value_delimiter = ',')]
+    tables: Vec<String>,
+    /// Maximum number of printed results
+    #[arg(long, default_value_t = 50)]
+    limit: usize,
+}
+
+/// One search hit, higher score is better
+struct Hit<'a> {
+    score: f64,
+    table: &'a DifficultTable,
+    element: &'a DifficultTableElement,
+}
+
+pub fn run(args: SearchArgs, ctx: &Context) -> CliResult {
+    let table_args = ctx.tables_or_default(&args.tables);
+    if table_args.is_empty() {
+        return Err("no table to search in, pass --tables or configure default tables".into());
+    }
+    let mut tables = TableSet::new();
+    for table in table_args {
+        let (url, table) = ctx.parse(table)?;
+        tables.insert(url, table);
+    }
+    let hits = search(&args.query, &tables);
+    if hits.is_empty() {
+        println!("no song matches `{}`", args.query);
+        return Ok(ExitCode::FAILURE);
+    }
+    for hit in hits.iter().take(args.limit) {
+        let element = hit.element;
+        println!(
+            "[{}] {}{} {} / {}",
+            hit.table.name, hit.table.symbol, element.level, element.title, element.artist
+        );
+        println!("    md5: {}", element.md5);
+        println!("    LR2IR: {}", lr2ir_url(&element.md5));
+        if !element.sha256.is_empty() {
+            println!("    Mocha: {}", mocha_url(&element.sha256));
+        }
+    }
+    if hits.len() > args.limit {
+        println!("... and {} more", hits.len() - args.limit);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Search every table's contents, best matches come first
+///
+/// A query that is an md5 or a sha256 looks the chart up by hash. Otherwise a song matches when its normalized
+/// title/artist contains the normalized query, or when its title is similar enough to the query (see
+/// [`FUZZY_THRESHOLD`])
+fn search<'a>(query: &str, tables: &'a TableSet) -> Vec<Hit<'a>> {
+    let query = query.trim();
+    let (sha256, md5) = (query.parse::<Sha256>().ok(), query.parse::<Md5>().ok());
+    if sha256.is_some() || md5.is_some() {
+        return tables
+            .find_by_hash(sha256, md5)
+            .into_iter()
+            .map(|(table, element)| Hit {
+                score: 2.0,
+                table,
+                element,
+            })
+            .collect();
+    }
+    let query = &normalize(query);
+    let mut hits: Vec<Hit> = tables
+        .iter()
+        .flat_map(|(_, table)| {
+            table.contents.iter().map(move |element| Hit {
+                score: score(query, element),
+                table,
+                element,
+            })
+        })
+        .filter(|hit| hit.score >= FUZZY_THRESHOLD)
+        .collect();
+    hits.sort_by(|lhs, rhs| rhs.score.total_cmp(&lhs.score));
+    hits
+}
+
+fn score(query: &str, element: &DifficultTableElement) -> f64 {
+    let title = normalize(&element.title);
+    if title.contains(query) {
+        return 2.0;
+    }
+    if normalize(&element.artist).contains(query) {
+        return 1.5;
+    }
+    strsim::jaro_winkler(query, &title)
+}
+
+/// Lowercase and drop whitespace, so "Air Blue" matches "AIRBLUE"
+fn normalize(s: &str) -> String {
+    s.chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn lr2ir_url(md5: &str) -> String {
+    format!("http://www.dream-pro.info/~lavalse/LR2IR/search.cgi?mode=ranking&bmsmd5={md5}")
+}
+
+fn mocha_url(sha256: &str) -> String {
+    format!("https://mocha-repository.info/song.php?sha256={sha256}")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    pub fn should_match_fragment_and_typo() {
+        let mut table: DifficultTable = serde_json::from_value(json!({
+            "name": "Satellite",
+            "symbol": "sl",
+            "data_url": "body.json",
+        }))
+        .unwrap();
+        table.contents = ["Air Blue", "Airblue Remix", "Evans", "Evens"]
+            .iter()
+            .zip(["a", "b", "c", "d"])
+            .map(|(title, md5)| {
+                serde_json::from_value(json!({
+                    "title": title,
+                    "artist": "someone",
+                    "md5": md5.repeat(32),
+                    "level": "1",
+                }))
+                .unwrap()
+            })
+            .collect();
+        let mut tables = TableSet::new();
+        tables.insert("body.json", table);
+
+        let titles = |query: &str| -> Vec<String> {
+            search(query, &tables)
+                .iter()
+                .map(|hit| hit.element.title.clone())
+                .collect()
+        };
+        assert_eq!(titles("AIRBLUE"), ["Air Blue", "Airblue Remix"]);
+        assert_eq!(titles("evans"), ["Evans", "Evens"]);
+        assert!(titles("not a song").is_empty());
+        assert_eq!(titles(&"C".repeat(32)), ["Evans"]);
+    }
+}
@@ -0,0 +1,235 @@
+use std::{fmt, fs, path::PathBuf, process::ExitCode};
+
+use clap::Args;
+use rbmstable_parser::{lint, DifficultTable, DifficultTableElement, LintLocation, Severity};
+use serde_json::{json, Value};
+
+use crate::{context::Context, CliResult};
+
+#[derive(Args)]
+pub struct ValidateArgs {
+    /// Path to the difficult table header json, or the url of a published table
+    header: PathBuf,
+    /// Path to the difficult table body json
+    ///
+    /// When omitted, the body is looked up next to the header by its relative data_url
+    body: Option<PathBuf>,
+}
+
+/// One problem found in the header or body
+#[derive(Debug)]
+struct Finding {
+    severity: Severity,
+    /// Where the problem is, e.g. `header` or `body[12]`
+    location: String,
+    message: String,
+}
+
+impl Finding {
+    fn error(location: impl Into<String>, message: impl Into<String>) -> Self {
+        Finding {
+            severity: Severity::Error,
+            location: location.into(),
+            message: message.into(),
+        }
+    }
+
+    fn warning(location: impl Into<String>, message: impl Into<String>) -> Self {
+        Finding {
+            severity: Severity::Warning,
+            location: location.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.severity, self.location, self.message)
+    }
+}
+
+pub fn run(args: ValidateArgs, ctx: &Context) -> CliResult {
+    let mut findings = match args.header.to_str() {
+        Some(url) if !args.header.exists() && url.starts_with("http") => check_url(url, ctx)?,
+        _ => check_files(&args)?,
+    };
+    findings.sort_by_key(|finding| finding.severity);
+    for finding in &findings {
+        println!("{finding}");
+    }
+    let errors = findings
+        .iter()
+        .filter(|finding| finding.severity == Severity::Error)
+        .count();
+    println!(
+        "{}: {errors} error(s), {} warning(s)",
+        args.header.display(),
+        findings.len() - errors
+    );
+    if errors > 0 {
+        Ok(ExitCode::FAILURE)
+    } else {
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// Fetch a published table and lint it as a whole
+fn check_url(url: &str, ctx: &Context) -> Result<Vec<Finding>, Box<dyn std::error::Error>> {
+    let (_, table) = ctx.parse(url)?;
+    Ok(lint(&table)
+        .into_iter()
+        .map(|finding| Finding {
+            severity: finding.severity,
+            location: finding.location.to_string(),
+            message: finding.message,
+        })
+        .collect())
+}
+
+fn check_files(args: &ValidateArgs) -> Result<Vec<Finding>, Box<dyn std::error::Error>> {
+    let header_data = fs::read_to_string(&args.header)?;
+    let (header, mut findings) = check_header(&header_data);
+    let body_path = match (&args.body, &header) {
+        (Some(body), _) => Some(body.clone()),
+        (None, Some(header)) if !header.data_url.starts_with("http") => {
+            let dir = args.header.parent().map(PathBuf::from).unwrap_or_default();
+            Some(dir.join(&header.data_url))
+        }
+        (None, Some(_)) => {
+            findings.push(Finding::warning(
+                "body",
+                "data_url is a remote url, pass the body path explicitly to validate it",
+            ));
+            None
+        }
+        (None, None) => None,
+    };
+    if let Some(body_path) = body_path {
+        let body_data = fs::read_to_string(&body_path)
+            .map_err(|e| format!("cannot read body {}: {e}", body_path.display()))?;
+        findings.extend(check_body(&body_data, header.as_ref()));
+    }
+    Ok(findings)
+}
+
+/// Check header json, returns the deserialized header if it's at least readable
+fn check_header(data: &str) -> (Option<DifficultTable>, Vec<Finding>) {
+    let header: DifficultTable = match serde_json::from_str(data) {
+        Ok(header) => header,
+        Err(e) => {
+            return (
+                None,
+                vec![Finding::error("header", format!("not a valid header: {e}"))],
+            )
+        }
+    };
+    let findings = lint(&header)
+        .into_iter()
+        .map(|finding| Finding {
+            severity: finding.severity,
+            location: finding.location.to_string(),
+            message: finding.message,
+        })
+        .collect();
+    (Some(header), findings)
+}
+
+/// Check body json, courses in header are checked against the body's md5s
+///
+/// Elements are linted along with the header, header findings are left to [`check_header`]
+fn check_body(data: &str, header: Option<&DifficultTable>) -> Vec<Finding> {
+    let rows: Vec<Value> = match serde_json::from_str(data) {
+        Ok(rows) => rows,
+        Err(e) => return vec![Finding::error("body", format!("not a valid body: {e}"))],
+    };
+    // (body position, finding), findings about the whole body or courses go last
+    let mut findings = Vec::new();
+    let mut positions = Vec::new();
+    let mut contents = Vec::new();
+    for (i, row) in rows.into_iter().enumerate() {
+        match serde_json::from_value::<DifficultTableElement>(row) {
+            Ok(element) => {
+                positions.push(i);
+                contents.push(element);
+            }
+            Err(e) => findings.push((i, Finding::error(format!("body[{i}]"), e.to_string()))),
+        }
+    }
+    let header = header.cloned().unwrap_or_else(|| {
+        serde_json::from_value(json!({ "name": "", "symbol": "" })).expect("header is valid")
+    });
+    let (header, _, courses) = header.into_parts();
+    let table = DifficultTable::from_parts(header, contents, courses);
+    for finding in lint(&table) {
+        let (position, location) = match finding.location {
+            LintLocation::Header => continue,
+            LintLocation::Contents => (usize::MAX, "body".to_string()),
+            LintLocation::Element(pos) => (positions[pos], format!("body[{}]", positions[pos])),
+            location => (usize::MAX, location.to_string()),
+        };
+        findings.push((
+            position,
+            Finding {
+                severity: finding.severity,
+                location,
+                message: finding.message,
+            },
+        ));
+    }
+    findings.sort_by_key(|(position, _)| *position);
+    findings.into_iter().map(|(_, finding)| finding).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn should_report_missing_header_fields() {
+        let (header, findings) = check_header(r#"{ "name": "", "symbol": "sl", "data_url": "" }"#);
+        assert!(header.is_some());
+        assert_eq!(findings.len(), 2);
+        assert!(findings.iter().all(|f| f.severity == Severity::Error));
+
+        let (header, findings) = check_header("}not even a json{");
+        assert!(header.is_none());
+        assert_eq!(findings.len(), 1);
+    }
+
+    #[test]
+    pub fn should_report_body_problems() {
+        let (header, _) = check_header(
+            r#"
+            {
+                "name": "Satellite",
+                "symbol": "sl",
+                "data_url": "body.json",
+                "course": [[{
+                    "name": "sl0",
+                    "constraint": [],
+                    "trophy": [],
+                    "md5": ["00000000000000000000000000000000", "ffffffffffffffffffffffffffffffff"]
+                }]]
+            }
+            "#,
+        );
+        let body = r#"
+        [
+            { "title": "a", "artist": "a", "md5": "00000000000000000000000000000000", "level": "0" },
+            { "title": "b", "artist": "b", "md5": "00000000000000000000000000000000", "level": "0" },
+            { "title": "c", "artist": "c", "md5": "not a md5", "level": "1" },
+            { "title": "d", "artist": "d", "md5": "11111111111111111111111111111111" }
+        ]
+        "#;
+        let findings = check_body(body, header.as_ref());
+        let errors: Vec<&str> = findings
+            .iter()
+            .filter(|f| f.severity == Severity::Error)
+            .map(|f| f.location.as_str())
+            .collect();
+        assert_eq!(errors, ["body[2]", "body[3]"]);
+        // duplicate md5, missing sha256 and the unknown course md5
+        assert_eq!(findings.len() - errors.len(), 3);
+    }
+}
@@ -0,0 +1,210 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::Write as _,
+    path::{Path, PathBuf},
+    process::{Command, ExitCode, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use clap::{Args, ValueEnum};
+use rbmstable_parser::{
+    diff_contents, DifficultTable, DifficultTableElement, ParseOptions, Subscription, TableDiff,
+    TableSubscriptions,
+};
+use reqwest::blocking::Client;
+use serde_json::Value;
+
+use crate::{config::parse_interval, context::Context, CliResult};
+
+#[derive(Args)]
+pub struct WatchArgs {
+    /// Toml file listing the watched tables
+    ///
+    /// ```text
+    /// [[tables]]
+    /// url = "https://stellabms.xyz/sl/table.html"
+    /// # optional, polled every --interval otherwise
+    /// refresh = "1d"
+    /// ```
+    #[arg(long)]
+    config: PathBuf,
+    /// Polling interval, e.g. `30m`, `1h` or `1d`
+    #[arg(long, default_value = "1h", value_parser = parse_interval)]
+    interval: Duration,
+    /// Directory snapshots are persisted to, defaults to `snapshots` under the cache directory
+    #[arg(long)]
+    snapshots: Option<PathBuf>,
+    /// Shell command invoked with the change report on stdin whenever a table changes
+    #[arg(long)]
+    exec: Option<String>,
+    /// Url the changes are POSTed to whenever a table changes
+    #[arg(long)]
+    webhook: Option<String>,
+    /// Payload POSTed to --webhook
+    #[arg(long, value_enum, default_value_t = WebhookFormat::Json)]
+    webhook_format: WebhookFormat,
+    /// Poll once and exit, useful when scheduled by cron
+    #[arg(long)]
+    once: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum WebhookFormat {
+    /// Table name, symbol and url along with the added, removed and moved songs
+    Json,
+    /// A Discord webhook message containing the change report
+    Discord,
+}
+
+pub fn run(args: WatchArgs, ctx: &Context) -> CliResult {
+    let subscriptions = TableSubscriptions::from_config(&args.config)?;
+    let snapshots = args
+        .snapshots
+        .clone()
+        .unwrap_or_else(|| ctx.config.cache_dir().join("snapshots"));
+    fs::create_dir_all(&snapshots)?;
+    // When each subscription is next polled, a subscription missing from it is polled right away
+    let mut next_polls: HashMap<&str, Instant> = HashMap::new();
+    loop {
+        for watched in subscriptions.enabled() {
+            if next_polls
+                .get(watched.url.as_str())
+                .is_some_and(|next| *next > Instant::now())
+            {
+                continue;
+            }
+            let refresh = watched.refresh.unwrap_or(args.interval);
+            next_polls.insert(&watched.url, Instant::now() + refresh);
+            if let Err(e) = poll(watched, &snapshots, &args, ctx) {
+                eprintln!("error: cannot poll {}: {e}", watched.label());
+            }
+        }
+        if args.once {
+            return Ok(ExitCode::SUCCESS);
+        }
+        let wait = next_polls.values().min().map_or(args.interval, |next| {
+            next.saturating_duration_since(Instant::now())
+        });
+        thread::sleep(wait);
+    }
+}
+
+/// Fetch one table, report its changes against the last snapshot and replace the snapshot
+fn poll(
+    watched: &Subscription,
+    snapshots: &Path,
+    args: &WatchArgs,
+    ctx: &Context,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let table = watched.parse(&ParseOptions::default())?;
+    let snapshot_path = snapshots.join(format!("{}.json", table.id()));
+    if snapshot_path.exists() {
+        let old: Vec<DifficultTableElement> =
+            serde_json::from_str(&fs::read_to_string(&snapshot_path)?)?;
+        let changes = diff_contents(&old, &table.contents);
+        if changes.is_empty() {
+            println!("[{}] no changes", table.name);
+        } else {
+            let report = changes.report(&table);
+            print!("{report}");
+            if let Some(exec) = &args.exec {
+                notify(exec, &watched.url, &table, &report)?;
+            }
+            if let Some(webhook) = &args.webhook {
+                let payload = webhook_payload(args.webhook_format, &watched.url, &table, &changes);
+                post_webhook(ctx.parser.client(), webhook, &payload)?;
+            }
+        }
+    } else {
+        println!(
+            "[{}] first snapshot, {} song(s)",
+            table.name,
+            table.contents.len()
+        );
+    }
+    fs::write(&snapshot_path, serde_json::to_string(&table.contents)?)?;
+    Ok(())
+}
+
+/// Run the user's command through the shell, the report is piped to its stdin
+fn notify(
+    exec: &str,
+    url: &str,
+    table: &DifficultTable,
+    report: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(exec)
+        .env("RBMSTABLE_TABLE", &table.name)
+        .env("RBMSTABLE_URL", url)
+        .stdin(Stdio::piped())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(report.as_bytes())?;
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(format!("`{exec}` exited with {status}").into());
+    }
+    Ok(())
+}
+
+/// Payload POSTed to the webhook in `format`
+fn webhook_payload(
+    format: WebhookFormat,
+    url: &str,
+    table: &DifficultTable,
+    changes: &TableDiff,
+) -> Value {
+    match format {
+        WebhookFormat::Json => changes.to_webhook_json(table, url),
+        WebhookFormat::Discord => changes.to_discord_message(table),
+    }
+}
+
+fn post_webhook(
+    client: &Client,
+    webhook: &str,
+    payload: &Value,
+) -> Result<(), Box<dyn std::error::Error>> {
+    client
+        .post(webhook)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(payload.to_string())
+        .send()?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn element(md5: &str, level: &str) -> DifficultTableElement {
+        serde_json::from_value(json!({
+            "title": md5,
+            "artist": "",
+            "md5": md5,
+            "level": level,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    pub fn should_diff_by_md5() {
+        let old = [element("a", "1"), element("b", "1"), element("c", "2")];
+        let new = [element("a", "1"), element("c", "3"), element("d", "3")];
+        let changes = diff_contents(&old, &new);
+        assert_eq!(changes.added[0].md5, "d");
+        assert_eq!(changes.removed[0].md5, "b");
+        assert_eq!(changes.moved[0].element.level, "3");
+        assert!(diff_contents(&old, &old).is_empty());
+    }
+}
@@ -0,0 +1,210 @@
+use serde_json::json;
+
+use crate::{
+    hash::{Md5, Sha256},
+    level::Level,
+    modal::{DifficultTable, DifficultTableCourse, DifficultTableElement},
+    parser::ParseError,
+};
+
+/// Builder of a [`DifficultTable`] from scratch, see [`DifficultTable::builder`]
+///
+/// Every song and course is validated when it's added, the first mistake is kept and returned by
+/// [`DifficultTableBuilder::build`]
+///
+/// # Example:
+/// ```text
+/// let dth = DifficultTable::builder()
+///     .name("My table")
+///     .symbol("my")
+///     .add_song("1", "Song", "Artist", "0123456789abcdef0123456789abcdef")
+///     .build()?;
+/// ```
+#[derive(Debug, Default)]
+pub struct DifficultTableBuilder {
+    name: String,
+    symbol: String,
+    data_url: String,
+    tag: String,
+    original_url: String,
+    last_update: String,
+    level_order: Vec<Level>,
+    contents: Vec<DifficultTableElement>,
+    courses: Vec<DifficultTableCourse>,
+    error: Option<ParseError>,
+}
+
+impl DifficultTable {
+    pub fn builder() -> DifficultTableBuilder {
+        DifficultTableBuilder::default()
+    }
+}
+
+impl DifficultTableBuilder {
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn symbol(mut self, symbol: impl Into<String>) -> Self {
+        self.symbol = symbol.into();
+        self
+    }
+
+    /// Left empty, [`DifficultTable::to_header_json`] writes `body.json`
+    pub fn data_url(mut self, data_url: impl Into<String>) -> Self {
+        self.data_url = data_url.into();
+        self
+    }
+
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tag = tag.into();
+        self
+    }
+
+    pub fn original_url(mut self, original_url: impl Into<String>) -> Self {
+        self.original_url = original_url.into();
+        self
+    }
+
+    pub fn last_update(mut self, last_update: impl Into<String>) -> Self {
+        self.last_update = last_update.into();
+        self
+    }
+
+    /// Display order of levels, see [`DifficultTable::level_order`]
+    pub fn level_order<L: Into<Level>>(mut self, levels: impl IntoIterator<Item = L>) -> Self {
+        self.level_order = levels.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Add a song, its md5 must be a valid one
+    pub fn add_song(
+        self,
+        level: impl Into<Level>,
+        title: impl Into<String>,
+        artist: impl Into<String>,
+        md5: impl Into<String>,
+    ) -> Self {
+        let element = serde_json::from_value(json!({
+            "title": title.into(),
+            "artist": artist.into(),
+            "md5": md5.into(),
+            "level": level.into(),
+        }))
+        .expect("element has every required field");
+        self.add_element(element)
+    }
+
+    /// Add a fully described song, its md5 must be a valid one, as its sha256 if any
+    pub fn add_element(mut self, element: DifficultTableElement) -> Self {
+        let checked = if element.title.is_empty() {
+            Err(ParseError::CorruptedHeaderData(format!(
+                "song {} has no title",
+                element.md5
+            )))
+        } else {
+            element
+                .md5
+                .parse::<Md5>()
+                .and_then(|_| match element.sha256.as_str() {
+                    "" => Ok(()),
+                    sha256 => sha256.parse::<Sha256>().map(|_| ()),
+                })
+        };
+        match checked {
+            Ok(()) => self.contents.push(element),
+            Err(err) => self.fail(err),
+        }
+        self
+    }
+
+    /// Add a course, every chart md5 must be a valid one
+    pub fn add_course(mut self, course: DifficultTableCourse) -> Self {
+        let checked = if course.name.is_empty() {
+            Err(ParseError::CorruptedHeaderData(
+                "course has no name".to_string(),
+            ))
+        } else {
+            course
+                .md5
+                .iter()
+                .try_for_each(|md5| md5.parse::<Md5>().map(|_| ()))
+        };
+        match checked {
+            Ok(()) => self.courses.push(course),
+            Err(err) => self.fail(err),
+        }
+        self
+    }
+
+    /// Fails with the first invalid song or course, or when name or symbol is missing
+    pub fn build(self) -> Result<DifficultTable, ParseError> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        for (field, value) in [("name", &self.name), ("symbol", &self.symbol)] {
+            if value.is_empty() {
+                return Err(ParseError::CorruptedHeaderData(format!(
+                    "{field} is missing"
+                )));
+            }
+        }
+        let mut header: DifficultTable = serde_json::from_value(json!({
+            "name": self.name,
+            "symbol": self.symbol,
+            "data_url": self.data_url,
+            "tag": self.tag,
+            "original_url": self.original_url,
+            "last_update": self.last_update,
+        }))?;
+        header.level_order = self.level_order;
+        Ok(DifficultTable::from_parts(
+            header,
+            self.contents,
+            self.courses,
+        ))
+    }
+
+    fn fail(&mut self, err: ParseError) {
+        self.error.get_or_insert(err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn should_build_tables() {
+        let md5 = "0123456789abcdef0123456789abcdef";
+        let table = DifficultTable::builder()
+            .name("My table")
+            .symbol("my")
+            .add_song("10", "b", "", md5)
+            .add_song("2", "a", "", md5.to_uppercase())
+            .add_course(DifficultTableCourse {
+                name: "my2".to_string(),
+                constraints: vec!["grade_mirror".to_string()],
+                trophy: Vec::new(),
+                md5: vec![md5.to_string()],
+            })
+            .build()
+            .unwrap();
+        assert_eq!(table.levels, ["2", "10"].map(Level::from));
+        assert_eq!(table.courses.len(), 1);
+        assert_eq!(table.find_by_md5(md5.parse().unwrap()).unwrap().title, "b");
+
+        let invalid = DifficultTable::builder()
+            .name("My table")
+            .symbol("my")
+            .add_song("1", "a", "", "not a md5")
+            .add_song("1", "", "", md5)
+            .build();
+        assert!(matches!(invalid, Err(ParseError::InvalidHash(msg)) if msg.contains("not a md5")));
+        let nameless = DifficultTable::builder().symbol("my").build();
+        assert!(
+            matches!(nameless, Err(ParseError::CorruptedHeaderData(msg)) if msg.contains("name"))
+        );
+    }
+}
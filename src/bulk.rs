@@ -0,0 +1,279 @@
+use std::{
+    collections::HashMap,
+    sync::{Condvar, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use url::Url;
+
+use crate::{
+    client::default_client,
+    fetcher::{Fetcher, Progress},
+    modal::DifficultTable,
+    options::ParseOptions,
+    parser::{FetchError, ParseError},
+};
+
+/// Parse many tables at once on up to `concurrency` threads, see [`parse_many_with_fetcher`]
+///
+/// Results are in the same order as urls
+///
+/// # Example:
+/// ```text
+/// for (url, dth) in urls.iter().zip(parse_many(&urls, 8)) {
+///     match dth {
+///         Ok(dth) => println!("{}: {} charts", dth.name, dth.contents.len()),
+///         Err(err) => eprintln!("{url}: {err}"),
+///     }
+/// }
+/// ```
+pub fn parse_many(urls: &[String], concurrency: usize) -> Vec<Result<DifficultTable, ParseError>> {
+    parse_many_with_fetcher(
+        &default_client(),
+        urls,
+        concurrency,
+        &ParseOptions::default(),
+    )
+}
+
+/// Same as [`parse_many`], with a [`Fetcher`] and [`ParseOptions`]
+///
+/// No more than [`ParseOptions::per_host_concurrency`] tables of the same host are parsed at a time, other hosts'
+/// tables are picked meanwhile. Requests to the same host are spaced by [`ParseOptions::per_host_interval`]
+pub fn parse_many_with_fetcher(
+    fetcher: &(dyn Fetcher + Sync),
+    urls: &[String],
+    concurrency: usize,
+    options: &ParseOptions,
+) -> Vec<Result<DifficultTable, ParseError>> {
+    let fetcher = Polite {
+        fetcher,
+        schedule: HostSchedule::new(options.per_host_interval),
+    };
+    let hosts: Vec<String> = urls.iter().map(|url| host(url)).collect();
+    let queue = Queue {
+        state: Mutex::new(QueueState {
+            pending: (0..urls.len()).collect(),
+            busy: HashMap::new(),
+        }),
+        freed: Condvar::new(),
+        per_host: options.per_host_concurrency.max(1),
+    };
+    let mut results: Vec<Option<Result<DifficultTable, ParseError>>> =
+        (0..urls.len()).map(|_| None).collect();
+    let workers = concurrency.clamp(1, urls.len().max(1));
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut parsed = Vec::new();
+                    while let Some(i) = queue.take(&hosts) {
+                        parsed.push((i, crate::parse_with_fetcher(&fetcher, &urls[i], options)));
+                        queue.release(&hosts[i]);
+                    }
+                    parsed
+                })
+            })
+            .collect();
+        for worker in workers {
+            for (i, result) in worker.join().unwrap() {
+                results[i] = Some(result);
+            }
+        }
+    });
+    results.into_iter().map(Option::unwrap).collect()
+}
+
+/// Urls left to parse, shared by the workers
+struct Queue {
+    state: Mutex<QueueState>,
+    freed: Condvar,
+    per_host: usize,
+}
+
+struct QueueState {
+    pending: Vec<usize>,
+    /// Tables being parsed, by host
+    busy: HashMap<String, usize>,
+}
+
+impl Queue {
+    /// Index of the next url whose host isn't busy, waiting if there's none, or None once everything is taken
+    fn take(&self, hosts: &[String]) -> Option<usize> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if state.pending.is_empty() {
+                return None;
+            }
+            let next = state
+                .pending
+                .iter()
+                .position(|&i| state.busy.get(&hosts[i]).copied().unwrap_or(0) < self.per_host);
+            if let Some(position) = next {
+                let i = state.pending.remove(position);
+                *state.busy.entry(hosts[i].clone()).or_default() += 1;
+                return Some(i);
+            }
+            state = self.freed.wait(state).unwrap();
+        }
+    }
+
+    fn release(&self, host: &str) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(busy) = state.busy.get_mut(host) {
+            *busy -= 1;
+        }
+        self.freed.notify_all();
+    }
+}
+
+/// Turns of the requests to each host, `interval` apart, see [`ParseOptions::per_host_interval`]
+pub(crate) struct HostSchedule {
+    interval: Duration,
+    /// Earliest time of the next request, by host
+    next: Mutex<HashMap<String, Instant>>,
+}
+
+impl HostSchedule {
+    pub(crate) fn new(interval: Duration) -> Self {
+        HostSchedule {
+            interval,
+            next: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Book the next turn of `url`'s host, to be waited for before requesting it. None when there's no interval
+    pub(crate) fn turn(&self, url: &str) -> Option<Instant> {
+        if self.interval.is_zero() {
+            return None;
+        }
+        let now = Instant::now();
+        let mut next = self.next.lock().unwrap();
+        let next = next.entry(host(url)).or_insert(now);
+        let turn = (*next).max(now);
+        *next = turn + self.interval;
+        Some(turn)
+    }
+}
+
+/// Fetcher waiting for its turn before each request, so that requests to a host are spaced out
+struct Polite<'a> {
+    fetcher: &'a (dyn Fetcher + Sync),
+    schedule: HostSchedule,
+}
+
+impl Polite<'_> {
+    fn wait(&self, url: &str) {
+        if let Some(turn) = self.schedule.turn(url) {
+            thread::sleep(turn.saturating_duration_since(Instant::now()));
+        }
+    }
+}
+
+impl Fetcher for Polite<'_> {
+    fn fetch(&self, url: &str) -> Result<Vec<u8>, FetchError> {
+        self.wait(url);
+        self.fetcher.fetch(url)
+    }
+
+    fn fetch_with_content_type(&self, url: &str) -> Result<(Vec<u8>, Option<String>), FetchError> {
+        self.wait(url);
+        self.fetcher.fetch_with_content_type(url)
+    }
+
+    fn fetch_with_progress(
+        &self,
+        url: &str,
+        progress: &mut Progress<'_>,
+    ) -> Result<(Vec<u8>, Option<String>), FetchError> {
+        self.wait(url);
+        self.fetcher.fetch_with_progress(url, progress)
+    }
+}
+
+fn host(url: &str) -> String {
+    Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use super::*;
+
+    #[test]
+    pub fn should_parse_many_tables_per_host() {
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let fetcher = |url: &str| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(20));
+            running.fetch_sub(1, Ordering::SeqCst);
+            if url.ends_with("header.json") {
+                Ok(br#"{ "name": "t", "symbol": "t", "data_url": "body.json" }"#.to_vec())
+            } else if url.ends_with("body.json") {
+                Ok(b"[]".to_vec())
+            } else {
+                Err(FetchError::NotFound(url.to_string()))
+            }
+        };
+        let mut urls: Vec<String> = (0..6)
+            .map(|i| format!("https://example.com/{i}/header.json"))
+            .collect();
+        urls.push("https://example.com/missing.json".to_string());
+        let options = ParseOptions {
+            per_host_concurrency: 2,
+            ..Default::default()
+        };
+        let results = parse_many_with_fetcher(&fetcher, &urls, 8, &options);
+        assert_eq!(results.len(), 7);
+        assert!(results[..6].iter().all(Result::is_ok));
+        assert!(matches!(results[6], Err(ParseError::HeaderFetch { .. })));
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+    }
+
+    #[test]
+    pub fn should_space_requests_to_the_same_host() {
+        let requests = Mutex::new(Vec::new());
+        let fetcher = |url: &str| {
+            requests.lock().unwrap().push((host(url), Instant::now()));
+            if url.ends_with("header.json") {
+                Ok(br#"{ "name": "t", "symbol": "t", "data_url": "body.json" }"#.to_vec())
+            } else {
+                Ok(b"[]".to_vec())
+            }
+        };
+        let urls: Vec<String> = ["a", "a", "b"]
+            .iter()
+            .enumerate()
+            .map(|(i, host)| format!("https://{host}.example.com/{i}/header.json"))
+            .collect();
+        let options = ParseOptions {
+            per_host_concurrency: 2,
+            per_host_interval: Duration::from_millis(50),
+            ..Default::default()
+        };
+        let results = parse_many_with_fetcher(&fetcher, &urls, 3, &options);
+        assert!(results.iter().all(Result::is_ok));
+        let requests = requests.into_inner().unwrap();
+        assert_eq!(requests.len(), 6);
+        for host in ["a.example.com", "b.example.com"] {
+            let times: Vec<Instant> = requests
+                .iter()
+                .filter(|(of, _)| of == host)
+                .map(|(_, at)| *at)
+                .collect();
+            assert!(times
+                .windows(2)
+                .all(|pair| pair[1] - pair[0] >= Duration::from_millis(45)));
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use reqwest::{
+    blocking::Client,
+    header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    client::default_client,
+    fetcher::{self, Fetcher, Progress},
+    modal::DifficultTable,
+    parser::FetchError,
+};
+
+/// [`Fetcher`] keeping every downloaded document on disk, revalidated with `If-None-Match` /
+/// `If-Modified-Since` so that unchanged headers and bodies are not downloaded again
+///
+/// Documents are keyed by url, a `304 Not Modified` answer is served from disk. Servers sending neither an
+/// `ETag` nor a `Last-Modified` header are always downloaded again
+///
+/// # Example:
+/// ```text
+/// let cache = DiskCache::new(dirs::cache_dir().unwrap().join("tables"));
+/// let dth = parse_with_fetcher(&cache, "https://stellabms.xyz/sl/table.html", &ParseOptions::default())?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    client: Client,
+    dir: PathBuf,
+}
+
+/// Validators of one cached document
+#[derive(Serialize, Deserialize, Default)]
+struct Validators {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// Not a validator, but needed to decode the cached document
+    #[serde(default)]
+    content_type: Option<String>,
+}
+
+impl DiskCache {
+    /// Cache documents under `dir`, which is created on the first write
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        DiskCache::with_client(default_client(), dir)
+    }
+
+    pub fn with_client(client: Client, dir: impl Into<PathBuf>) -> Self {
+        DiskCache {
+            client,
+            dir: dir.into(),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// (document, validators) paths of an url
+    fn paths(&self, url: &str) -> (PathBuf, PathBuf) {
+        let key: String = Sha256::digest(url.as_bytes())
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        (
+            self.dir.join(format!("{key}.body")),
+            self.dir.join(format!("{key}.json")),
+        )
+    }
+
+    /// Best effort, failing to cache must not fail the fetch
+    fn store(&self, url: &str, raw: &[u8], validators: &Validators) {
+        let (body_path, validators_path) = self.paths(url);
+        let _ = fs::create_dir_all(&self.dir)
+            .and_then(|_| fs::write(&body_path, raw))
+            .and_then(|_| fs::write(&validators_path, serde_json::to_vec(validators)?));
+    }
+}
+
+impl Fetcher for DiskCache {
+    fn fetch(&self, url: &str) -> Result<Vec<u8>, FetchError> {
+        Ok(self.fetch_with_content_type(url)?.0)
+    }
+
+    fn fetch_with_content_type(&self, url: &str) -> Result<(Vec<u8>, Option<String>), FetchError> {
+        self.fetch_with_progress(url, &mut |_, _| Ok(()))
+    }
+
+    fn fetch_with_progress(
+        &self,
+        url: &str,
+        progress: &mut Progress<'_>,
+    ) -> Result<(Vec<u8>, Option<String>), FetchError> {
+        let (body_path, validators_path) = self.paths(url);
+        let cached = fs::read(&validators_path)
+            .ok()
+            .and_then(|raw| serde_json::from_slice::<Validators>(&raw).ok())
+            .zip(fs::read(&body_path).ok());
+        let mut request = self.client.get(url);
+        if let Some((validators, _)) = &cached {
+            if let Some(etag) = &validators.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &validators.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        let mut resp = request.send()?;
+        if resp.status() == StatusCode::NOT_MODIFIED {
+            if let Some((validators, raw)) = cached {
+                let len = raw.len() as u64;
+                progress(len, Some(len))?;
+                return Ok((raw, validators.content_type));
+            }
+        }
+        resp = resp.error_for_status()?;
+        let header = |name| {
+            resp.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let validators = Validators {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+            content_type: fetcher::content_type(resp.headers()),
+        };
+        let raw = fetcher::read_body(resp, progress)?;
+        if validators.etag.is_some() || validators.last_modified.is_some() {
+            self.store(url, &raw, &validators);
+        }
+        Ok((raw, validators.content_type))
+    }
+}
+
+/// Parsed tables kept in memory for a while, keyed by the url given to the parser, see
+/// [`crate::ParserBuilder::cache_ttl`]
+///
+/// The whole table is cached rather than each document, so a hit skips both the header and the data_url fetch
+#[derive(Debug)]
+pub struct TableCache {
+    ttl: Duration,
+    tables: Mutex<HashMap<String, (Instant, DifficultTable)>>,
+}
+
+impl TableCache {
+    pub fn new(ttl: Duration) -> Self {
+        TableCache {
+            ttl,
+            tables: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Table parsed from url less than ttl ago
+    pub fn get(&self, url: &str) -> Option<DifficultTable> {
+        let mut tables = self.tables.lock().unwrap();
+        match tables.get(url) {
+            Some((parsed_at, table)) if parsed_at.elapsed() < self.ttl => Some(table.clone()),
+            Some(_) => {
+                tables.remove(url);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, url: &str, table: DifficultTable) {
+        self.tables
+            .lock()
+            .unwrap()
+            .insert(url.to_string(), (Instant::now(), table));
+    }
+
+    /// Forget one table, the next parse of url fetches it again
+    pub fn invalidate(&self, url: &str) {
+        self.tables.lock().unwrap().remove(url);
+    }
+
+    pub fn clear(&self) {
+        self.tables.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use super::*;
+
+    #[test]
+    pub fn should_revalidate_cached_documents() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/body.json", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let mut conditional = Vec::new();
+            for stream in listener.incoming().take(2) {
+                let mut stream = stream.unwrap();
+                let request: Vec<String> = BufReader::new(&stream)
+                    .lines()
+                    .map(Result::unwrap)
+                    .take_while(|line| !line.is_empty())
+                    .collect();
+                let revalidating = request
+                    .iter()
+                    .any(|line| line.eq_ignore_ascii_case("if-none-match: \"v1\""));
+                conditional.push(revalidating);
+                let response = if revalidating {
+                    "HTTP/1.1 304 Not Modified\r\nconnection: close\r\n\r\n"
+                } else {
+                    "HTTP/1.1 200 OK\r\netag: \"v1\"\r\ncontent-length: 2\r\nconnection: close\r\n\r\n[]"
+                };
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+            conditional
+        });
+
+        let dir = std::env::temp_dir().join(format!("rbmstable-cache-{}", std::process::id()));
+        let cache = DiskCache::new(&dir);
+        assert_eq!(cache.fetch(&url).unwrap(), b"[]");
+        assert_eq!(cache.fetch(&url).unwrap(), b"[]");
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(server.join().unwrap(), [false, true]);
+    }
+
+    #[test]
+    pub fn should_expire_cached_tables() {
+        let table = crate::parse_from_parts(
+            r#"{ "name": "t", "symbol": "t", "data_url": "body.json" }"#,
+            "[]",
+        )
+        .unwrap();
+        let cache = TableCache::new(Duration::from_secs(60));
+        cache.insert("https://example.com/table.html", table.clone());
+        assert!(cache.get("https://example.com/table.html").is_some());
+        assert!(cache.get("https://example.com/header.json").is_none());
+        cache.invalidate("https://example.com/table.html");
+        assert!(cache.get("https://example.com/table.html").is_none());
+
+        let expired = TableCache::new(Duration::ZERO);
+        expired.insert("https://example.com/table.html", table);
+        assert!(expired.get("https://example.com/table.html").is_none());
+    }
+}
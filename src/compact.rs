@@ -0,0 +1,140 @@
+use std::{collections::HashSet, sync::Arc};
+
+use crate::{level::Level, modal::DifficultTableElement};
+
+/// Memory friendly counterpart of [`DifficultTableElement`], enabled by the `compact` feature
+///
+/// * text fields are `Box<str>`, which drops the spare capacity a deserialized `String` carries
+/// * artists are `Arc<str>` and shared between elements by [`compact_contents`], most artists appear many times
+///   in a table (and even more in merged tables)
+///
+/// Converting back and forth with [`DifficultTableElement`] keeps every field but the derived ones (`romanized`
+/// and `lr2ir`), which can be filled again
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactElement {
+    pub title: Box<str>,
+    pub artist: Arc<str>,
+    pub md5: Box<str>,
+    pub sha256: Box<str>,
+    pub mode: Box<str>,
+    pub level: Level,
+    pub diff_name: Box<str>,
+    pub comment: Box<str>,
+    pub info: Box<str>,
+    pub bms_id: Box<str>,
+    pub url: Box<str>,
+    pub url_diff: Box<str>,
+    pub url_pack: Box<str>,
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl From<DifficultTableElement> for CompactElement {
+    fn from(element: DifficultTableElement) -> Self {
+        CompactElement {
+            title: element.title.into_boxed_str(),
+            artist: Arc::from(element.artist),
+            md5: element.md5.into_boxed_str(),
+            sha256: element.sha256.into_boxed_str(),
+            mode: element.mode.into_boxed_str(),
+            level: element.level,
+            diff_name: element.diff_name.into_boxed_str(),
+            comment: element.comment.into_boxed_str(),
+            info: element.info.into_boxed_str(),
+            bms_id: element.bms_id.into_boxed_str(),
+            url: element.url.into_boxed_str(),
+            url_diff: element.url_diff.into_boxed_str(),
+            url_pack: element.url_pack.into_boxed_str(),
+            extra: element.extra,
+        }
+    }
+}
+
+impl From<CompactElement> for DifficultTableElement {
+    fn from(element: CompactElement) -> Self {
+        DifficultTableElement {
+            title: element.title.into(),
+            artist: element.artist.to_string(),
+            md5: element.md5.into(),
+            sha256: element.sha256.into(),
+            mode: element.mode.into(),
+            level: element.level,
+            diff_name: element.diff_name.into(),
+            comment: element.comment.into(),
+            info: element.info.into(),
+            bms_id: element.bms_id.into(),
+            url: element.url.into(),
+            url_diff: element.url_diff.into(),
+            url_pack: element.url_pack.into(),
+            #[cfg(feature = "romaji")]
+            romanized: None,
+            #[cfg(feature = "lr2ir")]
+            lr2ir: None,
+            extra: element.extra,
+        }
+    }
+}
+
+/// Convert a table's contents into [`CompactElement`]s, sharing identical artists
+pub fn compact_contents(contents: Vec<DifficultTableElement>) -> Vec<CompactElement> {
+    let mut artists: HashSet<Arc<str>> = HashSet::new();
+    contents
+        .into_iter()
+        .map(|mut element| {
+            let artist = match artists.get(element.artist.as_str()) {
+                Some(artist) => Arc::clone(artist),
+                None => {
+                    let artist: Arc<str> = Arc::from(std::mem::take(&mut element.artist));
+                    artists.insert(Arc::clone(&artist));
+                    artist
+                }
+            };
+            CompactElement {
+                artist,
+                ..CompactElement::from(element)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn element(title: &str, artist: &str) -> DifficultTableElement {
+        serde_json::from_value(json!({
+            "title": title,
+            "artist": artist,
+            "md5": "00000000000000000000000000000000",
+            "level": "1",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    pub fn should_share_artists() {
+        let compact = compact_contents(vec![
+            element("a", "someone"),
+            element("b", "someone"),
+            element("c", "other"),
+        ]);
+        assert!(Arc::ptr_eq(&compact[0].artist, &compact[1].artist));
+        assert!(!Arc::ptr_eq(&compact[0].artist, &compact[2].artist));
+        assert_eq!(&*compact[2].artist, "other");
+    }
+
+    #[test]
+    pub fn should_round_trip() {
+        let mut original = element("title", "artist");
+        original.comment = "comment".to_string();
+        original
+            .extra
+            .insert("proposer".to_string(), json!("someone"));
+        let restored = DifficultTableElement::from(CompactElement::from(original.clone()));
+        assert_eq!(
+            serde_json::to_value(&original).unwrap(),
+            serde_json::to_value(&restored).unwrap()
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Deserializer};
+
+/// Date time formats seen in the wild, values without a time zone are taken as UTC
+const DATE_TIME_FORMATS: [&str; 6] = [
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%Y/%m/%d %H:%M:%S",
+    "%Y/%m/%d %H:%M",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y.%m.%d %H:%M",
+];
+
+const DATE_FORMATS: [&str; 5] = ["%Y-%m-%d", "%Y/%m/%d", "%Y.%m.%d", "%Y年%m月%d日", "%Y%m%d"];
+
+/// Parse a header's `last_update` into a UTC time stamp, see [`crate::DifficultTable::last_update_utc`]
+pub(crate) fn parse_last_update(raw: &str) -> Option<DateTime<Utc>> {
+    let raw = raw.trim();
+    if let Ok(time) =
+        DateTime::parse_from_rfc3339(raw).or_else(|_| DateTime::parse_from_rfc2822(raw))
+    {
+        return Some(time.to_utc());
+    }
+    // "20170205" is a date rather than an epoch
+    if raw.len() > 8 && raw.bytes().all(|b| b.is_ascii_digit()) {
+        let epoch: i64 = raw.parse().ok()?;
+        // seconds wouldn't reach 10^11 until year 5138
+        return if epoch >= 100_000_000_000 {
+            DateTime::from_timestamp_millis(epoch)
+        } else {
+            DateTime::from_timestamp(epoch, 0)
+        };
+    }
+    DATE_TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(raw, format).ok())
+        .or_else(|| {
+            DATE_FORMATS
+                .iter()
+                .find_map(|format| NaiveDate::parse_from_str(raw, format).ok())
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .map(|time| time.and_utc())
+}
+
+/// Accept `last_update` either as a string or as a number (epoch seconds or millis)
+pub(crate) fn string_or_number<'de, D>(d: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        String(String),
+        Number(serde_json::Number),
+    }
+    Ok(match Raw::deserialize(d)? {
+        Raw::String(s) => s,
+        Raw::Number(n) => n.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn should_normalize_common_formats() {
+        let expected = "2017-02-05T00:00:00Z";
+        for raw in [
+            "2017/02/05",
+            "2017/2/5",
+            "2017-02-05",
+            "2017.02.05",
+            "2017年02月05日",
+            "20170205",
+            " 2017-02-05T09:00:00+09:00 ",
+            "1486252800",
+            "1486252800000",
+        ] {
+            assert_eq!(
+                parse_last_update(raw)
+                    .map(|time| time.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+                Some(expected.to_string()),
+                "{raw}"
+            );
+        }
+        assert_eq!(
+            parse_last_update("2017/02/05 12:34").unwrap().to_rfc3339(),
+            "2017-02-05T12:34:00+00:00"
+        );
+        assert!(parse_last_update("").is_none());
+        assert!(parse_last_update("yesterday").is_none());
+    }
+}
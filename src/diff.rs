@@ -0,0 +1,245 @@
+use std::{collections::HashMap, fmt::Write as _};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::{
+    level::Level,
+    modal::{DifficultTable, DifficultTableElement},
+};
+
+/// Discord rejects messages longer than this
+const DISCORD_MESSAGE_LIMIT: usize = 2000;
+
+/// Changes between two versions of one table, see [`diff`]
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TableDiff {
+    /// Songs only in the new table, in its order
+    pub added: Vec<DifficultTableElement>,
+    /// Songs only in the old table, in its order
+    pub removed: Vec<DifficultTableElement>,
+    /// Songs in both tables whose level differs, in the new table's order
+    pub moved: Vec<LevelChange>,
+}
+
+/// One song of a [`TableDiff`] whose level changed
+#[derive(Debug, Clone, Serialize)]
+pub struct LevelChange {
+    /// The song as it is in the new table
+    #[serde(flatten)]
+    pub element: DifficultTableElement,
+    /// Its level in the old table
+    pub from: Level,
+}
+
+impl TableDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.moved.is_empty()
+    }
+
+    /// Human readable change report of `table`, a summary line followed by one line per song
+    pub fn report(&self, table: &DifficultTable) -> String {
+        let symbol = &table.symbol;
+        let mut report = format!(
+            "[{}] {} added, {} removed, {} moved\n",
+            table.name,
+            self.added.len(),
+            self.removed.len(),
+            self.moved.len()
+        );
+        for e in &self.added {
+            let _ = writeln!(report, "  + {symbol}{} {}", e.level, e.title);
+        }
+        for e in &self.removed {
+            let _ = writeln!(report, "  - {symbol}{} {}", e.level, e.title);
+        }
+        for moved in &self.moved {
+            let _ = writeln!(
+                report,
+                "  ~ {}: {symbol}{} -> {symbol}{}",
+                moved.element.title, moved.from, moved.element.level
+            );
+        }
+        report
+    }
+
+    /// Webhook payload of these changes: `table`'s name and symbol, its `url` and the added, removed and moved
+    /// songs
+    pub fn to_webhook_json(&self, table: &DifficultTable, url: &str) -> Value {
+        json!({
+            "table": table.name,
+            "symbol": table.symbol,
+            "url": url,
+            "added": self.added,
+            "removed": self.removed,
+            "moved": self.moved,
+        })
+    }
+
+    /// Discord webhook message holding [`TableDiff::report`], truncated to fit Discord's message length limit
+    pub fn to_discord_message(&self, table: &DifficultTable) -> Value {
+        discord_message(self.report(table))
+    }
+}
+
+fn discord_message(mut report: String) -> Value {
+    // leave room for the code block fences
+    let limit = DISCORD_MESSAGE_LIMIT - 8;
+    if report.len() > limit {
+        let mut end = limit - 3;
+        while !report.is_char_boundary(end) {
+            end -= 1;
+        }
+        report.truncate(end);
+        report.push_str("...");
+    }
+    json!({ "content": format!("```\n{report}```") })
+}
+
+/// Songs added, removed and moved to another level from `old` to `new`
+///
+/// Songs are matched by sha256 first, then by md5, both case insensitively. Each old song
+/// matches at most one new song
+///
+/// # Example:
+/// ```text
+/// let changes = diff(&yesterday, &today);
+/// for moved in &changes.moved {
+///     println!("{}: {} -> {}", moved.element.title, moved.from, moved.element.level);
+/// }
+/// ```
+pub fn diff(old: &DifficultTable, new: &DifficultTable) -> TableDiff {
+    diff_contents(&old.contents, &new.contents)
+}
+
+/// Same as [`diff`], on bare contents
+pub fn diff_contents(old: &[DifficultTableElement], new: &[DifficultTableElement]) -> TableDiff {
+    let matches = match_contents(old, new);
+    let mut matched = vec![false; old.len()];
+    let mut diff = TableDiff::default();
+    for (element, found) in new.iter().zip(&matches) {
+        let Some(pos) = *found else {
+            diff.added.push(element.clone());
+            continue;
+        };
+        matched[pos] = true;
+        if old[pos].level != element.level {
+            diff.moved.push(LevelChange {
+                element: element.clone(),
+                from: old[pos].level.clone(),
+            });
+        }
+    }
+    diff.removed = old
+        .iter()
+        .zip(&matched)
+        .filter(|(_, &matched)| !matched)
+        .map(|(element, _)| element.clone())
+        .collect();
+    diff
+}
+
+/// Position in `old` of the song each element of `new` is, matched as described in [`diff`]
+pub(crate) fn match_contents(
+    old: &[DifficultTableElement],
+    new: &[DifficultTableElement],
+) -> Vec<Option<usize>> {
+    let index = |hash: fn(&DifficultTableElement) -> &str| {
+        let mut index = HashMap::new();
+        for (pos, element) in old.iter().enumerate() {
+            if !hash(element).is_empty() {
+                index
+                    .entry(hash(element).to_ascii_lowercase())
+                    .or_insert_with(Vec::new)
+                    .push(pos);
+            }
+        }
+        index
+    };
+    let by_sha256 = index(|element| &element.sha256);
+    let by_md5 = index(|element| &element.md5);
+    let mut matched = vec![false; old.len()];
+    let mut find = |index: &HashMap<String, Vec<usize>>, hash: &str| {
+        if hash.is_empty() {
+            return None;
+        }
+        let pos = *index
+            .get(&hash.to_ascii_lowercase())?
+            .iter()
+            .find(|&&pos| !matched[pos])?;
+        matched[pos] = true;
+        Some(pos)
+    };
+    new.iter()
+        .map(|element| find(&by_sha256, &element.sha256).or_else(|| find(&by_md5, &element.md5)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn element(md5: &str, sha256: &str, level: &str) -> DifficultTableElement {
+        serde_json::from_value(json!({
+            "title": md5,
+            "artist": "",
+            "md5": md5,
+            "sha256": sha256,
+            "level": level,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    pub fn should_diff_by_sha256_then_md5() {
+        let old = vec![
+            element("a", "", "1"),
+            element("b", "", "1"),
+            element("stale", "C", "1"),
+        ];
+        let new = vec![
+            element("A", "", "1"),
+            element("fresh", "c", "2"),
+            element("d", "", "3"),
+        ];
+        let changes = diff_contents(&old, &new);
+        assert_eq!(changes.added.len(), 1);
+        assert_eq!(changes.added[0].md5, "d");
+        assert_eq!(changes.removed.len(), 1);
+        assert_eq!(changes.removed[0].md5, "b");
+        assert_eq!(changes.moved.len(), 1);
+        assert_eq!(changes.moved[0].element.md5, "fresh");
+        assert_eq!(changes.moved[0].from, "1");
+        assert!(diff_contents(&new, &new).is_empty());
+    }
+
+    #[test]
+    pub fn should_build_webhook_payloads() {
+        let table: DifficultTable =
+            serde_json::from_value(json!({ "name": "Satellite", "symbol": "sl" })).unwrap();
+        let old = [element("a", "", "1"), element("b", "", "1")];
+        let new = [element("a", "", "2"), element("c", "", "1")];
+        let changes = diff_contents(&old, &new);
+        let payload = changes.to_webhook_json(&table, "url");
+        assert_eq!(payload["table"], "Satellite");
+        assert_eq!(payload["added"][0]["md5"], "c");
+        assert_eq!(payload["removed"][0]["md5"], "b");
+        assert_eq!(payload["moved"][0]["level"], "2");
+        assert_eq!(payload["moved"][0]["from"], "1");
+        assert_eq!(
+            changes.report(&table),
+            "[Satellite] 1 added, 1 removed, 1 moved\n  + sl1 c\n  - sl1 b\n  ~ a: sl1 -> sl2\n"
+        );
+        assert!(changes.to_discord_message(&table)["content"]
+            .as_str()
+            .unwrap()
+            .starts_with("```\n[Satellite]"));
+
+        let payload = discord_message("あ".repeat(DISCORD_MESSAGE_LIMIT));
+        let content = payload["content"].as_str().unwrap();
+        assert!(content.len() <= DISCORD_MESSAGE_LIMIT);
+        assert!(content.starts_with("```\n") && content.ends_with("...```"));
+    }
+}
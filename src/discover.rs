@@ -0,0 +1,236 @@
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use url::Url;
+
+use crate::{
+    client::default_client,
+    fetcher::Fetcher,
+    options::ParseOptions,
+    parser::{self, ParseError},
+};
+
+/// Keys of a listed table's url in json indexes, first match wins
+const URL_KEYS: [&str; 4] = ["url", "header_url", "table_url", "href"];
+const NAME_KEYS: [&str; 2] = ["name", "title"];
+const SYMBOL_KEYS: [&str; 2] = ["symbol", "tag"];
+
+/// One table listed by a "table of tables" index, see [`parse_table_index`]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TableDescriptor {
+    /// Name given by the index, the url itself when it gives none
+    pub name: String,
+    /// Absolute url of the table page or header, could be passed to [`crate::parse`] directly
+    pub url: String,
+    /// Empty unless the index gives one
+    #[serde(default)]
+    pub symbol: String,
+}
+
+/// Fetch an index page listing difficult tables and parse it, see [`parse_table_index`]
+///
+/// # Example:
+/// ```text
+/// for table in discover("https://example.com/tables.html")? {
+///     let dth = parse(table.url)?;
+/// }
+/// ```
+pub fn discover(url: &str) -> Result<Vec<TableDescriptor>, ParseError> {
+    discover_with_fetcher(&default_client(), url, &ParseOptions::default())
+}
+
+/// Same as [`discover`], downloading through a [`Fetcher`]
+pub fn discover_with_fetcher(
+    fetcher: &dyn Fetcher,
+    url: &str,
+    options: &ParseOptions,
+) -> Result<Vec<TableDescriptor>, ParseError> {
+    let fetched = parser::fetch_index(fetcher, url, options)?;
+    let page = parser::decode(fetched.raw, fetched.content_type.as_deref())?;
+    parse_table_index(&page, url)
+}
+
+/// Tables listed by an index page, e.g. the list of a mirror site, in the page's order without duplicates
+///
+/// * `raw` - either a json index, or an html page
+/// * `base_url` - url the index is published at, relative urls are resolved against it
+///
+/// A json index is an array (possibly under a `tables` key) of table urls, or of objects with the table's `url`
+/// (or `header_url`, `table_url`, `href`), `name` (or `title`) and `symbol` (or `tag`). In an html page, every
+/// link to a `.html`, `.htm` or `.json` document is taken as a table, named by the link's text
+pub fn parse_table_index(raw: &str, base_url: &str) -> Result<Vec<TableDescriptor>, ParseError> {
+    let base = Url::parse(base_url).ok();
+    let trimmed = raw.trim_start();
+    let listed = if trimmed.starts_with(['[', '{']) {
+        json_index(serde_json::from_str(trimmed)?)?
+    } else {
+        html_index(raw)
+    };
+    let mut tables: Vec<TableDescriptor> = Vec::new();
+    for mut table in listed {
+        table.url = match &base {
+            Some(base) => match base.join(&table.url) {
+                Ok(url) => url.into(),
+                Err(_) => continue,
+            },
+            None => table.url,
+        };
+        if table.url == base_url || tables.iter().any(|listed| listed.url == table.url) {
+            continue;
+        }
+        if table.name.is_empty() {
+            table.name = table.url.clone();
+        }
+        tables.push(table);
+    }
+    Ok(tables)
+}
+
+fn json_index(index: Value) -> Result<Vec<TableDescriptor>, ParseError> {
+    let entries = match index {
+        Value::Array(entries) => entries,
+        Value::Object(mut index) => match index.shift_remove("tables") {
+            Some(Value::Array(entries)) => entries,
+            _ => {
+                return Err(ParseError::CorruptedTableIndex(
+                    "table index has no list of tables".to_string(),
+                ))
+            }
+        },
+        _ => {
+            return Err(ParseError::CorruptedTableIndex(
+                "table index is neither an array nor an object".to_string(),
+            ))
+        }
+    };
+    Ok(entries
+        .into_iter()
+        .filter_map(|entry| match entry {
+            Value::String(url) => Some(TableDescriptor {
+                name: String::new(),
+                url,
+                symbol: String::new(),
+            }),
+            Value::Object(entry) => Some(TableDescriptor {
+                url: first_string(&entry, &URL_KEYS).filter(|url| !url.is_empty())?,
+                name: first_string(&entry, &NAME_KEYS).unwrap_or_default(),
+                symbol: first_string(&entry, &SYMBOL_KEYS).unwrap_or_default(),
+            }),
+            _ => None,
+        })
+        .collect())
+}
+
+fn first_string(entry: &Map<String, Value>, keys: &[&str]) -> Option<String> {
+    keys.iter()
+        .find_map(|key| entry.get(*key)?.as_str())
+        .map(str::to_string)
+}
+
+fn html_index(page: &str) -> Vec<TableDescriptor> {
+    let document = Html::parse_document(page);
+    let link = Selector::parse("a[href]").unwrap();
+    document
+        .select(&link)
+        .filter_map(|link| {
+            let href = link.value().attr("href")?.trim();
+            let path = href.split(['?', '#']).next().unwrap_or_default();
+            let path = path.to_ascii_lowercase();
+            if ![".html", ".htm", ".json"]
+                .iter()
+                .any(|suffix| path.ends_with(suffix))
+            {
+                return None;
+            }
+            Some(TableDescriptor {
+                name: link
+                    .text()
+                    .collect::<String>()
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                url: href.to_string(),
+                symbol: String::new(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::FetchError;
+
+    #[test]
+    pub fn should_parse_json_and_html_indexes() {
+        let base = "https://mirror.example.com/tables/index.html";
+        let json = r#"{ "tables": [
+            { "name": "Satellite", "symbol": "sl", "url": "sl/table.html" },
+            { "title": "Stella", "header_url": "https://stellabms.xyz/st/header.json" },
+            { "name": "no url" },
+            "https://example.com/other/table.html",
+            { "name": "Satellite again", "url": "./sl/table.html" }
+        ] }"#;
+        let tables = parse_table_index(json, base).unwrap();
+        assert_eq!(
+            tables,
+            [
+                TableDescriptor {
+                    name: "Satellite".to_string(),
+                    url: "https://mirror.example.com/tables/sl/table.html".to_string(),
+                    symbol: "sl".to_string(),
+                },
+                TableDescriptor {
+                    name: "Stella".to_string(),
+                    url: "https://stellabms.xyz/st/header.json".to_string(),
+                    symbol: String::new(),
+                },
+                TableDescriptor {
+                    name: "https://example.com/other/table.html".to_string(),
+                    url: "https://example.com/other/table.html".to_string(),
+                    symbol: String::new(),
+                },
+            ]
+        );
+        assert!(matches!(
+            parse_table_index(r#"{ "name": "not an index" }"#, base),
+            Err(ParseError::CorruptedTableIndex(_))
+        ));
+
+        let html = r#"<ul>
+            <li><a href="index.html">Home</a></li>
+            <li><a href="sl/table.html">Satellite
+                (sl)</a></li>
+            <li><a href="https://stellabms.xyz/st/header.json?v=2">Stella</a></li>
+            <li><a href="https://example.com/about">About</a></li>
+            <li><a href="mailto:someone@example.com">Contact</a></li>
+        </ul>"#;
+        let tables = parse_table_index(html, base).unwrap();
+        let listed: Vec<(&str, &str)> = tables
+            .iter()
+            .map(|table| (table.name.as_str(), table.url.as_str()))
+            .collect();
+        assert_eq!(
+            listed,
+            [
+                (
+                    "Satellite (sl)",
+                    "https://mirror.example.com/tables/sl/table.html"
+                ),
+                ("Stella", "https://stellabms.xyz/st/header.json?v=2"),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn should_report_index_fetch_failures() {
+        let fetcher = |url: &str| -> Result<Vec<u8>, FetchError> {
+            Err(FetchError::NotFound(url.to_string()))
+        };
+        let url = "https://mirror.example.com/tables/index.html";
+        assert!(matches!(
+            discover_with_fetcher(&fetcher, url, &ParseOptions::default()),
+            Err(ParseError::IndexFetch { url: failed, .. }) if failed == url
+        ));
+    }
+}
@@ -0,0 +1,185 @@
+use std::{fmt, io};
+
+use serde::{
+    de::{DeserializeSeed, SeqAccess, Visitor},
+    Deserializer,
+};
+use serde_json::{Map, Value};
+
+use crate::parser::ParseError;
+
+/// One low-level parse event, see [`header_events`] and [`body_events`]
+///
+/// Fields are reported as raw json values in the order they appear in the source, unknown fields included,
+/// no validation is performed
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<'a> {
+    /// One header field, except `course` which is reported by the course events
+    HeaderField {
+        name: &'a str,
+        value: &'a Value,
+    },
+    /// Start of one course, courses are numbered in the flattened order
+    CourseStart {
+        index: usize,
+    },
+    CourseField {
+        name: &'a str,
+        value: &'a Value,
+    },
+    CourseEnd {
+        index: usize,
+    },
+    /// Start of one body element
+    ElementStart {
+        index: usize,
+    },
+    ElementField {
+        name: &'a str,
+        value: &'a Value,
+    },
+    ElementEnd {
+        index: usize,
+    },
+}
+
+/// Report difficult table header json as a series of [`Event`]s
+///
+/// Courses are accepted both as the usual two-dimensional array and as a flat array
+pub fn header_events(data: &str, mut handler: impl FnMut(Event<'_>)) -> Result<(), ParseError> {
+    let header: Map<String, Value> = serde_json::from_str(data)?;
+    for (name, value) in &header {
+        if name != "course" {
+            handler(Event::HeaderField { name, value });
+            continue;
+        }
+        let courses = value
+            .as_array()
+            .into_iter()
+            .flatten()
+            .flat_map(|course| match course {
+                Value::Array(group) => group.iter().collect(),
+                course => vec![course],
+            });
+        for (index, course) in courses.enumerate() {
+            let Value::Object(course) = course else {
+                return Err(ParseError::CorruptedHeaderData(format!(
+                    "course {index} is not an object"
+                )));
+            };
+            handler(Event::CourseStart { index });
+            for (name, value) in course {
+                handler(Event::CourseField { name, value });
+            }
+            handler(Event::CourseEnd { index });
+        }
+    }
+    Ok(())
+}
+
+/// Report difficult table body json as a series of [`Event`]s
+///
+/// The body is read as a stream, only one element is held in memory at a time
+pub fn body_events(
+    reader: impl io::Read,
+    handler: impl FnMut(Event<'_>),
+) -> Result<(), ParseError> {
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    BodySeed { handler }.deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(())
+}
+
+struct BodySeed<F> {
+    handler: F,
+}
+
+impl<'de, F: FnMut(Event<'_>)> DeserializeSeed<'de> for BodySeed<F> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, F: FnMut(Event<'_>)> Visitor<'de> for BodySeed<F> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an array of difficult table elements")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(mut self, mut seq: A) -> Result<(), A::Error> {
+        let mut index = 0;
+        while let Some(element) = seq.next_element::<Map<String, Value>>()? {
+            (self.handler)(Event::ElementStart { index });
+            for (name, value) in &element {
+                (self.handler)(Event::ElementField { name, value });
+            }
+            (self.handler)(Event::ElementEnd { index });
+            index += 1;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn describe(event: Event<'_>) -> String {
+        match event {
+            Event::HeaderField { name, value } => format!("header {name}={value}"),
+            Event::CourseStart { index } => format!("course {index} {{"),
+            Event::CourseField { name, .. } => format!("course {name}"),
+            Event::CourseEnd { index } => format!("}} course {index}"),
+            Event::ElementStart { index } => format!("element {index} {{"),
+            Event::ElementField { name, value } => format!("element {name}={value}"),
+            Event::ElementEnd { index } => format!("}} element {index}"),
+        }
+    }
+
+    #[test]
+    pub fn should_report_header_and_courses() {
+        let header = r#"
+        {
+            "name": "Satellite",
+            "course": [[{ "name": "sl0" }], [{ "name": "sl1" }]]
+        }
+        "#;
+        let mut events = Vec::new();
+        header_events(header, |event| events.push(describe(event))).unwrap();
+        assert_eq!(
+            events,
+            [
+                r#"header name="Satellite""#,
+                "course 0 {",
+                "course name",
+                "} course 0",
+                "course 1 {",
+                "course name",
+                "} course 1",
+            ]
+        );
+    }
+
+    #[test]
+    pub fn should_stream_body_elements() {
+        let body = r#"[{ "title": "a", "level": 1 }, { "url": "http://example.com" }]"#;
+        let mut events = Vec::new();
+        body_events(body.as_bytes(), |event| events.push(describe(event))).unwrap();
+        assert_eq!(
+            events,
+            [
+                "element 0 {",
+                r#"element title="a""#,
+                "element level=1",
+                "} element 0",
+                "element 1 {",
+                r#"element url="http://example.com""#,
+                "} element 1",
+            ]
+        );
+        assert!(body_events(r#"{ "not": "an array" }"#.as_bytes(), |_| {}).is_err());
+    }
+}
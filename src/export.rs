@@ -0,0 +1,307 @@
+use std::{fmt::Write as _, fs, io, path::Path};
+
+use serde_json::Value;
+use sha2::Digest;
+use url::Url;
+
+use crate::{hash::Sha256, modal::DifficultTable};
+
+/// data_url written by [`DifficultTable::to_header_json`] when the table has none
+pub(crate) const DEFAULT_DATA_URL: &str = "body.json";
+
+/// Files written by [`DifficultTable::write_table_dir`]
+const TABLE_FILE: &str = "table.html";
+pub(crate) const HEADER_FILE: &str = "header.json";
+
+/// Columns of [`DifficultTable::to_csv`]
+const CSV_COLUMNS: [&str; 9] = [
+    "level",
+    "title",
+    "artist",
+    "md5",
+    "sha256",
+    "mode",
+    "diff_name",
+    "info",
+    "bms_id",
+];
+
+impl DifficultTable {
+    /// Header json of this table, the counterpart of [`DifficultTable::to_body_json`]
+    ///
+    /// Everything but `contents` and `levels` is written, courses as the format's two-dimensional array and unknown
+    /// fields as they were parsed. An empty data_url is written as `body.json`
+    ///
+    /// # Example:
+    /// ```text
+    /// fs::write(dir.join("header.json"), dth.to_header_json())?;
+    /// fs::write(dir.join("body.json"), dth.to_body_json())?;
+    /// ```
+    pub fn to_header_json(&self) -> String {
+        let data_url = match self.data_url.as_str() {
+            "" => DEFAULT_DATA_URL,
+            data_url => data_url,
+        };
+        self.header_json(data_url)
+    }
+
+    pub(crate) fn header_json(&self, data_url: &str) -> String {
+        let mut header = serde_json::to_value(self).expect("table is serializable");
+        if let Value::Object(header) = &mut header {
+            header.shift_remove("contents");
+            header.shift_remove("levels");
+            header.insert("data_url".to_string(), data_url.into());
+            if self.courses.is_empty() {
+                header.shift_remove("course");
+            }
+        }
+        serde_json::to_string_pretty(&header).expect("table is serializable")
+    }
+
+    /// Body json of this table, i.e. `contents`
+    pub fn to_body_json(&self) -> String {
+        serde_json::to_string_pretty(&self.contents).expect("contents are serializable")
+    }
+
+    /// Digest of this table's header and body, as written by [`DifficultTable::to_header_json`] (data_url kept as
+    /// is) and [`DifficultTable::to_body_json`]
+    ///
+    /// Only the content counts, not how the fetched documents are formatted: compare it to the digest stored with
+    /// a previous copy to skip rewriting an unchanged table. Element order and unknown header fields count
+    pub fn content_hash(&self) -> Sha256 {
+        let mut hasher = sha2::Sha256::new();
+        hasher.update(self.header_json(&self.data_url));
+        hasher.update(b"\n");
+        hasher.update(self.to_body_json());
+        Sha256::from_bytes(hasher.finalize().into())
+    }
+
+    /// Rewrite data_url relative to the url the header is published at, so that the published pair keeps working
+    /// when moved to another host
+    ///
+    /// data_url is left as is when it's already relative, or on another host than the header
+    pub fn make_data_url_relative(&mut self, header_url: &str) {
+        let Ok(header_url) = Url::parse(header_url) else {
+            return;
+        };
+        let relative = Url::parse(&self.data_url)
+            .ok()
+            .and_then(|data_url| header_url.make_relative(&data_url))
+            .filter(|relative| !relative.is_empty() && !relative.contains("://"));
+        if let Some(relative) = relative {
+            self.data_url = relative;
+        }
+    }
+
+    /// Minimal `table.html` pointing at `header.json` through the bmstable meta tag, followed by the songs of each
+    /// level
+    pub fn to_table_html(&self) -> String {
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <meta name=\"bmstable\" content=\"{HEADER_FILE}\">\n<title>{}</title>\n</head>\n<body>\n<h1>{}</h1>\n",
+            escape_html(&self.name),
+            escape_html(&self.name)
+        );
+        for (level, elements) in self.contents_by_level() {
+            let _ = writeln!(
+                html,
+                "<h2>{}</h2>\n<table>\n<tr><th>Title</th><th>Artist</th></tr>",
+                escape_html(&format!("{}{level}", self.symbol))
+            );
+            for element in elements {
+                let title = escape_html(&element.title);
+                let title = match element.url.as_str() {
+                    "" => title,
+                    url => format!("<a href=\"{}\">{title}</a>", escape_html(url)),
+                };
+                let _ = writeln!(
+                    html,
+                    "<tr><td>{title}</td><td>{}</td></tr>",
+                    escape_html(&element.artist)
+                );
+            }
+            html.push_str("</table>\n");
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    /// Write a complete, hostable table into `dir`: `table.html`, `header.json` and `body.json`
+    ///
+    /// The written header's data_url is `body.json` whatever this table's is. `dir` is created if missing
+    pub fn write_table_dir(&self, dir: impl AsRef<Path>) -> io::Result<()> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join(TABLE_FILE), self.to_table_html())?;
+        fs::write(dir.join(HEADER_FILE), self.header_json(DEFAULT_DATA_URL))?;
+        fs::write(dir.join(DEFAULT_DATA_URL), self.to_body_json())
+    }
+
+    /// Write `contents` as CSV, one row per element after a header row
+    ///
+    /// Columns are level, title, artist, md5, sha256, mode, diff_name, info and bms_id. Fields containing a
+    /// comma, a quote or a line break are quoted (RFC 4180), rows end with `\n`
+    ///
+    /// # Example:
+    /// ```text
+    /// dth.to_csv(File::create("satellite.csv")?)?;
+    /// ```
+    pub fn to_csv(&self, mut writer: impl io::Write) -> io::Result<()> {
+        write_csv_row(&mut writer, CSV_COLUMNS)?;
+        for element in &self.contents {
+            write_csv_row(
+                &mut writer,
+                [
+                    element.level.as_str(),
+                    &element.title,
+                    &element.artist,
+                    &element.md5,
+                    &element.sha256,
+                    &element.mode,
+                    &element.diff_name,
+                    &element.info,
+                    &element.bms_id,
+                ],
+            )?;
+        }
+        writer.flush()
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn write_csv_row<'a>(
+    writer: &mut impl io::Write,
+    fields: impl IntoIterator<Item = &'a str>,
+) -> io::Result<()> {
+    for (i, field) in fields.into_iter().enumerate() {
+        if i > 0 {
+            writer.write_all(b",")?;
+        }
+        if field.contains([',', '"', '\n', '\r']) {
+            write!(writer, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            writer.write_all(field.as_bytes())?;
+        }
+    }
+    writer.write_all(b"\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    pub fn should_write_csv_rows() {
+        let mut table: DifficultTable = serde_json::from_value(json!({
+            "name": "Satellite",
+            "symbol": "sl",
+            "data_url": "body.json",
+        }))
+        .unwrap();
+        table.contents = serde_json::from_value(json!([
+            { "title": "a", "artist": "x", "md5": "aa", "sha256": "bb", "level": "0" },
+            { "title": "two\nlines", "artist": "\"y\"", "md5": "", "level": "1", "diff_name": "[A]" },
+        ]))
+        .unwrap();
+        let mut out = Vec::new();
+        table.to_csv(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "level,title,artist,md5,sha256,mode,diff_name,info,bms_id\n\
+             0,a,x,aa,bb,,,,\n\
+             1,\"two\nlines\",\"\"\"y\"\"\",,,,[A],,\n"
+        );
+    }
+
+    #[test]
+    pub fn should_write_header_and_body_json() {
+        let mut table: DifficultTable = serde_json::from_value(json!({
+            "name": "Satellite",
+            "symbol": "sl",
+            "data_url": "https://example.com/sl/data/body.json",
+            "course": [[{ "name": "sl0", "constraint": [], "trophy": [], "md5": ["aa"] }]],
+            "proposer": "someone",
+        }))
+        .unwrap();
+        table.add_element(
+            serde_json::from_value(
+                json!({ "title": "a", "artist": "", "md5": "aa", "level": "0" }),
+            )
+            .unwrap(),
+        );
+        table.make_data_url_relative("https://other.example.com/sl/header.json");
+        assert_eq!(table.data_url, "https://example.com/sl/data/body.json");
+        table.make_data_url_relative("https://example.com/sl/header.json");
+        assert_eq!(table.data_url, "data/body.json");
+
+        let header: Value = serde_json::from_str(&table.to_header_json()).unwrap();
+        assert_eq!(header["data_url"], "data/body.json");
+        assert_eq!(header["course"][0][0]["md5"], json!(["aa"]));
+        assert_eq!(header["proposer"], "someone");
+        assert!(header.get("contents").is_none() && header.get("levels").is_none());
+        let reparsed =
+            crate::parse_from_parts(&table.to_header_json(), &table.to_body_json()).unwrap();
+        assert_eq!(reparsed.contents[0].md5, "aa");
+        assert_eq!(reparsed.courses.len(), 1);
+    }
+
+    #[test]
+    pub fn should_hash_content_not_formatting() {
+        let header = r#"{ "name": "Satellite", "symbol": "sl", "data_url": "body.json", "proposer": "someone" }"#;
+        let body = r#"[{ "title": "a", "artist": "", "md5": "aa", "level": "0" }]"#;
+        let table = crate::parse_from_parts(header, body).unwrap();
+        let reformatted = crate::parse_from_parts(
+            &serde_json::to_string_pretty(&serde_json::from_str::<Value>(header).unwrap()).unwrap(),
+            &body.replace(' ', ""),
+        )
+        .unwrap();
+        assert_eq!(table.content_hash(), reformatted.content_hash());
+
+        let mut changed = table.clone();
+        changed.contents[0].title = "b".to_string();
+        assert_ne!(table.content_hash(), changed.content_hash());
+        let mut changed = table.clone();
+        changed
+            .extra
+            .insert("proposer".to_string(), "someone else".into());
+        assert_ne!(table.content_hash(), changed.content_hash());
+    }
+
+    #[test]
+    pub fn should_write_hostable_table_dir() {
+        let mut table: DifficultTable = serde_json::from_value(json!({
+            "name": "<Satellite>",
+            "symbol": "sl",
+            "data_url": "https://example.com/sl/body.json",
+        }))
+        .unwrap();
+        table.add_element(
+            serde_json::from_value(
+                json!({ "title": "a & b", "artist": "", "md5": "aa", "level": "0" }),
+            )
+            .unwrap(),
+        );
+        let html = table.to_table_html();
+        assert_eq!(crate::parser::meta_content(&html).unwrap(), "header.json");
+        assert!(html.contains("<h1>&lt;Satellite&gt;</h1>"));
+        assert!(html.contains("<td>a &amp; b</td>"));
+
+        let dir = std::env::temp_dir().join(format!("rbmstable-table-dir-{}", std::process::id()));
+        table.write_table_dir(&dir).unwrap();
+        let read = |file: &str| fs::read_to_string(dir.join(file)).unwrap();
+        let reparsed = crate::parse_from_parts(&read("header.json"), &read("body.json"));
+        assert!(read("table.html").contains("bmstable"));
+        let _ = fs::remove_dir_all(&dir);
+        let reparsed = reparsed.unwrap();
+        assert_eq!(reparsed.data_url, "body.json");
+        assert_eq!(reparsed.contents[0].title, "a & b");
+    }
+}
@@ -0,0 +1,119 @@
+use std::io::{self, Read};
+
+use reqwest::{
+    blocking::{Client, Response},
+    header::{HeaderMap, CONTENT_TYPE},
+};
+
+use crate::parser::FetchError;
+
+/// Called with (bytes_done, bytes_total) while a response body is read, see [`Fetcher::fetch_with_progress`]
+///
+/// bytes_total is the response's `Content-Length` when there's one. An error stops reading
+pub type Progress<'a> = dyn FnMut(u64, Option<u64>) -> Result<(), FetchError> + 'a;
+
+/// Size of the chunks response bodies are read by
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// How documents (table pages, headers, bodies...) are downloaded, see [`crate::parse_with_fetcher`]
+///
+/// [`reqwest::blocking::Client`] is the default implementation. Closures work too, which makes it easy to parse
+/// canned responses in tests:
+///
+/// # Example:
+/// ```text
+/// let fetcher = |url: &str| match url {
+///     "https://example.com/header.json" => Ok(br#"{ "name": "t", "symbol": "t", "data_url": "body.json" }"#.to_vec()),
+///     "https://example.com/body.json" => Ok(b"[]".to_vec()),
+///     _ => Err(FetchError::NotFound(url.to_string())),
+/// };
+/// let dth = parse_with_fetcher(&fetcher, "https://example.com/header.json", &ParseOptions::default())?;
+/// ```
+///
+/// Fetchers see http(s) urls, along with the urls of any other scheme allowed by
+/// [`crate::ParseOptions::allowed_schemes`], as is. Scheme checks, [`crate::HttpPolicy`], `file` urls and the
+/// Wayback Machine fallback are handled by the parser
+pub trait Fetcher {
+    /// GET an url and return the raw response body, decoding is left to the parser (some tables are served as
+    /// Shift_JIS). Error statuses must be reported as errors
+    fn fetch(&self, url: &str) -> Result<Vec<u8>, FetchError>;
+
+    /// Same as [`Fetcher::fetch`], along with the response's `Content-Type`, whose charset takes precedence when
+    /// decoding. Defaults to no content type
+    fn fetch_with_content_type(&self, url: &str) -> Result<(Vec<u8>, Option<String>), FetchError> {
+        Ok((self.fetch(url)?, None))
+    }
+
+    /// Same as [`Fetcher::fetch_with_content_type`], calling `progress` as the response body is read. An error
+    /// returned by `progress` must stop reading and be returned as is
+    ///
+    /// Defaults to calling `progress` once, when the whole body is fetched
+    fn fetch_with_progress(
+        &self,
+        url: &str,
+        progress: &mut Progress<'_>,
+    ) -> Result<(Vec<u8>, Option<String>), FetchError> {
+        let (raw, content_type) = self.fetch_with_content_type(url)?;
+        let len = raw.len() as u64;
+        progress(len, Some(len))?;
+        Ok((raw, content_type))
+    }
+}
+
+impl Fetcher for Client {
+    fn fetch(&self, url: &str) -> Result<Vec<u8>, FetchError> {
+        Ok(self.fetch_with_content_type(url)?.0)
+    }
+
+    fn fetch_with_content_type(&self, url: &str) -> Result<(Vec<u8>, Option<String>), FetchError> {
+        self.fetch_with_progress(url, &mut |_, _| Ok(()))
+    }
+
+    fn fetch_with_progress(
+        &self,
+        url: &str,
+        progress: &mut Progress<'_>,
+    ) -> Result<(Vec<u8>, Option<String>), FetchError> {
+        let resp = self.get(url).send()?.error_for_status()?;
+        let content_type = content_type(resp.headers());
+        Ok((read_body(resp, progress)?, content_type))
+    }
+}
+
+/// Read a whole response body by chunks, reporting progress before the first one and after each one
+pub(crate) fn read_body(
+    mut resp: Response,
+    progress: &mut Progress<'_>,
+) -> Result<Vec<u8>, FetchError> {
+    let total = resp.content_length();
+    let mut raw = Vec::new();
+    let mut chunk = vec![0; CHUNK_SIZE];
+    progress(0, total)?;
+    loop {
+        match resp.read(&mut chunk) {
+            Ok(0) => return Ok(raw),
+            Ok(read) => {
+                raw.extend_from_slice(&chunk[..read]);
+                progress(raw.len() as u64, total)?;
+            }
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+pub(crate) fn content_type(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+impl<F> Fetcher for F
+where
+    F: Fn(&str) -> Result<Vec<u8>, FetchError>,
+{
+    fn fetch(&self, url: &str) -> Result<Vec<u8>, FetchError> {
+        self(url)
+    }
+}
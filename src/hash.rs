@@ -0,0 +1,128 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::parser::ParseError;
+
+/// Decode a hex string into a fixed size array, both lower and upper case digits are accepted
+fn decode_hex<const N: usize>(s: &str) -> Option<[u8; N]> {
+    // from_str_radix alone would take a leading `+`
+    if s.len() != N * 2 || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let mut bytes = [0; N];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&s[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(bytes)
+}
+
+macro_rules! hash_type {
+    ($(#[$meta:meta])* $name:ident, $len:literal, $label:literal) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub struct $name([u8; $len]);
+
+        impl $name {
+            pub fn from_bytes(bytes: [u8; $len]) -> Self {
+                $name(bytes)
+            }
+
+            pub fn as_bytes(&self) -> &[u8; $len] {
+                &self.0
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = ParseError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                decode_hex(s).map($name).ok_or_else(|| {
+                    ParseError::InvalidHash(format!(
+                        "`{s}` is not a {} hex {} hash",
+                        $len * 2,
+                        $label
+                    ))
+                })
+            }
+        }
+
+        /// Lowercase hex, which is how difficult tables spell hashes
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                for byte in &self.0 {
+                    write!(f, "{byte:02x}")?;
+                }
+                Ok(())
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+                s.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+                let s = <std::borrow::Cow<str>>::deserialize(d)?;
+                s.parse().map_err(serde::de::Error::custom)
+            }
+        }
+    };
+}
+
+hash_type!(
+    /// A validated MD5 chart hash, see [`crate::DifficultTableElement::md5_hash`]
+    ///
+    /// Parsing accepts any case, displaying and serializing always produce lowercase hex. Being a plain
+    /// 16 bytes array, it's `Copy` and cheap to use as a map key.
+    Md5,
+    16,
+    "md5"
+);
+
+hash_type!(
+    /// A validated SHA256 chart hash, see [`crate::DifficultTableElement::sha256_hash`]
+    ///
+    /// Parsing accepts any case, displaying and serializing always produce lowercase hex. Being a plain
+    /// 32 bytes array, it's `Copy` and cheap to use as a map key.
+    Sha256,
+    32,
+    "sha256"
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn should_normalize_case() {
+        let lower: Md5 = "0123456789abcdef0123456789abcdef".parse().unwrap();
+        let upper: Md5 = "0123456789ABCDEF0123456789ABCDEF".parse().unwrap();
+        assert_eq!(lower, upper);
+        assert_eq!(upper.to_string(), "0123456789abcdef0123456789abcdef");
+        assert_eq!(
+            serde_json::to_string(&upper).unwrap(),
+            r#""0123456789abcdef0123456789abcdef""#
+        );
+    }
+
+    #[test]
+    pub fn should_reject_malformed_hashes() {
+        let cases = [
+            "",
+            "0123456789abcdef",
+            "0123456789abcdef0123456789abcdeg",
+            "0123456789abcdef0123456789abcdef00",
+            "０123456789abcdef0123456789abcde",
+            "+0+0+0+0+0+0+0+0+0+0+0+0+0+0+0+0",
+        ];
+        assert!(cases.iter().all(|case| case.parse::<Md5>().is_err()));
+        assert!(serde_json::from_str::<Md5>(r#""not a hash""#).is_err());
+        let sha256 = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        assert_eq!(sha256.parse::<Sha256>().unwrap().to_string(), sha256);
+        assert!(sha256.parse::<Md5>().is_err());
+        assert!("+0".repeat(32).parse::<Sha256>().is_err());
+    }
+}
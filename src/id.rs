@@ -0,0 +1,123 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// A stable identifier of one difficult table, e.g. `satellite-5a8d6c2e`
+///
+/// Derived ids (see [`crate::DifficultTable::id`]) consist of a readable slug of the table name and a short
+/// fingerprint of (name, symbol, original_url), so that tables with non-latin or identical names still get
+/// distinct ids. The fingerprint is FNV-1a, which is stable across builds and platforms.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(transparent)]
+pub struct TableId(String);
+
+impl TableId {
+    pub fn new(id: impl Into<String>) -> Self {
+        TableId(id.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Derive an id from a table's identifying fields
+    pub fn derive(name: &str, symbol: &str, original_url: &str) -> Self {
+        let slug = slugify(name);
+        let fingerprint = fnv1a([name, symbol, original_url]) as u32;
+        if slug.is_empty() {
+            TableId(format!("{fingerprint:08x}"))
+        } else {
+            TableId(format!("{slug}-{fingerprint:08x}"))
+        }
+    }
+}
+
+/// Lowercase ascii alphanumerics, every other run of characters becomes a single `-`
+fn slugify(s: &str) -> String {
+    let mut slug = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+/// 64 bits FNV-1a over every part, parts are separated by a zero byte
+fn fnv1a<'a>(parts: impl IntoIterator<Item = &'a str>) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+    let mut hash = OFFSET_BASIS;
+    for part in parts {
+        for byte in part.bytes().chain([0]) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(PRIME);
+        }
+    }
+    hash
+}
+
+impl fmt::Display for TableId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for TableId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for TableId {
+    fn from(id: String) -> Self {
+        TableId(id)
+    }
+}
+
+impl From<&str> for TableId {
+    fn from(id: &str) -> Self {
+        TableId(id.to_string())
+    }
+}
+
+impl PartialEq<str> for TableId {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for TableId {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn should_slugify_name() {
+        assert_eq!(slugify("Satellite"), "satellite");
+        assert_eq!(slugify("  DP Satellite (beta)  "), "dp-satellite-beta");
+        assert_eq!(slugify("発狂BMS難易度表"), "bms");
+        assert_eq!(slugify("★★★"), "");
+    }
+
+    #[test]
+    pub fn derived_id_should_be_stable() {
+        let id = TableId::derive("Satellite", "sl", "https://stellabms.xyz/sl/table.html");
+        assert_eq!(
+            id,
+            TableId::derive("Satellite", "sl", "https://stellabms.xyz/sl/table.html")
+        );
+        assert!(id.as_str().starts_with("satellite-"));
+        // pinned, changing the derivation would break every persisted id
+        assert_eq!(TableId::derive("", "", ""), "6c0f2fb7");
+        assert_ne!(id, TableId::derive("Satellite", "st", ""));
+        assert_eq!(TableId::derive("★★★", "★", "").as_str().len(), 8);
+    }
+}
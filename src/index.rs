@@ -0,0 +1,105 @@
+use std::{collections::HashMap, fmt, hash::Hash, sync::OnceLock};
+
+use crate::{
+    hash::{Md5, Sha256},
+    modal::DifficultTableElement,
+};
+
+/// Lookup tables over a [`crate::DifficultTable`]'s contents, built on first use
+///
+/// Cloning (and deserializing) gives an empty index, which is rebuilt lazily. Elements with a malformed or
+/// empty hash aren't indexed
+#[derive(Default)]
+pub(crate) struct ContentIndex {
+    md5: OnceLock<Positions<Md5>>,
+    sha256: OnceLock<Positions<Sha256>>,
+}
+
+/// Hash to the position of the first element having it
+struct Positions<H> {
+    positions: HashMap<H, usize>,
+    /// Length of the contents indexed, elements pushed since then aren't indexed
+    indexed: usize,
+}
+
+impl ContentIndex {
+    /// Forget everything, to be called whenever contents change
+    pub(crate) fn reset(&mut self) {
+        *self = ContentIndex::default();
+    }
+
+    pub(crate) fn find_md5<'a>(
+        &self,
+        contents: &'a [DifficultTableElement],
+        md5: Md5,
+    ) -> Option<&'a DifficultTableElement> {
+        find(&self.md5, contents, md5, DifficultTableElement::md5_hash)
+    }
+
+    pub(crate) fn find_sha256<'a>(
+        &self,
+        contents: &'a [DifficultTableElement],
+        sha256: Sha256,
+    ) -> Option<&'a DifficultTableElement> {
+        find(
+            &self.sha256,
+            contents,
+            sha256,
+            DifficultTableElement::sha256_hash,
+        )
+    }
+}
+
+/// Look a hash up through the index, falling back to a scan when contents were mutated directly since it was
+/// built: either the indexed position no longer holds the hash, or the hash is missing while elements were added
+fn find<'a, H: Copy + Eq + Hash>(
+    index: &OnceLock<Positions<H>>,
+    contents: &'a [DifficultTableElement],
+    hash: H,
+    field: impl Fn(&DifficultTableElement) -> Option<H>,
+) -> Option<&'a DifficultTableElement> {
+    let index = index.get_or_init(|| build(contents, &field));
+    let indexed = match index.positions.get(&hash) {
+        Some(&pos) => contents.get(pos),
+        None if index.indexed == contents.len() => return None,
+        None => None,
+    };
+    if let Some(element) = indexed.filter(|element| field(element) == Some(hash)) {
+        return Some(element);
+    }
+    contents.iter().find(|element| field(element) == Some(hash))
+}
+
+fn build<H: Eq + Hash>(
+    contents: &[DifficultTableElement],
+    hash: impl Fn(&DifficultTableElement) -> Option<H>,
+) -> Positions<H> {
+    let mut positions = HashMap::with_capacity(contents.len());
+    for (pos, element) in contents.iter().enumerate() {
+        if let Some(hash) = hash(element) {
+            positions.entry(hash).or_insert(pos);
+        }
+    }
+    Positions {
+        positions,
+        indexed: contents.len(),
+    }
+}
+
+impl Clone for ContentIndex {
+    fn clone(&self) -> Self {
+        ContentIndex::default()
+    }
+}
+
+impl fmt::Debug for ContentIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ContentIndex")
+            .field("md5", &self.md5.get().map(|md5| md5.positions.len()))
+            .field(
+                "sha256",
+                &self.sha256.get().map(|sha256| sha256.positions.len()),
+            )
+            .finish()
+    }
+}
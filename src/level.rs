@@ -0,0 +1,324 @@
+use std::{cmp::Ordering, fmt};
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::date::string_or_number;
+
+/// Represents one difficult table level mark, e.g. `12`, `sl3` or `???`
+///
+/// Levels are (de)serialized as plain strings, numbers (`"level": 12`) are accepted and kept as their decimal
+/// string. The ordering is a natural ordering, which is total:
+/// * both sides are split into digit runs and non-digit runs, then compared run by run
+/// * two digit runs are compared as decimal numbers, leading zeros are ignored, a leading `-` makes the first
+///   run negative, and a `.` followed by digits is the fractional part
+/// * a trailing `+` / `-` right after a number sorts it just after / before the bare number
+/// * a digit run always comes before a non-digit run
+/// * two non-digit runs are compared as string
+///
+/// Levels that are equal under the rules above (e.g. `07` and `7`) are finally compared as string so
+/// that `Ord` stays consistent with `Eq`
+///
+/// # Example:
+/// ```text
+/// [-1, 0, 1, 2, ..., 10, 11, 11.5, 12-, 12, 12+, ..., 25, ???, X]
+/// ```
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[serde(transparent)]
+pub struct Level(String);
+
+impl<'de> Deserialize<'de> for Level {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        string_or_number(d).map(Level)
+    }
+}
+
+impl Level {
+    pub fn new(level: impl Into<String>) -> Self {
+        Level(level.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+
+    /// Typed view of this level, for consumers that need the number rather than the mark
+    ///
+    /// Only plain numbers are numeric, `12+`, `sl3` or `1-2` are symbolic. Ordering stays [`Level`]'s
+    pub fn kind(&self) -> LevelKind<'_> {
+        let level = self.0.trim();
+        if let Ok(level) = level.parse::<i32>() {
+            return LevelKind::Numeric(level);
+        }
+        let is_decimal = |s: &str| {
+            s.split_once('.').is_some_and(|(integer, fraction)| {
+                let integer = integer.strip_prefix('-').unwrap_or(integer);
+                !integer.is_empty()
+                    && !fraction.is_empty()
+                    && integer
+                        .chars()
+                        .chain(fraction.chars())
+                        .all(|c| c.is_ascii_digit())
+            })
+        };
+        match level.parse::<f32>() {
+            Ok(value) if is_decimal(level) => LevelKind::Decimal(value),
+            _ => LevelKind::Symbolic(&self.0),
+        }
+    }
+}
+
+/// What a [`Level`] mark holds, see [`Level::kind`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LevelKind<'a> {
+    /// e.g. `12`, `-1`
+    Numeric(i32),
+    /// e.g. `11.5`
+    Decimal(f32),
+    /// e.g. `???`, `sl3`, `12+`
+    Symbolic(&'a str),
+}
+
+/// One piece of a level mark, see [`Level`]'s ordering rules
+#[derive(Debug, PartialEq, Eq)]
+enum Chunk<'a> {
+    Number(Number<'a>),
+    Text(&'a str),
+}
+
+/// A decimal number within a level mark, e.g. `-1`, `07`, `12.5` or `20+`
+#[derive(Debug, PartialEq, Eq)]
+struct Number<'a> {
+    negative: bool,
+    /// integer part without leading zeros
+    integer: &'a str,
+    /// fractional part without trailing zeros
+    fraction: &'a str,
+    /// -1 for a trailing `-`, 1 for a trailing `+`
+    suffix: i8,
+}
+
+impl Ord for Number<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let magnitude = self
+            .integer
+            .len()
+            .cmp(&other.integer.len())
+            .then_with(|| self.integer.cmp(other.integer))
+            .then_with(|| self.fraction.cmp(other.fraction));
+        let value = match (self.negative, other.negative) {
+            (false, false) => magnitude,
+            (true, true) => magnitude.reverse(),
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+        };
+        value.then_with(|| self.suffix.cmp(&other.suffix))
+    }
+}
+
+impl PartialOrd for Number<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Chunk<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Chunk::Number(lhs), Chunk::Number(rhs)) => lhs.cmp(rhs),
+            (Chunk::Number(_), Chunk::Text(_)) => Ordering::Less,
+            (Chunk::Text(_), Chunk::Number(_)) => Ordering::Greater,
+            (Chunk::Text(lhs), Chunk::Text(rhs)) => lhs.cmp(rhs),
+        }
+    }
+}
+
+impl PartialOrd for Chunk<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn starts_with_digit(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Split the leading digit run off `s`
+fn split_digits(s: &str) -> (&str, &str) {
+    s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()))
+}
+
+fn chunks(s: &str) -> Vec<Chunk<'_>> {
+    let mut chunks = Vec::new();
+    let mut rest = s;
+    let mut negative = rest.starts_with('-') && starts_with_digit(&rest[1..]);
+    if negative {
+        rest = &rest[1..];
+    }
+    while !rest.is_empty() {
+        if !starts_with_digit(rest) {
+            let len = rest
+                .find(|c: char| c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let (chunk, tail) = rest.split_at(len);
+            chunks.push(Chunk::Text(chunk));
+            negative = false;
+            rest = tail;
+            continue;
+        }
+        let (integer, tail) = split_digits(rest);
+        rest = tail;
+        let mut fraction = "";
+        if rest.starts_with('.') && starts_with_digit(&rest[1..]) {
+            (fraction, rest) = split_digits(&rest[1..]);
+        }
+        // a trailing sign only counts as a suffix when no digit follows, "1-2" is not "1-" then "2"
+        let mut suffix = 0;
+        if let Some(sign) = rest.chars().next().filter(|c| *c == '+' || *c == '-') {
+            if !starts_with_digit(&rest[1..]) {
+                suffix = if sign == '+' { 1 } else { -1 };
+                rest = &rest[1..];
+            }
+        }
+        let integer = integer.trim_start_matches('0');
+        let fraction = fraction.trim_end_matches('0');
+        chunks.push(Chunk::Number(Number {
+            // "-0" is just 0
+            negative: negative && !(integer.is_empty() && fraction.is_empty()),
+            integer,
+            fraction,
+            suffix,
+        }));
+        negative = false;
+    }
+    chunks
+}
+
+impl Ord for Level {
+    fn cmp(&self, other: &Self) -> Ordering {
+        chunks(&self.0)
+            .cmp(&chunks(&other.0))
+            .then_with(|| self.0.cmp(&other.0))
+    }
+}
+
+impl PartialOrd for Level {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for Level {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for Level {
+    fn from(level: String) -> Self {
+        Level(level)
+    }
+}
+
+impl From<&str> for Level {
+    fn from(level: &str) -> Self {
+        Level(level.to_string())
+    }
+}
+
+impl From<Level> for String {
+    fn from(level: Level) -> Self {
+        level.0
+    }
+}
+
+impl PartialEq<str> for Level {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Level {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(levels: &[&str]) -> Vec<String> {
+        let mut levels: Vec<Level> = levels.iter().map(|level| Level::from(*level)).collect();
+        levels.sort();
+        levels.into_iter().map(Level::into_string).collect()
+    }
+
+    #[test]
+    pub fn should_sort_numbers_as_number() {
+        assert_eq!(
+            sorted(&["10", "2", "-1", "0", "1", "-2"]),
+            ["-2", "-1", "0", "1", "2", "10"]
+        );
+    }
+
+    #[test]
+    pub fn should_sort_naturally() {
+        assert_eq!(
+            sorted(&["???", "sl10", "X", "sl9", "25", "0"]),
+            ["0", "25", "???", "X", "sl9", "sl10"]
+        );
+    }
+
+    #[test]
+    pub fn should_be_consistent_with_eq() {
+        let (lhs, rhs) = (Level::from("07"), Level::from("7"));
+        assert_ne!(lhs, rhs);
+        assert_ne!(lhs.cmp(&rhs), Ordering::Equal);
+        // the old "parse as i32, else compare as string" rule had a cycle here: 2 < 10 < 1a < 2
+        assert_eq!(sorted(&["1a", "10", "2"]), ["1a", "2", "10"]);
+    }
+
+    #[test]
+    pub fn should_tell_kinds_apart() {
+        assert_eq!(Level::from("12").kind(), LevelKind::Numeric(12));
+        assert_eq!(Level::from("-1").kind(), LevelKind::Numeric(-1));
+        assert_eq!(Level::from("11.5").kind(), LevelKind::Decimal(11.5));
+        for symbolic in ["???", "sl3", "12+", "1-2", "NaN", "inf", "1."] {
+            assert_eq!(Level::from(symbolic).kind(), LevelKind::Symbolic(symbolic));
+        }
+    }
+
+    #[test]
+    pub fn should_accept_numbers() {
+        let levels: Vec<Level> = serde_json::from_str(r#"[12, "12", 11.5, -1]"#).unwrap();
+        assert_eq!(levels, ["12", "12", "11.5", "-1"].map(Level::from));
+        assert_eq!(serde_json::to_string(&levels[0]).unwrap(), r#""12""#);
+    }
+
+    #[test]
+    pub fn should_sort_decimals_and_suffixes() {
+        assert_eq!(
+            sorted(&["13", "12.5", "12.10", "12", "07", "6.50"]),
+            ["6.50", "07", "12", "12.10", "12.5", "13"]
+        );
+        assert_eq!(
+            sorted(&["20+", "21", "20", "20-", "19.9"]),
+            ["19.9", "20-", "20", "20+", "21"]
+        );
+        assert_eq!(sorted(&["-0.5", "0", "-1"]), ["-1", "-0.5", "0"]);
+        assert_eq!(sorted(&["1-3", "1-2", "1"]), ["1", "1-2", "1-3"]);
+    }
+}
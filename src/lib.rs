@@ -1,15 +1,159 @@
-use std::io::Read;
+use std::time::Instant;
 
+use reqwest::blocking::Client;
+use serde::de::DeserializeOwned;
+
+use crate::resolve::{Document, Resolver, Step};
+
+#[cfg(feature = "async")]
+pub use audit::{BrokenLink, LinkAudit};
+pub use beatoraja::{parse_beatoraja_table, read_beatoraja_table, BeatorajaCourse, BeatorajaSong};
+pub use builder::DifficultTableBuilder;
+pub use bulk::{parse_many, parse_many_with_fetcher};
+pub use cache::{DiskCache, TableCache};
+pub use client::{Parser, ParserBuilder, DEFAULT_USER_AGENT};
+#[cfg(feature = "compact")]
+pub use compact::{compact_contents, CompactElement};
+pub use diff::{diff, diff_contents, LevelChange, TableDiff};
+pub use discover::{discover, discover_with_fetcher, parse_table_index, TableDescriptor};
+pub use events::{body_events, header_events, Event};
+pub use fetcher::{Fetcher, Progress};
+pub use hash::{Md5, Sha256};
+pub use id::TableId;
+pub use level::{Level, LevelKind};
+pub use lint::{lint, LintFinding, LintLocation, Severity};
+#[cfg(feature = "lr2ir")]
+pub use lr2ir::{ChartStats, Lr2irClient};
+pub use merge::MergeStrategy;
 pub use modal::DifficultTable;
+pub use modal::DifficultTableCourse;
+pub use modal::DifficultTableCourseTrophy;
 pub use modal::DifficultTableElement;
-pub use parser::ParseError;
+pub use modal::SharedTable;
+pub use modal::TableHeader;
+#[cfg(feature = "async")]
+pub use nonblocking::{
+    parse_async, parse_async_with_client, parse_async_with_client_and_options,
+    parse_async_with_options,
+};
+pub use options::{
+    ContentOrdering, HashValidation, HttpPolicy, LevelOrdering, ParseOptions, ProgressHook,
+    RetryOn, RetryPolicy, Stage,
+};
+pub use parser::{parse_from_json, parse_from_parts, FetchError, ParseError, ParseWarning};
+pub use quirk::{Quirk, QuirkTarget, Quirks};
+pub use registry::{parse_all_known, parse_known, KnownTable, Registry};
+pub use report::{CourseCoverage, DeadLookingUrl, Duplicate, TableReport, TableStats};
+#[cfg(feature = "romaji")]
+pub use romaji::{romanize, KanjiReadings, NoReadings, Romanized};
+#[cfg(feature = "schemars")]
+pub use schema::{body_schema, header_schema};
+#[cfg(feature = "serve")]
+pub use serve::{router, TableStore};
+pub use set::TableSet;
+pub use subscription::{parse_interval, Overrides, Subscription, TableSubscriptions};
+pub use wayback::ArchiveCapture;
 
+#[cfg(feature = "async")]
+mod audit;
+mod beatoraja;
+mod builder;
+mod bulk;
+mod cache;
+mod client;
+#[cfg(feature = "compact")]
+mod compact;
+mod date;
+mod diff;
+mod discover;
+mod events;
+mod export;
+mod fetcher;
+mod hash;
+mod id;
+mod index;
+mod level;
+mod lint;
+#[cfg(feature = "lr2ir")]
+mod lr2ir;
+mod merge;
 mod modal;
+#[cfg(feature = "async")]
+mod nonblocking;
+mod options;
 mod parser;
+mod quirk;
+mod registry;
+mod report;
+mod resolve;
+#[cfg(feature = "romaji")]
+mod romaji;
+#[cfg(feature = "schemars")]
+mod schema;
+#[cfg(feature = "serve")]
+mod serve;
+mod set;
+mod snapshot;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod strict;
+mod subscription;
+mod wayback;
+#[cfg(feature = "zip")]
+mod zip_archive;
+
+/// Every public type is `Send + Sync`, breaking this is a breaking change
+///
+/// Any caching or index added to these types must keep it (e.g. `OnceLock`/`Mutex` instead of
+/// `OnceCell`/`RefCell`, `Arc` instead of `Rc`)
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<DifficultTable>();
+    assert_send_sync::<DifficultTableCourse>();
+    assert_send_sync::<DifficultTableCourseTrophy>();
+    assert_send_sync::<DifficultTableElement>();
+    assert_send_sync::<SharedTable>();
+    assert_send_sync::<Event<'static>>();
+    assert_send_sync::<Level>();
+    assert_send_sync::<Md5>();
+    assert_send_sync::<Sha256>();
+    assert_send_sync::<TableId>();
+    assert_send_sync::<KnownTable>();
+    assert_send_sync::<TableDescriptor>();
+    assert_send_sync::<Registry>();
+    assert_send_sync::<TableSubscriptions>();
+    assert_send_sync::<TableReport>();
+    #[cfg(feature = "async")]
+    assert_send_sync::<LinkAudit>();
+    assert_send_sync::<ParseError>();
+    assert_send_sync::<FetchError>();
+    assert_send_sync::<ParseOptions>();
+    assert_send_sync::<ProgressHook>();
+    assert_send_sync::<Parser>();
+    assert_send_sync::<DiskCache>();
+    assert_send_sync::<TableCache>();
+    assert_send_sync::<ParseWarning>();
+    assert_send_sync::<TableDiff>();
+    assert_send_sync::<TableSet>();
+    assert_send_sync::<DifficultTableBuilder>();
+    assert_send_sync::<LintFinding>();
+    assert_send_sync::<BeatorajaCourse>();
+    assert_send_sync::<Quirks>();
+    assert_send_sync::<ArchiveCapture>();
+    #[cfg(feature = "compact")]
+    assert_send_sync::<CompactElement>();
+    #[cfg(feature = "serve")]
+    assert_send_sync::<TableStore>();
+    #[cfg(feature = "romaji")]
+    assert_send_sync::<Romanized>();
+    #[cfg(feature = "lr2ir")]
+    assert_send_sync::<Lr2irClient>();
+};
 
 /// Parse difficult table data from an url
 ///
-/// * `url` - difficult table url, must be valid HTTP url and be suffixed with .htm[l] or .json
+/// * `url` - difficult table url, either the table page or the header json. Urls without a .htm[l] or .json
+///   suffix are told apart by their content
 ///
 /// # Example:
 /// ```text
@@ -18,54 +162,251 @@ mod parser;
 /// let dth: DifficultTable = parse(satellite_header_url.to_string())?;
 /// ```
 pub fn parse(url: String) -> Result<DifficultTable, ParseError> {
-    if !url.starts_with("http") {
-        return Err(ParseError::UnSupportedURLFormat);
-    }
-    if !url.ends_with(".json") && !url.ends_with(".htm") && !url.ends_with(".html") {
-        return Err(ParseError::UnSupportedURLFormat);
-    }
-    let mut resp = reqwest::blocking::get(url.clone())?;
-    let mut body = String::new();
-    resp.read_to_string(&mut body)?;
-    if body.is_empty() {
-        return Err(ParseError::CorruptedHeaderData(format!(
-            "Get nothing from {}",
-            url
-        )));
-    }
-    // If url is ends with .json, then we don't do anything
-    if url.ends_with(".json") {
-        let prefix_url = url[0..=url.rfind('/').unwrap()].to_owned();
-        return parser::parse_from_json(Some(prefix_url), body);
-    }
-    // Otherwise, we need an extra step to get the header json content
-    // <meta name="bmstable" content="header.json">
-    //                                -----------> what we want
-    let meta_line = body
-        .lines()
-        .find(|line| line.contains("<meta name=\"bmstable\""))
-        .ok_or(ParseError::CorruptedHeaderData(
-            "Cannot fetch meta line".to_string(),
-        ))?;
-    let pos = meta_line
-        .find("content=")
-        .ok_or(ParseError::CorruptedHeaderData(
-            "Cannot parse meta line".to_string(),
-        ))?;
-    let l = pos + "content=".len() + 1;
-    let r = meta_line.len() - 4;
-    let mut header_url = url[0..=url.rfind('/').unwrap()].to_owned();
-    let prefix_url = header_url.clone();
-    header_url.push_str(&meta_line[l..r]);
-    let mut resp = reqwest::blocking::get(header_url)?;
-    // NOTE: don't reuse the body
-    let mut body = String::new();
-    resp.read_to_string(&mut body)?;
-    return parser::parse_from_json(Some(prefix_url), body);
+    parse_with_options(url, &ParseOptions::default())
+}
+
+/// Same as [`parse`], with [`ParseOptions`] tweaking the result
+pub fn parse_with_options(
+    url: String,
+    options: &ParseOptions,
+) -> Result<DifficultTable, ParseError> {
+    parse_with_client_and_options(&client::default_client(), &url, options)
+}
+
+/// Same as [`parse`], fetching through the caller's own client
+///
+/// Clients keep a connection pool, sharing one is much faster when parsing many tables. It's also how
+/// timeouts, proxies or TLS are configured
+///
+/// # Example:
+/// ```text
+/// let client = reqwest::blocking::Client::builder().timeout(Duration::from_secs(10)).build()?;
+/// for url in urls {
+///     let dth = parse_with_client(&client, url)?;
+/// }
+/// ```
+pub fn parse_with_client(client: &Client, url: &str) -> Result<DifficultTable, ParseError> {
+    parse_with_fetcher(client, url, &ParseOptions::default())
+}
+
+/// Combination of [`parse_with_client`] and [`parse_with_options`]
+pub fn parse_with_client_and_options(
+    client: &Client,
+    url: &str,
+    options: &ParseOptions,
+) -> Result<DifficultTable, ParseError> {
+    parse_with_fetcher(client, url, options)
+}
+
+/// Same as [`parse_with_options`], downloading everything through a [`Fetcher`]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "parse", skip(fetcher, options))
+)]
+pub fn parse_with_fetcher(
+    fetcher: &dyn Fetcher,
+    url: &str,
+    options: &ParseOptions,
+) -> Result<DifficultTable, ParseError> {
+    parse_until(fetcher, url, options, options.deadline_from_now())
+}
+
+/// Same as [`parse_with_options`], trying each of `mirrors` in order until one parses
+///
+/// Table mirrors go down all the time: the first url is the primary one, the others its fallbacks. The mirror
+/// which worked is kept in [`DifficultTable::mirror`], the error of the last one is returned when all of them
+/// fail. [`ParseOptions::deadline`] bounds the whole failover, not each mirror
+///
+/// # Example:
+/// ```text
+/// let dth = parse_mirrors(
+///     &["https://stellabms.xyz/sl/table.html", "https://mirror.example.com/sl/table.html"],
+///     &ParseOptions::default(),
+/// )?;
+/// println!("parsed from {}", dth.mirror.unwrap());
+/// ```
+pub fn parse_mirrors<S: AsRef<str>>(
+    mirrors: &[S],
+    options: &ParseOptions,
+) -> Result<DifficultTable, ParseError> {
+    parse_mirrors_with_fetcher(&client::default_client(), mirrors, options)
+}
+
+/// Same as [`parse_mirrors`], downloading everything through a [`Fetcher`]
+pub fn parse_mirrors_with_fetcher<S: AsRef<str>>(
+    fetcher: &dyn Fetcher,
+    mirrors: &[S],
+    options: &ParseOptions,
+) -> Result<DifficultTable, ParseError> {
+    let deadline = options.deadline_from_now();
+    let mut last_err = ParseError::UnSupportedURLFormat;
+    for mirror in mirrors {
+        let mirror = mirror.as_ref();
+        match parse_until(fetcher, mirror, options, deadline) {
+            Ok(mut dth) => {
+                dth.mirror = Some(mirror.to_string());
+                return Ok(dth);
+            }
+            Err(err) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(mirror, error = %err, "mirror failed");
+                last_err = err;
+            }
+        }
+    }
+    Err(last_err)
+}
+
+fn parse_until(
+    fetcher: &dyn Fetcher,
+    url: &str,
+    options: &ParseOptions,
+    deadline: Option<Instant>,
+) -> Result<DifficultTable, ParseError> {
+    let mut resolver = Resolver::new(url, options)?;
+    resolver.deadline = deadline;
+    let (mut header, body) = resolve(fetcher, url.to_string(), &mut resolver)?;
+    parser::fill_contents(&mut header, &body, options, &resolver.quirks)?;
+    Ok(header)
+}
+
+/// Parse difficult table header from an url, then deserialize its body into caller's own element type
+///
+/// The header's `contents` and `levels` are left empty, everything else (url checking, meta tag resolving,
+/// header validating) behaves the same as [`parse`]
+///
+/// # Example:
+/// ```text
+/// #[derive(Deserialize)]
+/// struct Song {
+///     md5: String,
+///     level: String,
+///     url_diff: Option<String>,
+/// }
+/// let (header, songs) = parse_into::<Song>("https://stellabms.xyz/sl/table.html".to_string())?;
+/// ```
+pub fn parse_into<T: DeserializeOwned>(url: String) -> Result<(TableHeader, Vec<T>), ParseError> {
+    let options = ParseOptions::default();
+    let (header, body) = resolve(
+        &client::default_client(),
+        url.clone(),
+        &mut Resolver::new(&url, &options)?,
+    )?;
+    let contents = serde_json::from_str(&body)?;
+    Ok((header, contents))
+}
+
+/// Fetch, resolve and validate the header behind a difficult table url, then fetch its body, see [`Resolver`]
+fn resolve(
+    fetcher: &dyn Fetcher,
+    mut url: String,
+    resolver: &mut Resolver,
+) -> Result<(TableHeader, String), ParseError> {
+    let options = resolver.options();
+    let mut page = resolver.header_page(parser::fetch_header(
+        fetcher,
+        &url,
+        options,
+        resolver.deadline,
+    )?)?;
+    loop {
+        let mut header = follow(fetcher, &mut url, page, resolver)?;
+        let fetched = parser::fetch_body(fetcher, &header.data_url, options, resolver.deadline)?;
+        match resolver.body(&mut header, fetched)? {
+            Document::Body(body) => return Ok((*header, body)),
+            Document::HeaderPage(next_page) => {
+                url = header.data_url;
+                page = next_page;
+            }
+        }
+    }
+}
+
+/// Follow header pages from `page`, fetched from `url`, until the header
+fn follow(
+    fetcher: &dyn Fetcher,
+    url: &mut String,
+    mut page: String,
+    resolver: &mut Resolver,
+) -> Result<Box<TableHeader>, ParseError> {
+    loop {
+        match resolver.step(url, &page)? {
+            Step::Follow(next) => {
+                // NOTE: don't reuse the body
+                page = resolver.header_page(parser::fetch_header(
+                    fetcher,
+                    &next,
+                    resolver.options(),
+                    resolver.deadline,
+                )?)?;
+                *url = next;
+            }
+            Step::Header(header) => return Ok(header),
+        }
+    }
+}
+
+/// Whether the table behind `url` changed since `cached` was parsed from it, see [`has_update_with_fetcher`]
+///
+/// # Example:
+/// ```text
+/// if has_update(&cached, url)? {
+///     cached = parse(url.to_string())?;
+/// }
+/// ```
+pub fn has_update(cached: &DifficultTable, url: &str) -> Result<bool, ParseError> {
+    has_update_with_fetcher(
+        &client::default_client(),
+        cached,
+        url,
+        &ParseOptions::default(),
+    )
+}
+
+/// Same as [`has_update`], downloading through a [`Fetcher`], with the [`ParseOptions`] `cached` was parsed with
+///
+/// Only the header pages are fetched when both headers have a `last_update`, which tables bump on every change, or
+/// when the data_url moved. Otherwise the body is fetched and compared too: pass a [`DiskCache`] so that it's
+/// revalidated with a conditional GET rather than downloaded again
+pub fn has_update_with_fetcher(
+    fetcher: &dyn Fetcher,
+    cached: &DifficultTable,
+    url: &str,
+    options: &ParseOptions,
+) -> Result<bool, ParseError> {
+    let mut resolver = Resolver::new(url, options)?;
+    let page = resolver.header_page(parser::fetch_header(
+        fetcher,
+        url,
+        options,
+        resolver.deadline,
+    )?)?;
+    let header = follow(fetcher, &mut url.to_string(), page, &mut resolver)?;
+    if header.data_url != cached.data_url {
+        return Ok(true);
+    }
+    if !header.last_update.is_empty() && !cached.last_update.is_empty() {
+        return Ok(header.last_update != cached.last_update);
+    }
+    let latest = parse_with_fetcher(fetcher, url, options)?;
+    Ok(latest.content_hash() != cached.content_hash())
+}
+
+/// Same as [`parse`], but returns a [`SharedTable`] which could be handed to multiple threads
+pub fn parse_shared(url: String) -> Result<SharedTable, ParseError> {
+    parse(url).map(DifficultTable::into_shared)
 }
 
 #[cfg(test)]
+#[allow(clippy::useless_vec, clippy::len_zero)]
 mod tests {
+    use std::{
+        io,
+        sync::{Arc, Mutex},
+        thread,
+        time::Duration,
+    };
+
     use super::*;
 
     #[test]
@@ -78,6 +419,300 @@ mod tests {
         assert!(test_cases.iter().all(|url| parse(url.to_string()).is_err()));
     }
 
+    #[test]
+    pub fn should_tell_header_and_body_fetch_failures_apart() {
+        // nothing listens on port 1
+        let err = parse("http://127.0.0.1:1/header.json".to_string()).unwrap_err();
+        assert!(
+            matches!(err, ParseError::HeaderFetch { url, .. } if url == "http://127.0.0.1:1/header.json")
+        );
+        let header = r#"{ "name": "Satellite", "symbol": "sl", "data_url": "http://127.0.0.1:1/body.json" }"#;
+        let err = parse_from_json(None, header.to_string()).unwrap_err();
+        assert!(
+            matches!(err, ParseError::BodyFetch { url, .. } if url == "http://127.0.0.1:1/body.json")
+        );
+    }
+
+    #[test]
+    pub fn should_parse_local_files_when_allowed() {
+        let dir = std::env::temp_dir().join(format!("rbmstable-parser-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("table.html"),
+            r#"<meta name="bmstable" content="header.json">"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("header.json"),
+            r#"{ "name": "Local", "symbol": "l", "data_url": "body.json" }"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("body.json"),
+            r#"[{ "title": "a", "artist": "", "md5": "", "level": "1" }]"#,
+        )
+        .unwrap();
+        let url = format!("file://{}/table.html", dir.display());
+
+        assert!(matches!(
+            parse(url.clone()),
+            Err(ParseError::UnsupportedScheme(scheme)) if scheme == "file"
+        ));
+        let mut options = ParseOptions::default();
+        options.allowed_schemes.push("file".to_string());
+        let dth = parse_with_options(url.clone(), &options).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(dth.name, "Local");
+        assert_eq!(dth.levels, [Level::from("1")]);
+        assert!(matches!(
+            parse_with_options(url.clone(), &options),
+            Err(ParseError::HeaderFetch { url: failed, source: FetchError::Io(_) }) if failed == url
+        ));
+    }
+
+    #[test]
+    pub fn should_hand_custom_schemes_to_the_fetcher() {
+        let fetcher = |url: &str| -> Result<Vec<u8>, FetchError> {
+            let page: &str = match url {
+                "ipfs:QmHeader" => {
+                    r#"{ "name": "Pinned", "symbol": "p", "data_url": "ipfs:QmBody" }"#
+                }
+                "ipfs:QmRelative" => {
+                    r#"{ "name": "Pinned", "symbol": "p", "data_url": "body.json" }"#
+                }
+                "ipfs:QmBody" => r#"[{ "title": "a", "artist": "", "md5": "", "level": "1" }]"#,
+                _ => return Err(FetchError::NotFound(url.to_string())),
+            };
+            Ok(page.as_bytes().to_vec())
+        };
+        let mut options = ParseOptions::default();
+        options.allowed_schemes.push("ipfs".to_string());
+        let dth = parse_with_fetcher(&fetcher, "ipfs:QmHeader", &options).unwrap();
+        assert_eq!(dth.name, "Pinned");
+        assert_eq!(dth.contents.len(), 1);
+        assert!(matches!(
+            parse_with_fetcher(&fetcher, "ipfs:QmRelative", &options),
+            Err(ParseError::InvalidDataUrl(..))
+        ));
+    }
+
+    #[test]
+    pub fn should_parse_through_a_fetcher() {
+        let fetcher = |url: &str| -> Result<Vec<u8>, FetchError> {
+            let page: &str = match url {
+                "https://example.com/table.html" => {
+                    r#"<meta name="bmstable" content="header.json">"#
+                }
+                "https://example.com/header.json" => {
+                    r#"{ "name": "Canned", "symbol": "c", "data_url": "https://gone.example.com/body.json" }"#
+                }
+                _ if url.starts_with("https://archive.org/wayback/available") => {
+                    r#"{ "archived_snapshots": { "closest": { "available": true, "timestamp": "20150908052343" } } }"#
+                }
+                "https://web.archive.org/web/20150908052343id_/https://gone.example.com/body.json" => {
+                    r#"[{ "title": "a", "artist": "", "md5": "", "level": "1" }]"#
+                }
+                _ => return Err(FetchError::NotFound(url.to_string())),
+            };
+            Ok(page.as_bytes().to_vec())
+        };
+        let url = "https://example.com/table.html";
+        let mut options = ParseOptions::default();
+        assert!(matches!(
+            parse_with_fetcher(&fetcher, url, &options),
+            Err(ParseError::BodyFetch {
+                source: FetchError::NotFound(_),
+                ..
+            })
+        ));
+        options.wayback_fallback = true;
+        let dth = parse_with_fetcher(&fetcher, url, &options).unwrap();
+        assert_eq!(dth.name, "Canned");
+        assert_eq!(dth.contents.len(), 1);
+        assert_eq!(
+            dth.archive.unwrap().url,
+            "https://gone.example.com/body.json"
+        );
+    }
+
+    #[test]
+    pub fn should_flip_schemes_on_connection_failure() {
+        let fetcher = |url: &str| -> Result<Vec<u8>, FetchError> {
+            let page: &str = match url {
+                "https://example.com/header.json" => {
+                    r#"{ "name": "Moved", "symbol": "m", "data_url": "http://old.example.com/body.json" }"#
+                }
+                "http://old.example.com/body.json" => "[]",
+                _ => return Err(io::Error::from(io::ErrorKind::ConnectionRefused).into()),
+            };
+            Ok(page.as_bytes().to_vec())
+        };
+        let url = "http://example.com/header.json";
+        let mut options = ParseOptions::default();
+        assert!(matches!(
+            parse_with_fetcher(&fetcher, url, &options),
+            Err(ParseError::HeaderFetch { .. })
+        ));
+        options.scheme_fallback = true;
+        let dth = parse_with_fetcher(&fetcher, url, &options).unwrap();
+        assert_eq!(dth.name, "Moved");
+        assert_eq!(
+            dth.fetched_urls,
+            [
+                "https://example.com/header.json",
+                "http://old.example.com/body.json"
+            ]
+        );
+        options.http_policy = HttpPolicy::Reject;
+        assert!(
+            parse_with_fetcher(&fetcher, "https://old.example.com/body.json", &options).is_err()
+        );
+    }
+
+    #[test]
+    pub fn should_fail_over_to_mirrors() {
+        let fetcher = |url: &str| -> Result<Vec<u8>, FetchError> {
+            let page: &str = match url {
+                "https://mirror.example.com/header.json" => {
+                    r#"{ "name": "Mirrored", "symbol": "m", "data_url": "body.json" }"#
+                }
+                "https://mirror.example.com/body.json" => "[]",
+                _ => return Err(FetchError::NotFound(url.to_string())),
+            };
+            Ok(page.as_bytes().to_vec())
+        };
+        let options = ParseOptions::default();
+        let mirrors = [
+            "https://example.com/header.json",
+            "https://mirror.example.com/header.json",
+        ];
+        let dth = parse_mirrors_with_fetcher(&fetcher, &mirrors, &options).unwrap();
+        assert_eq!(dth.name, "Mirrored");
+        assert_eq!(dth.mirror.as_deref(), Some(mirrors[1]));
+        assert!(matches!(
+            parse_mirrors_with_fetcher(&fetcher, &mirrors[..1], &options),
+            Err(ParseError::HeaderFetch { url, .. }) if url == mirrors[0]
+        ));
+        assert!(parse_mirrors_with_fetcher(&fetcher, &[] as &[&str], &options).is_err());
+    }
+
+    #[test]
+    pub fn should_check_for_updates_without_the_body() {
+        let header = Mutex::new(
+            r#"{ "name": "t", "symbol": "t", "data_url": "body.json", "last_update": "2024-01-01" }"#,
+        );
+        let body = Mutex::new("[]");
+        let body_fetches = Mutex::new(0);
+        let fetcher = |url: &str| -> Result<Vec<u8>, FetchError> {
+            let page = match url {
+                "https://example.com/header.json" => *header.lock().unwrap(),
+                "https://example.com/body.json" => {
+                    *body_fetches.lock().unwrap() += 1;
+                    *body.lock().unwrap()
+                }
+                _ => return Err(FetchError::NotFound(url.to_string())),
+            };
+            Ok(page.as_bytes().to_vec())
+        };
+        let url = "https://example.com/header.json";
+        let options = ParseOptions::default();
+        let cached = parse_with_fetcher(&fetcher, url, &options).unwrap();
+        assert!(!has_update_with_fetcher(&fetcher, &cached, url, &options).unwrap());
+        *header.lock().unwrap() = r#"{ "name": "t", "symbol": "t", "data_url": "body.json", "last_update": "2024-02-01" }"#;
+        assert!(has_update_with_fetcher(&fetcher, &cached, url, &options).unwrap());
+        assert_eq!(*body_fetches.lock().unwrap(), 1);
+
+        // without last_update, the body tells
+        *header.lock().unwrap() = r#"{ "name": "t", "symbol": "t", "data_url": "body.json" }"#;
+        let cached = parse_with_fetcher(&fetcher, url, &options).unwrap();
+        assert!(!has_update_with_fetcher(&fetcher, &cached, url, &options).unwrap());
+        *body.lock().unwrap() = r#"[{ "title": "a", "artist": "", "md5": "", "level": "1" }]"#;
+        assert!(has_update_with_fetcher(&fetcher, &cached, url, &options).unwrap());
+    }
+
+    #[test]
+    pub fn should_report_download_progress() {
+        let header = r#"{ "name": "Canned", "symbol": "c", "data_url": "body.json" }"#;
+        let body = r#"[{ "title": "a", "artist": "", "md5": "", "level": "1" }]"#;
+        let fetcher = |url: &str| -> Result<Vec<u8>, FetchError> {
+            match url {
+                "https://example.com/header.json" => Ok(header.as_bytes().to_vec()),
+                "https://example.com/body.json" => Ok(body.as_bytes().to_vec()),
+                _ => Err(FetchError::NotFound(url.to_string())),
+            }
+        };
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let options = ParseOptions::default().on_progress({
+            let reports = reports.clone();
+            move |stage, done, total| reports.lock().unwrap().push((stage, done, total))
+        });
+        parse_with_fetcher(&fetcher, "https://example.com/header.json", &options).unwrap();
+        let (header, body) = (header.len() as u64, body.len() as u64);
+        assert_eq!(
+            *reports.lock().unwrap(),
+            [
+                (Stage::Header, header, Some(header)),
+                (Stage::Body, body, Some(body))
+            ]
+        );
+    }
+
+    #[test]
+    pub fn should_give_up_past_the_deadline() {
+        let slow = |url: &str| -> Result<Vec<u8>, FetchError> {
+            thread::sleep(Duration::from_millis(20));
+            match url {
+                "https://example.com/header.json" => {
+                    Ok(br#"{ "name": "Slow", "symbol": "s", "data_url": "body.json" }"#.to_vec())
+                }
+                _ => Ok(b"[]".to_vec()),
+            }
+        };
+        let url = "https://example.com/header.json";
+        let options = |deadline| ParseOptions {
+            deadline: Some(deadline),
+            ..Default::default()
+        };
+        assert!(matches!(
+            parse_with_fetcher(&slow, url, &options(Duration::from_millis(30))),
+            Err(ParseError::BodyFetch {
+                source: FetchError::DeadlineExceeded,
+                ..
+            })
+        ));
+        assert!(parse_with_fetcher(&slow, url, &options(Duration::from_secs(10))).is_ok());
+    }
+
+    #[test]
+    pub fn should_sniff_urls_without_suffix() {
+        let fetcher = |url: &str| -> Result<Vec<u8>, FetchError> {
+            let page: &str = match url {
+                "https://example.com/table" => {
+                    "<html><head><meta name=\"bmstable\" content=\"api?part=header\"></head></html>"
+                }
+                "https://example.com/api?part=header" => {
+                    r#"{ "name": "Sniffed", "symbol": "s", "data_url": "api?part=body" }"#
+                }
+                "https://example.com/api?part=body" => "[]",
+                "https://example.com/readme" => "nothing to see here",
+                _ => return Err(FetchError::NotFound(url.to_string())),
+            };
+            Ok(page.as_bytes().to_vec())
+        };
+        let options = ParseOptions::default();
+        for url in [
+            "https://example.com/table",
+            "https://example.com/api?part=header",
+        ] {
+            let dth = parse_with_fetcher(&fetcher, url, &options).unwrap();
+            assert_eq!(dth.name, "Sniffed");
+        }
+        assert!(matches!(
+            parse_with_fetcher(&fetcher, "https://example.com/readme", &options),
+            Err(ParseError::UnSupportedURLFormat)
+        ));
+    }
+
     /// basic parse api test
     ///
     /// Parse difficult table data from below urls:
@@ -132,4 +767,26 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    pub fn shared_table_should_be_readable_from_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SharedTable>();
+
+        let mut table: DifficultTable = serde_json::from_str(
+            r#"{ "name": "Satellite", "symbol": "sl", "data_url": "body.json" }"#,
+        )
+        .unwrap();
+        table.levels = vec![Level::from("0"), Level::from("1")];
+        let table = table.into_shared();
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let table = SharedTable::clone(&table);
+                thread::spawn(move || table.levels.len())
+            })
+            .collect();
+        assert!(handles
+            .into_iter()
+            .all(|handle| handle.join().unwrap() == 2));
+    }
 }
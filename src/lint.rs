@@ -0,0 +1,226 @@
+use std::{collections::HashSet, fmt};
+
+use crate::{
+    hash::{Md5, Sha256},
+    level::Level,
+    modal::DifficultTable,
+    report::looks_dead,
+};
+
+/// How bad a [`LintFinding`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The table is broken for players, e.g. a chart can't be matched
+    Error,
+    /// Worth fixing, but the table works
+    Warning,
+}
+
+/// Where a [`LintFinding`] is
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LintLocation {
+    Header,
+    /// The contents as a whole
+    Contents,
+    /// Position in `contents`
+    Element(usize),
+    /// Course name
+    Course(String),
+}
+
+/// One problem found by [`lint`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintFinding {
+    pub severity: Severity,
+    pub location: LintLocation,
+    pub message: String,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        })
+    }
+}
+
+impl fmt::Display for LintLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintLocation::Header => f.write_str("header"),
+            LintLocation::Contents => f.write_str("contents"),
+            LintLocation::Element(pos) => write!(f, "contents[{pos}]"),
+            LintLocation::Course(name) => write!(f, "course `{name}`"),
+        }
+    }
+}
+
+impl fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.severity, self.location, self.message)
+    }
+}
+
+impl LintFinding {
+    fn error(location: LintLocation, message: impl Into<String>) -> Self {
+        LintFinding {
+            severity: Severity::Error,
+            location,
+            message: message.into(),
+        }
+    }
+
+    fn warning(location: LintLocation, message: impl Into<String>) -> Self {
+        LintFinding {
+            severity: Severity::Warning,
+            location,
+            message: message.into(),
+        }
+    }
+}
+
+/// Authoring problems of a table, header first, then elements in order, then courses
+///
+/// Checks for empty header fields, malformed and duplicate hashes, missing sha256s, empty titles and levels,
+/// levels missing from `level_order`, an `original_url` that looks unreachable (nothing is fetched) and course
+/// charts missing from contents
+///
+/// # Example:
+/// ```text
+/// for finding in lint(&dth) {
+///     eprintln!("{finding}");
+/// }
+/// ```
+pub fn lint(table: &DifficultTable) -> Vec<LintFinding> {
+    let mut findings = Vec::new();
+    for (field, value) in [
+        ("name", &table.name),
+        ("symbol", &table.symbol),
+        ("data_url", &table.data_url),
+    ] {
+        if value.is_empty() {
+            findings.push(LintFinding::error(
+                LintLocation::Header,
+                format!("{field} cannot be empty"),
+            ));
+        }
+    }
+    if !table.original_url.is_empty() && looks_dead(&table.original_url) {
+        findings.push(LintFinding::warning(
+            LintLocation::Header,
+            format!("original_url `{}` looks unreachable", table.original_url),
+        ));
+    }
+
+    let duplicates = table.duplicates();
+    let mut missing_sha256 = 0;
+    for (pos, element) in table.contents.iter().enumerate() {
+        let location = || LintLocation::Element(pos);
+        if element.level.as_str().is_empty() {
+            findings.push(LintFinding::error(location(), "level cannot be empty"));
+        }
+        if element.title.is_empty() {
+            findings.push(LintFinding::warning(location(), "title is empty"));
+        }
+        if element.md5.is_empty() {
+            findings.push(LintFinding::warning(location(), "md5 is empty"));
+        } else if let Err(err) = element.md5.parse::<Md5>() {
+            findings.push(LintFinding::error(location(), err.to_string()));
+        }
+        if element.sha256.is_empty() {
+            missing_sha256 += 1;
+        } else if let Err(err) = element.sha256.parse::<Sha256>() {
+            findings.push(LintFinding::error(location(), err.to_string()));
+        }
+        for duplicate in &duplicates {
+            if duplicate.positions[0] != pos && duplicate.positions.contains(&pos) {
+                let first = &table.contents[duplicate.positions[0]];
+                findings.push(LintFinding::warning(
+                    location(),
+                    format!(
+                        "{} `{}` is also used by `{}` ({}{})",
+                        duplicate.field, duplicate.hash, first.title, table.symbol, first.level
+                    ),
+                ));
+            }
+        }
+    }
+    if missing_sha256 > 0 {
+        findings.push(LintFinding::warning(
+            LintLocation::Contents,
+            format!("{missing_sha256} element(s) have no sha256"),
+        ));
+    }
+    if !table.level_order.is_empty() {
+        let listed: HashSet<&Level> = table.level_order.iter().collect();
+        for level in &table.levels {
+            if !listed.contains(level) {
+                findings.push(LintFinding::warning(
+                    LintLocation::Contents,
+                    format!("level `{level}` is not listed in level_order"),
+                ));
+            }
+        }
+    }
+
+    for course in &table.courses {
+        for md5 in &course.md5 {
+            if md5
+                .parse()
+                .ok()
+                .and_then(|md5| table.find_by_md5(md5))
+                .is_none()
+            {
+                findings.push(LintFinding::warning(
+                    LintLocation::Course(course.name.clone()),
+                    format!("md5 `{md5}` is not present in contents"),
+                ));
+            }
+        }
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    pub fn should_lint_authoring_mistakes() {
+        let md5 = "0".repeat(32);
+        let mut table: DifficultTable = serde_json::from_value(json!({
+            "name": "Satellite",
+            "symbol": "sl",
+            "data_url": "body.json",
+            "original_url": "http://nekokan.dyndns.info/~lobsak/genocide/insane.html",
+            "level_order": ["0"],
+            "course": [[{ "name": "sl0", "constraint": [], "trophy": [], "md5": [md5, "f".repeat(32)] }]],
+        }))
+        .unwrap();
+        table.contents = serde_json::from_value(json!([
+            { "title": "a", "artist": "", "md5": md5, "sha256": "1".repeat(64), "level": "0" },
+            { "title": "", "artist": "", "md5": md5.to_uppercase(), "sha256": "1".repeat(64), "level": "0" },
+            { "title": "c", "artist": "", "md5": "not a md5", "sha256": "short", "level": "1" },
+        ]))
+        .unwrap();
+        table.refresh_levels(crate::LevelOrdering::Sorted);
+
+        let findings: Vec<String> = lint(&table).iter().map(ToString::to_string).collect();
+        assert_eq!(
+            findings,
+            [
+                "warning: header: original_url `http://nekokan.dyndns.info/~lobsak/genocide/insane.html` looks unreachable".to_string(),
+                "warning: contents[1]: title is empty".to_string(),
+                format!("warning: contents[1]: md5 `{md5}` is also used by `a` (sl0)"),
+                format!("warning: contents[1]: sha256 `{}` is also used by `a` (sl0)", "1".repeat(64)),
+                "error: contents[2]: Invalid hash: `not a md5` is not a 32 hex md5 hash".to_string(),
+                "error: contents[2]: Invalid hash: `short` is not a 64 hex sha256 hash".to_string(),
+                "warning: contents: level `1` is not listed in level_order".to_string(),
+                format!("warning: course `sl0`: md5 `{}` is not present in contents", "f".repeat(32)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{
+    client::default_client, modal::DifficultTable, options::ParseOptions, parser,
+    parser::ParseError,
+};
+
+const RANKING_API: &str = "http://www.dream-pro.info/~lavalse/LR2IR/2/getrankingxml.cgi";
+
+/// Clear statistics of one chart on LR2IR, see [`Lr2irClient`]
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ChartStats {
+    pub players: usize,
+    pub failed: usize,
+    pub easy: usize,
+    pub normal: usize,
+    pub hard: usize,
+    pub full_combo: usize,
+}
+
+impl ChartStats {
+    /// Ratio of players who cleared the chart (easy or better), 0 when nobody played it
+    pub fn clear_rate(&self) -> f64 {
+        if self.players == 0 {
+            return 0.0;
+        }
+        (self.easy + self.normal + self.hard + self.full_combo) as f64 / self.players as f64
+    }
+}
+
+/// Blocking LR2IR client, enabled by the `lr2ir` feature
+///
+/// Requests are spaced by at least [`Lr2irClient::min_interval`] (LR2IR is a single, old server) and results are
+/// cached by md5 for the lifetime of the client
+#[derive(Debug)]
+pub struct Lr2irClient {
+    client: Client,
+    min_interval: Duration,
+    last_request: Mutex<Option<Instant>>,
+    cache: Mutex<HashMap<String, ChartStats>>,
+}
+
+impl Default for Lr2irClient {
+    fn default() -> Self {
+        Lr2irClient::new(Duration::from_secs(1))
+    }
+}
+
+impl Lr2irClient {
+    pub fn new(min_interval: Duration) -> Self {
+        Lr2irClient {
+            client: default_client(),
+            min_interval,
+            last_request: Mutex::new(None),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn min_interval(&self) -> Duration {
+        self.min_interval
+    }
+
+    /// Clear statistics of the chart with this md5, a chart unknown to LR2IR has no player
+    pub fn chart_stats(&self, md5: &str) -> Result<ChartStats, ParseError> {
+        let md5 = md5.to_ascii_lowercase();
+        if let Some(stats) = self
+            .cache
+            .lock()
+            .expect("lr2ir cache is poisoned")
+            .get(&md5)
+        {
+            return Ok(*stats);
+        }
+        self.wait_turn();
+        let url = Url::parse_with_params(RANKING_API, [("id", "1"), ("songmd5", &md5)])
+            .expect("ranking api url is valid");
+        let raw = parser::fetch_ranking(&self.client, url.as_str(), &ParseOptions::default())?.raw;
+        let stats = parse_ranking(&String::from_utf8_lossy(&raw));
+        self.cache
+            .lock()
+            .expect("lr2ir cache is poisoned")
+            .insert(md5, stats);
+        Ok(stats)
+    }
+
+    /// Sleep until the next request is allowed
+    fn wait_turn(&self) {
+        let mut last_request = self.last_request.lock().expect("lr2ir client is poisoned");
+        if let Some(elapsed) = last_request.map(|last| last.elapsed()) {
+            if let Some(remaining) = self.min_interval.checked_sub(elapsed) {
+                thread::sleep(remaining);
+            }
+        }
+        *last_request = Some(Instant::now());
+    }
+}
+
+/// Count players and clear lamps of a ranking xml, only the `<clear>` tags are read
+///
+/// LR2IR lamps: 1 failed, 2 easy, 3 normal, 4 hard, 5 full combo
+fn parse_ranking(xml: &str) -> ChartStats {
+    let mut stats = ChartStats::default();
+    for clear in xml.split("<clear>").skip(1) {
+        let lamp = clear.split('<').next().unwrap_or_default().trim();
+        stats.players += 1;
+        match lamp {
+            "1" => stats.failed += 1,
+            "2" => stats.easy += 1,
+            "3" => stats.normal += 1,
+            "4" => stats.hard += 1,
+            "5" => stats.full_combo += 1,
+            _ => {}
+        }
+    }
+    stats
+}
+
+impl DifficultTable {
+    /// Attach LR2IR statistics to every element with an md5, returns the failures by md5
+    ///
+    /// This blocks on network for a while, one request per uncached chart spaced by the client's interval
+    pub fn attach_lr2ir(&mut self, client: &Lr2irClient) -> Vec<(String, ParseError)> {
+        let mut failures = Vec::new();
+        for content in &mut self.contents {
+            if content.md5.is_empty() {
+                continue;
+            }
+            match client.chart_stats(&content.md5) {
+                Ok(stats) => content.lr2ir = Some(stats),
+                Err(err) => failures.push((content.md5.clone(), err)),
+            }
+        }
+        failures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn should_count_clear_lamps() {
+        let xml = "#<?xml version=\"1.0\" encoding=\"shift_jis\"?>\
+            <ranking>\
+            <score><name>a</name><clear>5</clear><notes>1000</notes></score>\
+            <score><name>b</name><clear>2</clear><notes>1000</notes></score>\
+            <score><name>c</name><clear>1</clear><notes>1000</notes></score>\
+            </ranking>";
+        let stats = parse_ranking(xml);
+        assert_eq!(stats.players, 3);
+        assert_eq!(stats.full_combo, 1);
+        assert_eq!(stats.failed, 1);
+        assert!((stats.clear_rate() - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(
+            parse_ranking("#<?xml?><ranking></ranking>"),
+            ChartStats::default()
+        );
+    }
+}
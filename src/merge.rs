@@ -0,0 +1,145 @@
+use crate::{
+    diff::match_contents,
+    level::Level,
+    modal::{DifficultTable, DifficultTableCourse, DifficultTableElement},
+    options::LevelOrdering,
+};
+
+/// What [`DifficultTable::merge`] does with a song listed by both tables
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// Keep the song (and course) of the table merged into
+    #[default]
+    PreferLeft,
+    /// Keep the song (and course) of the other table, at the position of the replaced one
+    PreferRight,
+    /// Keep both songs, every level is prefixed by its table's symbol (e.g. `★1` and `★★1`) so that levels of
+    /// both tables never mix
+    KeepBoth,
+}
+
+impl DifficultTable {
+    /// Combined table of this one followed by `other`, e.g. an "insane + overjoy" view
+    ///
+    /// The header (name, symbol, urls...) is this table's, header fields unknown to this crate are taken from
+    /// `other` when this table lacks them. Songs are matched by sha256 first, then by md5, see [`crate::diff`]
+    ///
+    /// # Example:
+    /// ```text
+    /// let mut combined = insane.merge(&overjoy, MergeStrategy::KeepBoth);
+    /// combined.name = "Insane + Overjoy".to_string();
+    /// ```
+    pub fn merge(&self, other: &DifficultTable, strategy: MergeStrategy) -> DifficultTable {
+        let mut merged = self.clone();
+        merged.warnings.clear();
+        match strategy {
+            MergeStrategy::KeepBoth => {
+                merged.contents = prefixed_contents(self)
+                    .chain(prefixed_contents(other))
+                    .collect();
+                merged.level_order = prefixed_levels(self)
+                    .chain(prefixed_levels(other))
+                    .collect();
+                merged.courses.extend(other.courses.iter().cloned());
+            }
+            MergeStrategy::PreferLeft | MergeStrategy::PreferRight => {
+                let prefer_right = strategy == MergeStrategy::PreferRight;
+                let matches = match_contents(&self.contents, &other.contents);
+                for (element, found) in other.contents.iter().zip(matches) {
+                    match found {
+                        None => merged.contents.push(element.clone()),
+                        Some(pos) if prefer_right => merged.contents[pos] = element.clone(),
+                        Some(_) => {}
+                    }
+                }
+                for level in &other.level_order {
+                    if !merged.level_order.contains(level) {
+                        merged.level_order.push(level.clone());
+                    }
+                }
+                merge_courses(&mut merged.courses, &other.courses, prefer_right);
+            }
+        }
+        for (key, value) in &other.extra {
+            if !merged.extra.contains_key(key) {
+                merged.extra.insert(key.clone(), value.clone());
+            }
+        }
+        merged.refresh_levels(LevelOrdering::Sorted);
+        merged
+    }
+}
+
+fn prefixed_contents(table: &DifficultTable) -> impl Iterator<Item = DifficultTableElement> + '_ {
+    table.contents.iter().map(|element| DifficultTableElement {
+        level: Level::from(format!("{}{}", table.symbol, element.level)),
+        ..element.clone()
+    })
+}
+
+/// `levels` rather than `level_order`, which is often missing
+fn prefixed_levels(table: &DifficultTable) -> impl Iterator<Item = Level> + '_ {
+    table
+        .levels
+        .iter()
+        .map(|level| Level::from(format!("{}{level}", table.symbol)))
+}
+
+/// Courses are matched by name
+fn merge_courses(
+    courses: &mut Vec<DifficultTableCourse>,
+    others: &[DifficultTableCourse],
+    prefer_right: bool,
+) {
+    for course in others {
+        match courses.iter().position(|listed| listed.name == course.name) {
+            None => courses.push(course.clone()),
+            Some(pos) if prefer_right => courses[pos] = course.clone(),
+            Some(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn table(symbol: &str, songs: &[(&str, &str, &str)]) -> DifficultTable {
+        let contents: Vec<DifficultTableElement> = songs
+            .iter()
+            .map(|(title, md5, level)| {
+                serde_json::from_value(
+                    json!({ "title": title, "artist": "", "md5": md5, "level": level }),
+                )
+                .unwrap()
+            })
+            .collect();
+        let header = serde_json::from_value(json!({ "name": symbol, "symbol": symbol })).unwrap();
+        DifficultTable::from_parts(header, contents, Vec::new())
+    }
+
+    #[test]
+    pub fn should_merge_duplicates_by_strategy() {
+        let insane = table("★", &[("a", "aa", "1"), ("b", "bb", "2")]);
+        let overjoy = table("★★", &[("B", "BB", "1"), ("c", "cc", "1")]);
+        let titles = |table: &DifficultTable| -> Vec<String> {
+            table.contents.iter().map(|e| e.title.clone()).collect()
+        };
+
+        let merged = insane.merge(&overjoy, MergeStrategy::PreferLeft);
+        assert_eq!(merged.name, "★");
+        assert_eq!(titles(&merged), ["a", "b", "c"]);
+        assert_eq!(merged.levels, ["1", "2"].map(Level::from));
+
+        let merged = insane.merge(&overjoy, MergeStrategy::PreferRight);
+        assert_eq!(titles(&merged), ["a", "B", "c"]);
+        assert_eq!(merged.levels, [Level::from("1")]);
+
+        let merged = insane.merge(&overjoy, MergeStrategy::KeepBoth);
+        assert_eq!(titles(&merged), ["a", "b", "B", "c"]);
+        assert_eq!(merged.levels, ["★1", "★2", "★★1"].map(Level::from));
+        assert_eq!(merged.contents[2].level, "★★1");
+    }
+}
@@ -1,16 +1,56 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+
+use crate::{
+    date::{parse_last_update, string_or_number},
+    hash::{Md5, Sha256},
+    id::TableId,
+    index::ContentIndex,
+    level::Level,
+    options::LevelOrdering,
+    parser::ParseWarning,
+    wayback::ArchiveCapture,
+};
+
+/// A parsed table shared between threads or views without cloning it
+///
+/// Every method on [`DifficultTable`] takes `&self` (or is explicitly mutating, which requires unique
+/// ownership through [`Arc::get_mut`] / [`Arc::make_mut`]), and [`DifficultTable`] is `Send + Sync`
+pub type SharedTable = Arc<DifficultTable>;
+
+/// Header part of a difficult table, i.e. a [`DifficultTable`] whose `contents` and `levels` are left empty
+///
+/// Returned by [`crate::parse_into`], where contents are deserialized into the caller's own type
+pub type TableHeader = DifficultTable;
 
 /// Represents one difficult table meta info
 ///
 /// NOTE: name, symbol, data_url must presents and must be non-empty value, and data_url should be a valid url
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct DifficultTable {
     /// BMS difficult table name
+    #[cfg_attr(feature = "schemars", schemars(length(min = 1)))]
     pub name: String,
     /// BMS difficult table symbol
+    #[cfg_attr(feature = "schemars", schemars(length(min = 1)))]
     pub symbol: String,
-    /// BMS difficult table last update time (not used)
-    #[serde(default)]
+    /// BMS difficult table last update time, kept as is
+    ///
+    /// The format differs from table to table, see [`DifficultTable::last_update_utc`] for a normalized one.
+    /// A number (epoch seconds or millis) is accepted and kept as its decimal string
+    #[serde(default, deserialize_with = "string_or_number")]
+    #[cfg_attr(
+        feature = "schemars",
+        schemars(schema_with = "crate::schema::string_or_number")
+    )]
     pub last_update: String,
     /// BMS difficult table tag (unkown field)
     #[serde(default)]
@@ -22,6 +62,7 @@ pub struct DifficultTable {
     /// TODO: The old jbmstable-parser shows that this field might be a list of urls.
     /// For now, we don't care
     #[serde(default)]
+    #[cfg_attr(feature = "schemars", schemars(length(min = 1)))]
     pub data_url: String,
     /// Unkown field
     #[serde(default)]
@@ -38,21 +79,34 @@ pub struct DifficultTable {
     #[serde(default)]
     pub original_url: String,
     /// BMS difficult table related contents
-    #[serde(skip_deserializing)]
+    ///
+    /// Elements are in the body json's order, unless the table is parsed with [`crate::ContentOrdering::ByLevel`]
+    ///
+    /// Mutating this field directly doesn't update `levels` (nor the index behind
+    /// [`DifficultTable::find_by_md5`]), prefer [`DifficultTable::add_element`] and
+    /// [`DifficultTable::retain_elements`]
+    ///
+    /// Deserialized only so that a serialized table can be loaded back, the parser ignores a header's `contents`
+    #[serde(default)]
     pub contents: Vec<DifficultTableElement>,
     /// BMS difficult table related levels
     ///
-    /// This field is ensured to be sorted, which comparison rule between lhs and rhs is definied as:
-    /// * if lhs and rhs are both numbers, then compare them as number
-    /// * if any of them are not number, then compare them as string
-    /// The level field is forced to be existed and cannot be empty, so there is no other cases
+    /// This field follows `level_order` when the header has one. Otherwise (and for levels missing from it)
+    /// it's sorted by [`Level`]'s ordering, unless the table is parsed with [`LevelOrdering::Appearance`]
     ///
     /// # Example:
     /// ```text
     /// [0, 1, 2, 3, ..., 24, 25, ???]
     /// ```
-    #[serde(skip_deserializing)]
-    pub levels: Vec<String>,
+    ///
+    /// Same as `contents`, deserialized for round trips only
+    #[serde(default)]
+    pub levels: Vec<Level>,
+    /// Intended display order of levels, e.g. `["0", "1", ..., "X", "?"]`, empty if the header has none
+    ///
+    /// Numbers are accepted and kept as their decimal string
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub level_order: Vec<Level>,
     /// BMS difficult table related courses
     ///
     /// # Format Explanation
@@ -78,34 +132,307 @@ pub struct DifficultTable {
         rename = "course",
         default
     )]
+    #[cfg_attr(
+        feature = "schemars",
+        schemars(with = "Vec<Vec<DifficultTableCourse>>")
+    )]
     pub courses: Vec<DifficultTableCourse>,
+    /// Set when part of the table was fetched from the Internet Archive instead of its own url, this is the
+    /// first archived capture used
+    #[serde(skip)]
+    pub archive: Option<ArchiveCapture>,
+    /// Urls the header pages and then the body were actually fetched from, which differ from the table's own
+    /// when [`crate::HttpPolicy::Upgrade`] or [`crate::ParseOptions::scheme_fallback`] switched schemes
+    #[serde(skip)]
+    pub fetched_urls: Vec<String>,
+    /// Mirror the table was parsed from, set by [`crate::parse_mirrors`] only
+    #[serde(skip)]
+    pub mirror: Option<String>,
+    /// Url given to the parser, empty for tables which weren't fetched
+    #[serde(skip)]
+    pub source_url: String,
+    /// When the body was fetched
+    #[serde(skip)]
+    pub fetched_at: Option<DateTime<Utc>>,
+    /// Body elements skipped by a lenient parse, see [`crate::ParseOptions::lenient`]
+    #[serde(skip)]
+    pub warnings: Vec<ParseWarning>,
+    /// Header fields unknown to this crate (proposer, ir links...), kept as is so that they survive a round trip
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+    /// Id set by [`DifficultTable::set_id`], takes precedence over the derived one
+    #[serde(skip)]
+    id_override: Option<TableId>,
+    /// Hash lookups, see [`DifficultTable::find_by_md5`]
+    #[serde(skip)]
+    index: ContentIndex,
+}
+
+impl DifficultTable {
+    /// Stable identifier of this table
+    ///
+    /// Unless overridden by [`DifficultTable::set_id`], the id is derived from name, symbol and original_url,
+    /// see [`TableId`]
+    pub fn id(&self) -> TableId {
+        match &self.id_override {
+            Some(id) => id.clone(),
+            None => TableId::derive(&self.name, &self.symbol, &self.original_url),
+        }
+    }
+
+    /// Override the derived id, e.g. with a registry id
+    pub fn set_id(&mut self, id: impl Into<TableId>) {
+        self.id_override = Some(id.into());
+    }
+
+    /// `last_update` normalized into UTC, `None` if it's empty or in an unknown format
+    ///
+    /// Accepts RFC 3339 / RFC 2822, `yyyy/mm/dd`, `yyyy-mm-dd`, `yyyy.mm.dd`, `yyyy年mm月dd日`, `yyyymmdd` (each
+    /// optionally followed by a time) and epoch seconds or millis. Values without a time zone are taken as UTC
+    pub fn last_update_utc(&self) -> Option<DateTime<Utc>> {
+        parse_last_update(&self.last_update)
+    }
+
+    /// Wrap this table into a [`SharedTable`]
+    pub fn into_shared(self) -> SharedTable {
+        Arc::new(self)
+    }
+
+    /// Decompose this table into its header, contents and courses
+    ///
+    /// The returned header's `contents`, `levels` and `courses` are left empty. Use
+    /// [`DifficultTable::from_parts`] to put the (probably modified) pieces back together
+    pub fn into_parts(
+        mut self,
+    ) -> (
+        TableHeader,
+        Vec<DifficultTableElement>,
+        Vec<DifficultTableCourse>,
+    ) {
+        let contents = std::mem::take(&mut self.contents);
+        let courses = std::mem::take(&mut self.courses);
+        self.levels.clear();
+        self.index.reset();
+        (self, contents, courses)
+    }
+
+    /// Reconstruct a table from the pieces returned by [`DifficultTable::into_parts`]
+    ///
+    /// Derived state (`levels`) is recomputed from `contents`, whatever the header carries is discarded
+    pub fn from_parts(
+        header: TableHeader,
+        contents: Vec<DifficultTableElement>,
+        courses: Vec<DifficultTableCourse>,
+    ) -> Self {
+        let mut table = DifficultTable {
+            contents,
+            courses,
+            ..header
+        };
+        // the header may be a table whose index was built over other contents
+        table.index.reset();
+        table.refresh_levels(LevelOrdering::Sorted);
+        table
+    }
+
+    /// Keep only the elements matching `predicate`, levels no longer used by any element are dropped
+    pub fn retain_elements(&mut self, predicate: impl FnMut(&DifficultTableElement) -> bool) {
+        self.contents.retain(predicate);
+        self.index.reset();
+        self.drop_unused_levels();
+    }
+
+    /// Append one element, its level is inserted into `levels` if it's a new one
+    ///
+    /// The new level goes to its `level_order` position if it's listed there, to its sorted position if `levels`
+    /// is sorted, otherwise it's appended
+    pub fn add_element(&mut self, element: DifficultTableElement) {
+        self.insert_level(&element.level);
+        self.contents.push(element);
+        self.index.reset();
+    }
+
+    /// Remove every element with this md5, case insensitively, levels no longer used are dropped
+    ///
+    /// Returns the removed elements, in `contents` order
+    pub fn remove_by_md5(&mut self, md5: &str) -> Vec<DifficultTableElement> {
+        if md5.is_empty() {
+            return Vec::new();
+        }
+        let (removed, kept) = std::mem::take(&mut self.contents)
+            .into_iter()
+            .partition(|content| content.md5.eq_ignore_ascii_case(md5));
+        self.contents = kept;
+        self.index.reset();
+        self.drop_unused_levels();
+        removed
+    }
+
+    /// Move every element with this md5 (case insensitively) to another level, returns how many were moved
+    ///
+    /// The new level is inserted into `levels` the way [`DifficultTable::add_element`] does, levels no longer used
+    /// are dropped
+    pub fn set_level(&mut self, md5: &str, level: impl Into<Level>) -> usize {
+        let level = level.into();
+        let mut moved = 0;
+        for content in &mut self.contents {
+            if !md5.is_empty() && content.md5.eq_ignore_ascii_case(md5) {
+                content.level = level.clone();
+                moved += 1;
+            }
+        }
+        if moved > 0 {
+            self.insert_level(&level);
+            self.drop_unused_levels();
+        }
+        moved
+    }
+
+    /// First element with this md5, elements whose md5 is malformed never match
+    ///
+    /// Backed by an index built on the first lookup, so matching many hashes against a table doesn't scan
+    /// `contents` each time
+    pub fn find_by_md5(&self, md5: Md5) -> Option<&DifficultTableElement> {
+        self.index.find_md5(&self.contents, md5)
+    }
+
+    /// First element with this sha256, see [`DifficultTable::find_by_md5`]
+    pub fn find_by_sha256(&self, sha256: Sha256) -> Option<&DifficultTableElement> {
+        self.index.find_sha256(&self.contents, sha256)
+    }
+
+    /// Element matching a chart known by both hashes, the way beatoraja does: by sha256 first, then by md5
+    /// (many tables lack sha256s)
+    pub fn find_by_hash(
+        &self,
+        sha256: Option<Sha256>,
+        md5: Option<Md5>,
+    ) -> Option<&DifficultTableElement> {
+        sha256
+            .and_then(|sha256| self.find_by_sha256(sha256))
+            .or_else(|| self.find_by_md5(md5?))
+    }
+
+    /// Elements grouped by level, in `levels` order, each group keeping the order of `contents`
+    ///
+    /// # Example:
+    /// ```text
+    /// for (level, elements) in dth.contents_by_level() {
+    ///     println!("{}{level}: {} charts", dth.symbol, elements.len());
+    /// }
+    /// ```
+    pub fn contents_by_level(&self) -> Vec<(&Level, Vec<&DifficultTableElement>)> {
+        let mut groups: HashMap<&Level, Vec<&DifficultTableElement>> = HashMap::new();
+        for content in &self.contents {
+            groups.entry(&content.level).or_default().push(content);
+        }
+        self.levels
+            .iter()
+            .map(|level| (level, groups.remove(level).unwrap_or_default()))
+            .collect()
+    }
+
+    /// Remove the first course named `name`, returns the removed course if any
+    pub fn remove_course(&mut self, name: &str) -> Option<DifficultTableCourse> {
+        let pos = self.courses.iter().position(|course| course.name == name)?;
+        Some(self.courses.remove(pos))
+    }
+
+    /// Insert a level into `levels` if it's a new one, see [`DifficultTable::add_element`]
+    fn insert_level(&mut self, new: &Level) {
+        if self.levels.contains(new) {
+            return;
+        }
+        let order = |level: &Level| self.level_order.iter().position(|listed| listed == level);
+        let pos = if let Some(order_pos) = order(new) {
+            self.levels
+                .iter()
+                .position(|level| order(level).is_none_or(|pos| pos > order_pos))
+                .unwrap_or(self.levels.len())
+        } else if self.levels.is_sorted() {
+            self.levels.partition_point(|level| level < new)
+        } else {
+            self.levels.len()
+        };
+        self.levels.insert(pos, new.clone());
+    }
+
+    fn drop_unused_levels(&mut self) {
+        let used: HashSet<&Level> = self.contents.iter().map(|content| &content.level).collect();
+        self.levels.retain(|level| used.contains(level));
+    }
+
+    /// Sort `contents` by the position of their level in `levels`, then by title
+    pub(crate) fn sort_contents(&mut self) {
+        let positions: HashMap<&Level, usize> = self
+            .levels
+            .iter()
+            .enumerate()
+            .map(|(pos, level)| (level, pos))
+            .collect();
+        let mut contents = std::mem::take(&mut self.contents);
+        contents.sort_by(|lhs, rhs| {
+            positions[&lhs.level]
+                .cmp(&positions[&rhs.level])
+                .then_with(|| lhs.title.cmp(&rhs.title))
+        });
+        self.contents = contents;
+        self.index.reset();
+    }
+
+    /// Recompute `levels` from `contents`
+    pub(crate) fn refresh_levels(&mut self, ordering: LevelOrdering) {
+        let levels = self
+            .contents
+            .iter()
+            .map(|content| content.level.clone())
+            .unique();
+        self.levels = match ordering {
+            LevelOrdering::Sorted => levels.sorted().collect(),
+            LevelOrdering::Appearance => levels.collect(),
+        };
+        if !self.level_order.is_empty() {
+            let positions: HashMap<&Level, usize> = self
+                .level_order
+                .iter()
+                .enumerate()
+                .map(|(pos, level)| (level, pos))
+                .collect();
+            // stable, levels missing from level_order stay in the order above, after the listed ones
+            self.levels
+                .sort_by_key(|level| positions.get(level).copied().unwrap_or(usize::MAX));
+        }
+    }
 }
 
 ///
 /// Represents one difficult table related content
 ///
 /// Warning: due to some historical issues, sha256 is not always present
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct DifficultTableElement {
     /// song title
     pub title: String,
     /// song artist
     pub artist: String,
     /// MD5 hash
+    #[cfg_attr(feature = "schemars", schemars(pattern(r"^([0-9a-fA-F]{32})?$")))]
     pub md5: String,
     /// SHA256 hash
     #[serde(default)]
+    #[cfg_attr(feature = "schemars", schemars(pattern(r"^([0-9a-fA-F]{64})?$")))]
     pub sha256: String,
     /// song mode(unkown field)
     #[serde(default)]
     pub mode: String,
     /// song level mark
-    pub level: String,
+    pub level: Level,
     /// variant(差分) name(unkown field)
     #[serde(default)]
     pub diff_name: String,
-    /// song comment(discarded, not used field)
-    #[serde(skip)]
+    /// song comment
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub comment: String,
     /// song info(unkown field)
     #[serde(default)]
@@ -113,10 +440,46 @@ pub struct DifficultTableElement {
     /// bms id(unkown field)
     #[serde(default)]
     pub bms_id: String,
+    /// song download url
+    #[serde(default)]
+    pub url: String,
+    /// variant(差分) download url
+    #[serde(default)]
+    pub url_diff: String,
+    /// song package download url
+    #[serde(default)]
+    pub url_pack: String,
+    /// Derived romanized title and artist, filled by [`DifficultTable::romanize`], enabled by the `romaji`
+    /// feature
+    #[cfg(feature = "romaji")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub romanized: Option<crate::romaji::Romanized>,
+    /// LR2IR clear statistics, filled by [`DifficultTable::attach_lr2ir`], enabled by the `lr2ir` feature
+    #[cfg(feature = "lr2ir")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lr2ir: Option<crate::lr2ir::ChartStats>,
+    /// Element fields unknown to this crate, kept as is so that they survive a round trip
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl DifficultTableElement {
+    /// Validated md5, `None` if the md5 is empty or malformed
+    ///
+    /// The raw `md5` field is kept as is since some published tables contain malformed hashes
+    pub fn md5_hash(&self) -> Option<Md5> {
+        self.md5.parse().ok()
+    }
+
+    /// Validated sha256, `None` if the sha256 is empty (which is common) or malformed
+    pub fn sha256_hash(&self) -> Option<Sha256> {
+        self.sha256.parse().ok()
+    }
 }
 
 /// Represents one difficult table related course
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct DifficultTableCourse {
     /// course name
     pub name: String,
@@ -129,7 +492,15 @@ pub struct DifficultTableCourse {
     pub md5: Vec<String>,
 }
 
+impl DifficultTableCourse {
+    /// Validated chart md5s, malformed ones are skipped
+    pub fn md5_hashes(&self) -> impl Iterator<Item = Md5> + '_ {
+        self.md5.iter().filter_map(|md5| md5.parse().ok())
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct DifficultTableCourseTrophy {
     // trophy name
     pub name: String,
@@ -141,12 +512,11 @@ pub struct DifficultTableCourseTrophy {
     pub score_rate: f32,
 }
 
-fn lift_serialize<S>(x: &Vec<DifficultTableCourse>, s: S) -> Result<S::Ok, S::Error>
+fn lift_serialize<S>(x: &[DifficultTableCourse], s: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    let lift_vec = vec![x.clone()];
-    lift_vec.serialize(s)
+    [x].serialize(s)
 }
 
 fn unlift_deserialize<'de, D>(d: D) -> Result<Vec<DifficultTableCourse>, D::Error>
@@ -156,3 +526,256 @@ where
     let lifted_courses: Vec<Vec<DifficultTableCourse>> = Deserialize::deserialize(d)?;
     Ok(lifted_courses.into_iter().flatten().collect())
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    pub fn should_rebuild_levels_from_parts() {
+        let mut table: DifficultTable = serde_json::from_value(json!({
+            "name": "Satellite",
+            "symbol": "sl",
+            "data_url": "body.json",
+            "course": [[{ "name": "sl0", "constraint": [], "trophy": [], "md5": [] }]],
+        }))
+        .unwrap();
+        table.contents = serde_json::from_value(json!([
+            { "title": "a", "artist": "", "md5": "", "level": "10" },
+            { "title": "b", "artist": "", "md5": "", "level": "2" },
+        ]))
+        .unwrap();
+        table.refresh_levels(LevelOrdering::Sorted);
+
+        let (header, mut contents, courses) = table.into_parts();
+        assert!(
+            header.contents.is_empty() && header.levels.is_empty() && header.courses.is_empty()
+        );
+        assert_eq!(courses.len(), 1);
+        contents.retain(|content| content.title != "a");
+
+        let table = DifficultTable::from_parts(header, contents, courses);
+        assert_eq!(table.name, "Satellite");
+        assert_eq!(table.levels, [Level::from("2")]);
+        assert_eq!(table.courses.len(), 1);
+    }
+
+    #[test]
+    pub fn should_not_look_up_through_the_header_index() {
+        let mut header: DifficultTable =
+            serde_json::from_value(json!({ "name": "Satellite", "symbol": "sl" })).unwrap();
+        header.contents = serde_json::from_value(json!([
+            { "title": "a", "artist": "", "md5": "a".repeat(32), "level": "1" },
+        ]))
+        .unwrap();
+        assert!(header
+            .find_by_md5("a".repeat(32).parse().unwrap())
+            .is_some());
+        let contents = serde_json::from_value(json!([
+            { "title": "b", "artist": "", "md5": "b".repeat(32), "level": "1" },
+        ]))
+        .unwrap();
+        let table = DifficultTable::from_parts(header, contents, Vec::new());
+        let b = "b".repeat(32).parse().unwrap();
+        assert_eq!(table.find_by_md5(b).unwrap().title, "b");
+    }
+
+    #[test]
+    pub fn should_accept_numeric_last_update() {
+        let table: DifficultTable = serde_json::from_value(
+            json!({ "name": "Satellite", "symbol": "sl", "last_update": 1486252800 }),
+        )
+        .unwrap();
+        assert_eq!(table.last_update, "1486252800");
+        assert_eq!(
+            table.last_update_utc().unwrap().to_rfc3339(),
+            "2017-02-05T00:00:00+00:00"
+        );
+    }
+
+    #[test]
+    pub fn editing_should_keep_levels_in_sync() {
+        let mut table: DifficultTable = serde_json::from_value(json!({
+            "name": "Satellite",
+            "symbol": "sl",
+            "course": [[{ "name": "sl0", "constraint": [], "trophy": [], "md5": [] }]],
+        }))
+        .unwrap();
+        let element = |title: &str, level: &str| -> DifficultTableElement {
+            serde_json::from_value(
+                json!({ "title": title, "artist": "", "md5": "", "level": level }),
+            )
+            .unwrap()
+        };
+        table.add_element(element("a", "10"));
+        table.add_element(element("b", "2"));
+        table.add_element(element("c", "10"));
+        assert_eq!(table.levels, [Level::from("2"), Level::from("10")]);
+        let groups: Vec<(&Level, Vec<&str>)> = table
+            .contents_by_level()
+            .into_iter()
+            .map(|(level, elements)| (level, elements.iter().map(|e| e.title.as_str()).collect()))
+            .collect();
+        assert_eq!(
+            groups,
+            [
+                (&Level::from("2"), vec!["b"]),
+                (&Level::from("10"), vec!["a", "c"])
+            ]
+        );
+
+        table.retain_elements(|element| element.level != "2");
+        assert_eq!(table.contents.len(), 2);
+        assert_eq!(table.levels, [Level::from("10")]);
+
+        let (ab, cd, ef) = (
+            "ab".repeat(16).parse().unwrap(),
+            "cd".repeat(32).parse().unwrap(),
+            "ef".repeat(32).parse().unwrap(),
+        );
+        assert_eq!(table.find_by_md5(ab).map(|e| &e.title), None);
+        let mut element = element("d", "2");
+        element.md5 = "AB".repeat(16);
+        table.add_element(element);
+        assert_eq!(table.find_by_md5(ab).unwrap().title, "d");
+        table.contents.swap(0, 2);
+        assert_eq!(table.find_by_md5(ab).unwrap().title, "d");
+        let mut pushed = table.contents[0].clone();
+        pushed.title = "f".to_string();
+        pushed.md5 = "fe".repeat(16);
+        table.contents.push(pushed);
+        let fe = "FE".repeat(16).parse().unwrap();
+        assert_eq!(table.find_by_md5(fe).unwrap().title, "f");
+        table.contents.pop();
+        let mut element = table.contents[1].clone();
+        element.title = "e".to_string();
+        element.sha256 = "cd".repeat(32);
+        table.add_element(element);
+        assert_eq!(table.find_by_hash(Some(cd), None).unwrap().title, "e");
+        assert_eq!(table.find_by_hash(Some(ef), Some(ab)).unwrap().title, "d");
+        assert!(table.find_by_hash(None, None).is_none());
+
+        assert_eq!(table.set_level(&"AB".repeat(16), "1"), 1);
+        assert_eq!(table.levels, [Level::from("1"), Level::from("10")]);
+        assert_eq!(table.find_by_md5(ab).unwrap().level, "1");
+        assert_eq!(table.set_level("", "3"), 0);
+        assert_eq!(table.remove_by_md5(&"ab".repeat(16)).len(), 1);
+        assert_eq!(table.levels, [Level::from("10")]);
+        assert!(table.find_by_md5(ab).is_none());
+
+        assert!(table.remove_course("sl1").is_none());
+        assert_eq!(table.remove_course("sl0").unwrap().name, "sl0");
+        assert!(table.courses.is_empty());
+    }
+
+    #[test]
+    pub fn should_keep_appearance_order() {
+        let mut table: DifficultTable =
+            serde_json::from_value(json!({ "name": "Event", "symbol": "ev" })).unwrap();
+        table.contents = ["day2", "day1", "day2", "final"]
+            .into_iter()
+            .map(|level| {
+                serde_json::from_value(
+                    json!({ "title": "", "artist": "", "md5": "", "level": level }),
+                )
+                .unwrap()
+            })
+            .collect();
+        table.refresh_levels(LevelOrdering::Appearance);
+        assert_eq!(table.levels, ["day2", "day1", "final"].map(Level::from));
+
+        table.retain_elements(|element| element.level != "final");
+        assert_eq!(table.levels, ["day2", "day1"].map(Level::from));
+        table.add_element(table.contents[0].clone());
+        let mut element = table.contents[0].clone();
+        element.level = Level::from("day0");
+        table.add_element(element);
+        assert_eq!(table.levels, ["day2", "day1", "day0"].map(Level::from));
+    }
+
+    #[test]
+    pub fn should_round_trip_through_serde() {
+        let mut table: DifficultTable = serde_json::from_value(json!({
+            "name": "Satellite",
+            "symbol": "sl",
+            "data_url": "body.json",
+        }))
+        .unwrap();
+        table.add_element(
+            serde_json::from_value(
+                json!({ "title": "a", "artist": "", "md5": "a".repeat(32), "level": "1" }),
+            )
+            .unwrap(),
+        );
+        let reloaded: DifficultTable =
+            serde_json::from_str(&serde_json::to_string(&table).unwrap()).unwrap();
+        assert_eq!(reloaded.contents.len(), 1);
+        assert_eq!(reloaded.levels, ["1"].map(Level::from));
+        assert_eq!(
+            reloaded
+                .find_by_md5("a".repeat(32).parse().unwrap())
+                .unwrap()
+                .title,
+            "a"
+        );
+        assert!(reloaded.extra.is_empty());
+        assert_eq!(reloaded.content_hash(), table.content_hash());
+    }
+
+    #[test]
+    pub fn should_keep_unknown_fields() {
+        let header = json!({
+            "name": "Satellite",
+            "symbol": "sl",
+            "data_url": "body.json",
+            "proposer": "someone",
+        });
+        let table: DifficultTable = serde_json::from_value(header).unwrap();
+        assert_eq!(table.extra["proposer"], "someone");
+        assert_eq!(serde_json::to_value(&table).unwrap()["proposer"], "someone");
+
+        let element =
+            json!({ "title": "a", "artist": "", "md5": "", "level": "1", "ir": { "lr2": 1 } });
+        let element: DifficultTableElement = serde_json::from_value(element).unwrap();
+        assert_eq!(element.extra["ir"], json!({ "lr2": 1 }));
+        assert_eq!(serde_json::to_value(&element).unwrap()["ir"]["lr2"], 1);
+
+        let element =
+            json!({ "title": "a", "artist": "", "md5": "", "level": "1", "comment": "new" });
+        let element: DifficultTableElement = serde_json::from_value(element).unwrap();
+        assert_eq!(element.comment, "new");
+        assert!(element.extra.is_empty());
+        assert_eq!(serde_json::to_value(&element).unwrap()["comment"], "new");
+    }
+
+    #[test]
+    pub fn should_follow_header_level_order() {
+        let mut table: DifficultTable = serde_json::from_value(json!({
+            "name": "Insane",
+            "symbol": "★",
+            "level_order": [0, "1", "2", "X", "?"],
+        }))
+        .unwrap();
+        table.contents = ["?", "2", "99", "X", "0"]
+            .into_iter()
+            .map(|level| {
+                serde_json::from_value(
+                    json!({ "title": "", "artist": "", "md5": "", "level": level }),
+                )
+                .unwrap()
+            })
+            .collect();
+        table.refresh_levels(LevelOrdering::Sorted);
+        assert_eq!(table.levels, ["0", "2", "X", "?", "99"].map(Level::from));
+
+        let mut element = table.contents[0].clone();
+        element.level = Level::from("1");
+        table.add_element(element);
+        assert_eq!(
+            table.levels,
+            ["0", "1", "2", "X", "?", "99"].map(Level::from)
+        );
+    }
+}
@@ -0,0 +1,319 @@
+use std::{future::Future, time::Instant};
+
+use crate::{
+    client::DEFAULT_USER_AGENT,
+    fetcher,
+    modal::{DifficultTable, TableHeader},
+    options::{ParseOptions, Stage},
+    parser::{self, FetchError, Fetched, ParseError, Target},
+    resolve::{Document, Resolver, Step},
+    wayback,
+};
+
+/// Same as [`crate::parse`], without blocking, enabled by the `async` feature
+///
+/// Must be run inside a tokio runtime
+///
+/// # Example:
+/// ```text
+/// let dth = parse_async("https://stellabms.xyz/sl/table.html".to_string()).await?;
+/// ```
+pub async fn parse_async(url: String) -> Result<DifficultTable, ParseError> {
+    parse_async_with_options(url, &ParseOptions::default()).await
+}
+
+/// Same as [`crate::parse_with_options`], without blocking, enabled by the `async` feature
+pub async fn parse_async_with_options(
+    url: String,
+    options: &ParseOptions,
+) -> Result<DifficultTable, ParseError> {
+    let client = reqwest::Client::builder()
+        .user_agent(DEFAULT_USER_AGENT)
+        .build()
+        .expect("default client is valid");
+    parse_async_with_client_and_options(&client, &url, options).await
+}
+
+/// Same as [`crate::parse_with_client`], without blocking, enabled by the `async` feature
+pub async fn parse_async_with_client(
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<DifficultTable, ParseError> {
+    parse_async_with_client_and_options(client, url, &ParseOptions::default()).await
+}
+
+/// Same as [`crate::parse_with_client_and_options`], without blocking, enabled by the `async` feature
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "parse", skip(client, options))
+)]
+pub async fn parse_async_with_client_and_options(
+    client: &reqwest::Client,
+    url: &str,
+    options: &ParseOptions,
+) -> Result<DifficultTable, ParseError> {
+    let mut resolver = Resolver::new(url, options)?;
+    let (mut header, body) = resolve(client, url.to_string(), &mut resolver).await?;
+    parser::fill_contents(&mut header, &body, options, &resolver.quirks)?;
+    Ok(header)
+}
+
+/// Async driver of [`Resolver`], see `resolve` in the crate root for the blocking one
+async fn resolve(
+    client: &reqwest::Client,
+    mut url: String,
+    resolver: &mut Resolver<'_>,
+) -> Result<(TableHeader, String), ParseError> {
+    let options = resolver.options();
+    let mut page =
+        resolver.header_page(fetch_header(client, &url, options, resolver.deadline).await?)?;
+    loop {
+        match resolver.step(&url, &page)? {
+            Step::Follow(next) => {
+                page = resolver
+                    .header_page(fetch_header(client, &next, options, resolver.deadline).await?)?;
+                url = next;
+            }
+            Step::Header(mut header) => {
+                let fetched =
+                    fetch_body(client, &header.data_url, options, resolver.deadline).await?;
+                match resolver.body(&mut header, fetched)? {
+                    Document::Body(body) => return Ok((*header, body)),
+                    Document::HeaderPage(next_page) => {
+                        url = header.data_url;
+                        page = next_page;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip(client, options))
+)]
+async fn fetch_header(
+    client: &reqwest::Client,
+    url: &str,
+    options: &ParseOptions,
+    deadline: Option<Instant>,
+) -> Result<Fetched, ParseError> {
+    fetch(client, url, options, Stage::Header, deadline, |source| {
+        ParseError::HeaderFetch {
+            url: url.to_string(),
+            source,
+        }
+    })
+    .await
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip(client, options))
+)]
+async fn fetch_body(
+    client: &reqwest::Client,
+    url: &str,
+    options: &ParseOptions,
+    deadline: Option<Instant>,
+) -> Result<Fetched, ParseError> {
+    fetch(client, url, options, Stage::Body, deadline, |source| {
+        ParseError::BodyFetch {
+            url: url.to_string(),
+            source,
+        }
+    })
+    .await
+}
+
+/// Async counterpart of the blocking fetch, see [`parser::target`]
+///
+/// Unlike the blocking one, requests are bounded by `deadline` as a whole, connecting included
+async fn fetch(
+    client: &reqwest::Client,
+    url: &str,
+    options: &ParseOptions,
+    stage: Stage,
+    deadline: Option<Instant>,
+    on_error: impl FnOnce(FetchError) -> ParseError,
+) -> Result<Fetched, ParseError> {
+    #[cfg(feature = "tracing")]
+    let started = std::time::Instant::now();
+    let urls = match parser::target(url, options)? {
+        Target::File(path) => {
+            let read = || {
+                parser::check_deadline(deadline)?;
+                let len = std::fs::metadata(&path)?.len();
+                options.check_response_size(len, Some(len))?;
+                Ok(std::fs::read(&path)?)
+            };
+            let raw = read().map_err(on_error)?;
+            options.report_progress(stage, raw.len() as u64, Some(raw.len() as u64));
+            return Ok(Fetched {
+                raw,
+                content_type: None,
+                capture: None,
+                url: url.to_string(),
+            });
+        }
+        Target::Http(urls) => urls,
+    };
+    let mut resp = until(deadline, get_with_retry(client, &urls[0], options))
+        .await
+        .map(|resp| (resp, urls[0].clone()));
+    for fallback in &urls[1..] {
+        if resp.is_err() {
+            resp = until(deadline, get_with_retry(client, fallback, options))
+                .await
+                .map(|resp| (resp, fallback.clone()));
+        }
+    }
+    if let Err(err) = &resp {
+        if let Some(flipped) = parser::flipped_scheme(url, &urls, err, options) {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(url, flipped, "retrying with the scheme flipped");
+            if let Ok(flipped_resp) =
+                until(deadline, get_with_retry(client, &flipped, options)).await
+            {
+                resp = Ok((flipped_resp, flipped));
+            }
+        }
+    }
+    let (resp, capture, url) = match resp {
+        Ok((resp, url)) => (resp, None, url),
+        Err(err)
+            if options.wayback_fallback
+                && err.is_gone()
+                && parser::check_deadline(deadline).is_ok() =>
+        {
+            let Some(capture) = find_capture(client, url).await else {
+                return Err(on_error(err));
+            };
+            let raw_url = capture.raw_url();
+            match until(deadline, get(client, &raw_url)).await {
+                Ok(resp) => (resp, Some(capture), raw_url),
+                Err(_) => return Err(on_error(err)),
+            }
+        }
+        Err(err) => {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(error = %err, elapsed = ?started.elapsed(), "fetch failed");
+            return Err(on_error(err));
+        }
+    };
+    let content_type = fetcher::content_type(resp.headers());
+    let raw = until(deadline, read_body(resp, options, stage))
+        .await
+        .map_err(on_error)?;
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        bytes = raw.len(),
+        content_type,
+        elapsed = ?started.elapsed(),
+        "fetched"
+    );
+    Ok(Fetched {
+        raw,
+        content_type,
+        capture,
+        url,
+    })
+}
+
+/// Async counterpart of [`fetcher::read_body`], reporting progress to [`ParseOptions::progress`] and stopping
+/// past [`ParseOptions::max_response_size`]
+async fn read_body(
+    mut resp: reqwest::Response,
+    options: &ParseOptions,
+    stage: Stage,
+) -> Result<Vec<u8>, FetchError> {
+    let total = resp.content_length();
+    let mut raw = Vec::new();
+    options.check_response_size(0, total)?;
+    options.report_progress(stage, 0, total);
+    while let Some(chunk) = resp.chunk().await? {
+        raw.extend_from_slice(&chunk);
+        options.check_response_size(raw.len() as u64, total)?;
+        options.report_progress(stage, raw.len() as u64, total);
+    }
+    Ok(raw)
+}
+
+/// Run a request until `deadline`, failing with [`FetchError::DeadlineExceeded`] past it
+async fn until<T>(
+    deadline: Option<Instant>,
+    request: impl Future<Output = Result<T, FetchError>>,
+) -> Result<T, FetchError> {
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline.into(), request)
+            .await
+            .unwrap_or(Err(FetchError::DeadlineExceeded)),
+        None => request.await,
+    }
+}
+
+/// Async counterpart of the blocking retry loop, following [`ParseOptions::retry`]
+async fn get_with_retry(
+    client: &reqwest::Client,
+    url: &str,
+    options: &ParseOptions,
+) -> Result<reqwest::Response, FetchError> {
+    let policy = &options.retry;
+    let mut retry = 0;
+    loop {
+        match get(client, url).await {
+            Err(err) if retry + 1 < policy.attempts && err.is_retryable(policy) => {
+                tokio::time::sleep(policy.delay(retry)).await;
+                retry += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+async fn get(client: &reqwest::Client, url: &str) -> Result<reqwest::Response, FetchError> {
+    Ok(client.get(url).send().await?.error_for_status()?)
+}
+
+/// Async counterpart of the blocking Wayback Machine lookup
+async fn find_capture(client: &reqwest::Client, url: &str) -> Option<wayback::ArchiveCapture> {
+    let resp = get(client, wayback::availability_url(url)?.as_str())
+        .await
+        .ok()?;
+    wayback::parse_availability(url, &resp.text().await.ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn should_parse_without_blocking() {
+        let err = parse_async("http://127.0.0.1:1/header.json".to_string())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ParseError::HeaderFetch { .. }));
+
+        let dir = std::env::temp_dir().join(format!("rbmstable-async-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("header.json"),
+            r#"{ "name": "Local", "symbol": "l", "data_url": "body.json" }"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("body.json"),
+            r#"[{ "title": "a", "artist": "", "md5": "", "level": "1" }]"#,
+        )
+        .unwrap();
+        let mut options = ParseOptions::default();
+        options.allowed_schemes.push("file".to_string());
+        let url = format!("file://{}/header.json", dir.display());
+        let dth = parse_async_with_options(url, &options).await;
+        std::fs::remove_dir_all(&dir).unwrap();
+        let dth = dth.unwrap();
+        assert_eq!(dth.name, "Local");
+        assert_eq!(dth.contents.len(), 1);
+    }
+}
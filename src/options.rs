@@ -0,0 +1,299 @@
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{parser::FetchError, quirk::Quirks};
+
+/// Default of [`ParseOptions::max_response_size`]
+const DEFAULT_MAX_RESPONSE_SIZE: u64 = 32 * 1024 * 1024;
+
+/// How [`crate::DifficultTable::levels`] is ordered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LevelOrdering {
+    /// Sorted by [`crate::Level`]'s ordering
+    #[default]
+    Sorted,
+    /// In order of first appearance in the body, for tables that group levels intentionally (event tables,
+    /// themed groups...)
+    Appearance,
+}
+
+/// How [`crate::DifficultTable::contents`] is ordered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContentOrdering {
+    /// Exactly the order of the body json
+    #[default]
+    Source,
+    /// By level (following [`LevelOrdering`]), then by title. The sort is stable, elements with the same level
+    /// and title keep their source order
+    ByLevel,
+}
+
+/// What to do with plain `http://` urls (the table page, the header and the body)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HttpPolicy {
+    /// Fetch them as is
+    #[default]
+    Allow,
+    /// Try the `https://` counterpart first, fall back to plain http if the request fails
+    Upgrade,
+    /// Refuse them with [`crate::ParseError::InsecureUrl`]
+    Reject,
+}
+
+/// What to do with the md5 and sha256 of body elements, see [`ParseOptions::hash_validation`]
+///
+/// Empty hashes are missing rather than malformed, they are always kept as is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HashValidation {
+    /// Keep them as written
+    #[default]
+    Keep,
+    /// Trim and lowercase them, malformed ones (not 32/64 hex digits) are kept and reported in
+    /// [`crate::DifficultTable::warnings`]
+    Normalize,
+    /// Same as `Normalize`, but fail with [`crate::ParseError::InvalidHash`] on the first malformed one
+    Reject,
+}
+
+/// What is being downloaded when a [`ProgressHook`] is called
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stage {
+    /// A header page: the table html, or the header json
+    Header,
+    /// The body json pointed by the header's data_url
+    Body,
+}
+
+/// Download progress callback, see [`ParseOptions::on_progress`]
+#[derive(Clone)]
+pub struct ProgressHook(Arc<dyn Fn(Stage, u64, Option<u64>) + Send + Sync>);
+
+impl ProgressHook {
+    pub fn new(hook: impl Fn(Stage, u64, Option<u64>) + Send + Sync + 'static) -> Self {
+        ProgressHook(Arc::new(hook))
+    }
+
+    pub(crate) fn call(&self, stage: Stage, bytes_done: u64, bytes_total: Option<u64>) {
+        (self.0)(stage, bytes_done, bytes_total)
+    }
+}
+
+impl fmt::Debug for ProgressHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressHook")
+    }
+}
+
+/// Failures worth another attempt, see [`RetryPolicy`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryOn {
+    /// Connection refused or reset, DNS failures
+    Connect,
+    Timeout,
+    /// 5xx statuses, e.g. the 502/503 of an overloaded mirror
+    ServerError,
+    /// 429 statuses
+    TooManyRequests,
+}
+
+/// How failed requests (the table page, the header and the body) are retried
+///
+/// # Example:
+/// ```text
+/// let options = ParseOptions {
+///     retry: RetryPolicy {
+///         retry_on: vec![RetryOn::ServerError],
+///         ..RetryPolicy::new(5)
+///     },
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts per request including the first one, 1 means no retry
+    pub attempts: u32,
+    /// Delay before the first retry, doubled after each retry
+    pub backoff: Duration,
+    pub retry_on: Vec<RetryOn>,
+}
+
+impl RetryPolicy {
+    /// Never retry, the default
+    pub fn none() -> Self {
+        RetryPolicy::new(1)
+    }
+
+    /// Retry every [`RetryOn`] class until `attempts` requests were sent, starting with a 500ms backoff
+    pub fn new(attempts: u32) -> Self {
+        RetryPolicy {
+            attempts,
+            backoff: Duration::from_millis(500),
+            retry_on: vec![
+                RetryOn::Connect,
+                RetryOn::Timeout,
+                RetryOn::ServerError,
+                RetryOn::TooManyRequests,
+            ],
+        }
+    }
+
+    /// Delay before the `retry`th retry, starting from 0
+    pub fn delay(&self, retry: u32) -> Duration {
+        self.backoff.saturating_mul(2u32.saturating_pow(retry))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::none()
+    }
+}
+
+/// Options tweaking how a difficult table is parsed, see [`crate::parse_with_options`]
+///
+/// # Example:
+/// ```text
+/// let options = ParseOptions {
+///     level_ordering: LevelOrdering::Appearance,
+///     ..Default::default()
+/// };
+/// let dth = parse_with_options(url, &options)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    pub level_ordering: LevelOrdering,
+    pub content_ordering: ContentOrdering,
+    pub http_policy: HttpPolicy,
+    /// How many times a page may point at another page before the header is reached, e.g. a table.html
+    /// pointing at another table.html, a page redirecting with a meta refresh, or a header whose data_url is yet
+    /// another header. Defaults to 4
+    pub max_indirections: usize,
+    /// Url schemes allowed for the table page, the header and the body. Defaults to `http` and `https`
+    ///
+    /// `file` urls are read from the local file system. Any other scheme is handed to the [`crate::Fetcher`] as is
+    pub allowed_schemes: Vec<String>,
+    /// Work arounds for awkward tables, defaults to [`Quirks::builtin`]
+    pub quirks: Quirks,
+    /// When the table page, the header or the body is gone (connection failure, 404 or 410), parse the latest
+    /// Internet Archive capture of it instead, see [`crate::DifficultTable::archive`]. Defaults to false
+    pub wayback_fallback: bool,
+    /// When connecting fails, try again with the scheme flipped (`http` to `https` and back) before giving up, for
+    /// tables whose host moved to https only, or dropped it. The url that worked is kept in
+    /// [`crate::DifficultTable::fetched_urls`]. [`HttpPolicy::Reject`] still applies. Defaults to false
+    pub scheme_fallback: bool,
+    /// Retries of failed requests, defaults to [`RetryPolicy::none`]
+    pub retry: RetryPolicy,
+    /// How many tables of the same host [`crate::parse_many`] parses at a time, defaults to 2
+    pub per_host_concurrency: usize,
+    /// Least time between two requests [`crate::parse_many`] (or `DifficultTable::audit_links`, with the `async`
+    /// feature) sends to the same host, so that tables sharing a server don't get the crate blocked by it. Defaults to 100ms
+    pub per_host_interval: Duration,
+    /// Skip malformed body elements instead of failing the whole parse, each skipped element is reported in
+    /// [`crate::DifficultTable::warnings`]. Defaults to false
+    pub lenient: bool,
+    /// Reject unknown fields, and optional fields that are present but empty or null, in the header and the
+    /// body with [`crate::ParseError::Strict`], for table authors validating their json. Defaults to false
+    pub strict: bool,
+    /// Normalize the case of body hashes and check their format, defaults to [`HashValidation::Keep`]
+    pub hash_validation: HashValidation,
+    /// Time allowed to the whole parse, from the first header page request to the end of the body download.
+    /// Past it, requests and downloads stop with [`crate::FetchError::DeadlineExceeded`]. Defaults to none
+    ///
+    /// The deadline is checked before each request and while responses are read, a request stuck connecting is
+    /// bounded by the client's own timeouts (see [`crate::ParserBuilder::connect_timeout`]) rather than this one,
+    /// except with the `async` feature
+    pub deadline: Option<Duration>,
+    /// Largest response accepted for a header page or a body, in bytes, larger ones fail with
+    /// [`crate::FetchError::TooLarge`] as soon as the limit is crossed. Compressed responses are limited by their
+    /// decompressed size. Defaults to 32 MiB, `None` means no limit
+    ///
+    /// Fetchers which don't read responses by chunks (see [`crate::Fetcher::fetch_with_progress`]) are only
+    /// checked once the whole response is read
+    pub max_response_size: Option<u64>,
+    /// Told how much of each header page and body is downloaded, see [`ParseOptions::on_progress`]. Defaults to
+    /// none
+    pub progress: Option<ProgressHook>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            level_ordering: LevelOrdering::default(),
+            content_ordering: ContentOrdering::default(),
+            http_policy: HttpPolicy::default(),
+            max_indirections: 4,
+            allowed_schemes: vec!["http".to_string(), "https".to_string()],
+            quirks: Quirks::builtin(),
+            wayback_fallback: false,
+            scheme_fallback: false,
+            retry: RetryPolicy::none(),
+            per_host_concurrency: 2,
+            per_host_interval: Duration::from_millis(100),
+            lenient: false,
+            strict: false,
+            hash_validation: HashValidation::default(),
+            deadline: None,
+            max_response_size: Some(DEFAULT_MAX_RESPONSE_SIZE),
+            progress: None,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Whether `scheme` is one of [`ParseOptions::allowed_schemes`], case insensitively
+    pub fn allows_scheme(&self, scheme: &str) -> bool {
+        self.allowed_schemes
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(scheme))
+    }
+
+    /// Call `hook(stage, bytes_done, bytes_total)` as header pages and the body are downloaded
+    ///
+    /// bytes_total is the response's `Content-Length`, when there's one. The hook is called from the thread (or
+    /// task) parsing the table, once before the first byte and after every chunk read
+    ///
+    /// # Example:
+    /// ```text
+    /// let options = ParseOptions::default().on_progress(|stage, done, total| match total {
+    ///     Some(total) => println!("downloading {stage:?}... {done} / {total}"),
+    ///     None => println!("downloading {stage:?}... {done}"),
+    /// });
+    /// ```
+    pub fn on_progress(
+        mut self,
+        hook: impl Fn(Stage, u64, Option<u64>) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress = Some(ProgressHook::new(hook));
+        self
+    }
+
+    /// Fail once a response is known to be larger than [`ParseOptions::max_response_size`], either from what was
+    /// read or from its `Content-Length`
+    pub(crate) fn check_response_size(
+        &self,
+        bytes_done: u64,
+        bytes_total: Option<u64>,
+    ) -> Result<(), FetchError> {
+        match self.max_response_size {
+            Some(limit) if bytes_done.max(bytes_total.unwrap_or(0)) > limit => {
+                Err(FetchError::TooLarge(limit))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// When a parse started now must be done by, see [`ParseOptions::deadline`]
+    pub(crate) fn deadline_from_now(&self) -> Option<Instant> {
+        self.deadline.map(|deadline| Instant::now() + deadline)
+    }
+
+    /// Report progress of `stage` to [`ParseOptions::progress`], if any
+    pub(crate) fn report_progress(&self, stage: Stage, bytes_done: u64, bytes_total: Option<u64>) {
+        if let Some(hook) = &self.progress {
+            hook.call(stage, bytes_done, bytes_total);
+        }
+    }
+}
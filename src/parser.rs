@@ -1,84 +1,847 @@
-use std::io::{self, Read};
+use std::{
+    io,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 
-use itertools::Itertools;
+use encoding_rs::{Encoding, SHIFT_JIS};
+use scraper::{Html, Selector};
 use thiserror::Error;
+use url::Url;
 
-use crate::modal::DifficultTable;
+use crate::{
+    client::default_client,
+    fetcher::{Fetcher, Progress},
+    hash::{Md5, Sha256},
+    modal::{DifficultTable, DifficultTableElement},
+    options::{
+        ContentOrdering, HashValidation, HttpPolicy, ParseOptions, RetryOn, RetryPolicy, Stage,
+    },
+    quirk::ActiveQuirks,
+    strict,
+    wayback::{self, ArchiveCapture},
+};
 
 #[derive(Error, Debug)]
 pub enum ParseError {
-    #[error("URL format must be ended with .json or .html")]
+    /// The document at the url is neither an html page nor a header json
+    #[error("URL doesn't point at a table page nor a header")]
     UnSupportedURLFormat,
     #[error("Difficult table header data is corrupted: `{0}`")]
     CorruptedHeaderData(String),
+    #[error("Unsupported url scheme {0}")]
+    UnsupportedScheme(String),
+    #[error("Invalid data_url `{0}`: {1}")]
+    InvalidDataUrl(String, String),
+    #[error("Invalid hash: {0}")]
+    InvalidHash(String),
+    /// Authoring mistake rejected by [`ParseOptions::strict`]
+    #[error("Strict parsing failed: {0}")]
+    Strict(String),
     #[error(transparent)]
     SerdeError(#[from] serde_json::Error),
-    #[error(transparent)]
-    ReqwestError(#[from] reqwest::Error),
+    #[error("Failed to fetch difficult table header from {url}")]
+    HeaderFetch {
+        url: String,
+        #[source]
+        source: FetchError,
+    },
+    #[error("Failed to fetch difficult table body from {url}")]
+    BodyFetch {
+        url: String,
+        #[source]
+        source: FetchError,
+    },
+    #[error("Refused to fetch {0} over plain http")]
+    InsecureUrl(String),
+    #[error("Failed to fetch registry index from {url}")]
+    RegistryFetch {
+        url: String,
+        #[source]
+        source: FetchError,
+    },
+    #[error("Failed to fetch table index from {url}")]
+    IndexFetch {
+        url: String,
+        #[source]
+        source: FetchError,
+    },
+    /// The document is neither a json list of tables nor an html page, see [`crate::parse_table_index`]
+    #[error("Table index is corrupted: `{0}`")]
+    CorruptedTableIndex(String),
+    #[cfg(feature = "lr2ir")]
+    #[error("Failed to fetch LR2IR ranking from {url}")]
+    RankingFetch {
+        url: String,
+        #[source]
+        source: FetchError,
+    },
+    #[error("Failed to build HTTP client")]
+    ClientBuild(#[source] reqwest::Error),
+    #[error("Invalid request header {0}")]
+    InvalidRequestHeader(String),
+    #[error("Fingerprint mismatch, expected {expected} but got {actual}")]
+    FingerprintMismatch { expected: Sha256, actual: Sha256 },
+    #[error("Too many header indirections: {}", .0.join(" -> "))]
+    TooManyRedirections(Vec<String>),
     #[error(transparent)]
     IOError(#[from] io::Error),
+    #[cfg(feature = "config")]
+    #[error(transparent)]
+    ConfigError(#[from] toml::de::Error),
+    #[cfg(feature = "zip")]
+    #[error(transparent)]
+    ZipError(#[from] zip::result::ZipError),
+    #[error("Unsupported snapshot version {0}")]
+    UnsupportedSnapshot(u32),
+}
+
+/// Failure of one [`Fetcher`] request, reported as the source of the `*Fetch` variants of [`ParseError`]
+#[derive(Error, Debug)]
+pub enum FetchError {
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// Nothing at this url, for fetchers that are not backed by HTTP
+    #[error("Nothing found at {0}")]
+    NotFound(String),
+    /// The response is larger than [`ParseOptions::max_response_size`], which is the limit given
+    #[error("Response is larger than {0} bytes")]
+    TooLarge(u64),
+    /// [`ParseOptions::deadline`] passed before the response was read
+    #[error("Parse deadline exceeded")]
+    DeadlineExceeded,
+    #[error(transparent)]
+    Other(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl FetchError {
+    /// Whether the url is gone (connection failure, 404 or 410) rather than failing transiently
+    pub fn is_gone(&self) -> bool {
+        match self {
+            FetchError::Http(err) => {
+                err.is_connect()
+                    || matches!(
+                        err.status(),
+                        Some(reqwest::StatusCode::NOT_FOUND | reqwest::StatusCode::GONE)
+                    )
+            }
+            FetchError::NotFound(_) => true,
+            FetchError::Io(_)
+            | FetchError::TooLarge(_)
+            | FetchError::DeadlineExceeded
+            | FetchError::Other(_) => false,
+        }
+    }
+
+    /// Which [`RetryOn`] class this failure falls in, if any
+    pub fn retry_class(&self) -> Option<RetryOn> {
+        match self {
+            FetchError::Http(err) if err.is_timeout() => Some(RetryOn::Timeout),
+            FetchError::Http(err) if err.is_connect() => Some(RetryOn::Connect),
+            FetchError::Http(err) => match err.status()? {
+                reqwest::StatusCode::TOO_MANY_REQUESTS => Some(RetryOn::TooManyRequests),
+                status if status.is_server_error() => Some(RetryOn::ServerError),
+                _ => None,
+            },
+            FetchError::Io(err) => match err.kind() {
+                io::ErrorKind::TimedOut => Some(RetryOn::Timeout),
+                io::ErrorKind::ConnectionRefused
+                | io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::UnexpectedEof => Some(RetryOn::Connect),
+                _ => None,
+            },
+            FetchError::NotFound(_)
+            | FetchError::TooLarge(_)
+            | FetchError::DeadlineExceeded
+            | FetchError::Other(_) => None,
+        }
+    }
+
+    /// Whether another attempt is allowed by `policy`
+    pub(crate) fn is_retryable(&self, policy: &RetryPolicy) -> bool {
+        self.retry_class()
+            .is_some_and(|class| policy.retry_on.contains(&class))
+    }
+}
+
+/// Body element skipped by a lenient parse (see [`ParseOptions::lenient`]), or having a malformed hash (see
+/// [`ParseOptions::hash_validation`])
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseWarning {
+    /// Position of the element in the body json
+    pub index: usize,
+    /// Title of the element, if it has a readable one
+    pub title: Option<String>,
+    pub message: String,
+}
+
+impl std::fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.title {
+            Some(title) => write!(f, "element #{} ({title}): {}", self.index, self.message),
+            None => write!(f, "element #{}: {}", self.index, self.message),
+        }
+    }
 }
 
 /// Parse one difficult table data from json data
 ///
 /// * prefix_url: json corresponding url's prefix, could be empty. Only used when data_url is a relative path
-///     e.g: Suppose our json is fetched from `https://stellabms.xyz/sl/header.json`, then prefix should be `https://stellabms.xyz/sl/`
-///     This behavior would not be used in most cases, unit test could ignore this.
+///   e.g: Suppose our json is fetched from `https://stellabms.xyz/sl/header.json`, then prefix should be `https://stellabms.xyz/sl/`
+///   This behavior would not be used in most cases, unit test could ignore this.
 /// * data: difficult table header json data
 pub fn parse_from_json(
     prefix_url: Option<String>,
     data: String,
 ) -> Result<DifficultTable, ParseError> {
+    let options = ParseOptions::default();
+    let mut header = parse_header(prefix_url, &data, &options)?;
+    let fetched = fetch_body(&default_client(), &header.data_url, &options, None)?;
+    let body = decode(fetched.raw, fetched.content_type.as_deref())?;
+    fill_contents(&mut header, &body, &options, &ActiveQuirks::default())?;
+    Ok(header)
+}
+
+/// Parse one difficult table from its header and body json, without any network access
+///
+/// The header is validated as in [`parse_from_json`], except data_url which is not fetched and kept as is (it
+/// may be relative or even empty)
+///
+/// # Example:
+/// ```text
+/// let dth = parse_from_parts(&fs::read_to_string("header.json")?, &fs::read_to_string("score.json")?)?;
+/// ```
+pub fn parse_from_parts(header_json: &str, body_json: &str) -> Result<DifficultTable, ParseError> {
+    let options = ParseOptions::default();
+    let mut header: DifficultTable = serde_json::from_str(header_json)?;
+    check_name_and_symbol(&header)?;
+    fill_contents(&mut header, body_json, &options, &ActiveQuirks::default())?;
+    Ok(header)
+}
+
+/// Deserialize and validate difficult table header json, see [`parse_from_json`] for the parameters
+///
+/// A relative data_url is resolved against prefix_url, so the returned header's data_url is always an absolute
+/// url with one of [`ParseOptions::allowed_schemes`]
+pub(crate) fn parse_header(
+    prefix_url: Option<String>,
+    data: &str,
+    options: &ParseOptions,
+) -> Result<DifficultTable, ParseError> {
+    if options.strict {
+        strict::check_header(data)?;
+    }
     let mut header: DifficultTable = serde_json::from_slice(data.as_bytes())?;
-    if header.name == "" {
+    check_name_and_symbol(&header)?;
+    if header.data_url.is_empty() {
         return Err(ParseError::CorruptedHeaderData(
-            "Difficult table name cannot be empty".to_owned(),
+            "Difficult table data_url cannot be empty".to_owned(),
         ));
     }
-    if header.symbol == "" {
+    header.data_url = resolve_data_url(prefix_url, &header.data_url, options)?.into();
+    Ok(header)
+}
+
+fn check_name_and_symbol(header: &DifficultTable) -> Result<(), ParseError> {
+    if header.name.is_empty() {
         return Err(ParseError::CorruptedHeaderData(
-            "Difficult table symbol cannot be empty".to_owned(),
+            "Difficult table name cannot be empty".to_owned(),
         ));
     }
-    if header.data_url == "" {
+    if header.symbol.is_empty() {
         return Err(ParseError::CorruptedHeaderData(
-            "Difficult table data_url cannot be empty".to_owned(),
+            "Difficult table symbol cannot be empty".to_owned(),
         ));
     }
-    if !header.data_url.starts_with("http") {
-        let mut prefix_url = prefix_url.ok_or(ParseError::CorruptedHeaderData(
-            "data_url is a relative path while no prefix url is provided".to_string(),
-        ))?;
-        if !prefix_url.ends_with("/") {
-            prefix_url.push_str("/");
-        }
-        header.data_url = format!("{prefix_url}{}", header.data_url);
-    }
-    let mut resp = reqwest::blocking::get(header.data_url.clone())?;
-    let mut body = String::new();
-    resp.read_to_string(&mut body)?;
-    header.contents = serde_json::from_slice(body.as_bytes())?;
-    header.levels = header
-        .contents
-        .iter()
-        .map(|content| content.level.clone())
-        .unique()
-        .sorted_by(|lhs, rhs| {
-            let ilhs = lhs.parse::<i32>().ok();
-            let irhs = rhs.parse::<i32>().ok();
-            if ilhs.is_none() || irhs.is_none() {
-                return Ord::cmp(lhs, rhs);
+    Ok(())
+}
+
+/// Resolve data_url against prefix_url if it's a relative one, then check its scheme is allowed
+fn resolve_data_url(
+    prefix_url: Option<String>,
+    data_url: &str,
+    options: &ParseOptions,
+) -> Result<Url, ParseError> {
+    let invalid = |reason: String| ParseError::InvalidDataUrl(data_url.to_string(), reason);
+    let url = match Url::parse(data_url) {
+        Ok(url) => url,
+        Err(url::ParseError::RelativeUrlWithoutBase) => {
+            let mut prefix_url =
+                prefix_url.ok_or_else(|| invalid("relative path with no base".to_string()))?;
+            if !prefix_url.ends_with('/') {
+                prefix_url.push('/');
             }
-            return Ord::cmp(&ilhs.unwrap(), &irhs.unwrap());
+            let base = Url::parse(&prefix_url)
+                .map_err(|err| invalid(format!("invalid base `{prefix_url}`: {err}")))?;
+            base.join(data_url)
+                .map_err(|err| invalid(format!("cannot resolve against `{prefix_url}`: {err}")))?
+        }
+        Err(err) => return Err(invalid(err.to_string())),
+    };
+    if !options.allows_scheme(url.scheme()) {
+        return Err(invalid(format!("unsupported scheme {}", url.scheme())));
+    }
+    Ok(url)
+}
+
+/// Extract the `content` of a table page's `<meta name="bmstable" content="...">` tag
+///
+/// The page is parsed as html, so the tag may span lines, be minified, use any quoting or attribute order
+pub(crate) fn meta_content(page: &str) -> Result<String, ParseError> {
+    let document = Html::parse_document(page);
+    let meta = Selector::parse("meta").unwrap();
+    let meta = document
+        .select(&meta)
+        .find(|meta| {
+            meta.attr("name")
+                .is_some_and(|name| name.trim().eq_ignore_ascii_case("bmstable"))
         })
-        .collect();
-    return Ok(header);
+        .ok_or(ParseError::CorruptedHeaderData(
+            "Cannot fetch meta line".to_string(),
+        ))?;
+    meta.attr("content")
+        .map(|content| content.trim().to_string())
+        .filter(|content| !content.is_empty())
+        .ok_or_else(|| ParseError::CorruptedHeaderData("Cannot parse meta line".to_string()))
+}
+
+/// Extract a header json embedded in one of a table page's inline `<script>` blocks, for pages without the
+/// bmstable meta tag
+///
+/// The header is the first json object in a script having both a `name` and a `data_url`, e.g.
+/// `var header = { "name": ..., "data_url": ... };` or a `<script type="application/json">` block
+pub(crate) fn script_header(page: &str) -> Option<String> {
+    let document = Html::parse_document(page);
+    let script = Selector::parse("script:not([src])").unwrap();
+    document.select(&script).find_map(|script| {
+        let text: String = script.text().collect();
+        text.match_indices('{').find_map(|(pos, _)| {
+            let object = serde_json::Deserializer::from_str(&text[pos..])
+                .into_iter::<serde_json::Map<String, serde_json::Value>>()
+                .next()?
+                .ok()?;
+            (object.contains_key("name") && object.contains_key("data_url"))
+                .then(|| serde_json::Value::Object(object).to_string())
+        })
+    })
+}
+
+/// Target of a `<meta http-equiv="refresh" content="0;url=...">` redirect, for legacy pages moved elsewhere
+pub(crate) fn refresh_url(page: &str) -> Option<String> {
+    let document = Html::parse_document(page);
+    let meta = Selector::parse("meta").unwrap();
+    let content = document.select(&meta).find_map(|meta| {
+        meta.attr("http-equiv")
+            .filter(|equiv| equiv.trim().eq_ignore_ascii_case("refresh"))
+            .and(meta.attr("content"))
+    })?;
+    let (_, target) = content.split_once([';', ','])?;
+    let target = target.trim();
+    let target = match target.get(..4) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("url=") => &target[4..],
+        _ => target,
+    };
+    let target = target.trim().trim_matches(['"', '\'']).trim();
+    (!target.is_empty()).then(|| target.to_string())
+}
+
+/// Resolve a meta tag's content against the page it comes from
+///
+/// The content is usually relative to the page, but some pages use an absolute (or protocol/root relative)
+/// url, which is honored as is
+pub(crate) fn resolve_meta_url(page_url: &str, content: &str) -> Result<String, ParseError> {
+    let page_url = Url::parse(page_url).map_err(|err| {
+        ParseError::CorruptedHeaderData(format!("Invalid page url {page_url}: {err}"))
+    })?;
+    let url = page_url.join(content).map_err(|err| {
+        ParseError::CorruptedHeaderData(format!("Invalid meta content {content}: {err}"))
+    })?;
+    Ok(url.into())
+}
+
+/// Urls visited while following a table page to its header, see [`ParseOptions::max_indirections`]
+pub(crate) struct Chain {
+    visited: Vec<String>,
+    max_indirections: usize,
+}
+
+impl Chain {
+    pub(crate) fn new(options: &ParseOptions) -> Self {
+        Chain {
+            visited: Vec::new(),
+            max_indirections: options.max_indirections,
+        }
+    }
+
+    /// Record one more hop, fails on loops and on chains longer than allowed
+    pub(crate) fn visit(&mut self, url: &str) -> Result<(), ParseError> {
+        let looped = self.visited.iter().any(|visited| visited == url);
+        self.visited.push(url.to_string());
+        if looped || self.visited.len() > self.max_indirections + 1 {
+            return Err(ParseError::TooManyRedirections(std::mem::take(
+                &mut self.visited,
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Fetch a header page (either the table html or the header json), see [`fetch`]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip(fetcher, options))
+)]
+pub(crate) fn fetch_header(
+    fetcher: &dyn Fetcher,
+    url: &str,
+    options: &ParseOptions,
+    deadline: Option<Instant>,
+) -> Result<Fetched, ParseError> {
+    let stage = Some(Stage::Header);
+    fetch(fetcher, url, options, stage, deadline, |source| {
+        ParseError::HeaderFetch {
+            url: url.to_string(),
+            source,
+        }
+    })
+}
+
+/// Fetch the body json pointed by a header's data_url, see [`fetch`]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip(fetcher, options))
+)]
+pub(crate) fn fetch_body(
+    fetcher: &dyn Fetcher,
+    url: &str,
+    options: &ParseOptions,
+    deadline: Option<Instant>,
+) -> Result<Fetched, ParseError> {
+    let stage = Some(Stage::Body);
+    fetch(fetcher, url, options, stage, deadline, |source| {
+        ParseError::BodyFetch {
+            url: url.to_string(),
+            source,
+        }
+    })
+}
+
+/// Fetch a published registry index, see [`fetch`]
+pub(crate) fn fetch_registry(
+    fetcher: &dyn Fetcher,
+    url: &str,
+    options: &ParseOptions,
+) -> Result<Fetched, ParseError> {
+    fetch(fetcher, url, options, None, None, |source| {
+        ParseError::RegistryFetch {
+            url: url.to_string(),
+            source,
+        }
+    })
+}
+
+/// Fetch a table index page, see [`fetch`]
+pub(crate) fn fetch_index(
+    fetcher: &dyn Fetcher,
+    url: &str,
+    options: &ParseOptions,
+) -> Result<Fetched, ParseError> {
+    fetch(fetcher, url, options, None, None, |source| {
+        ParseError::IndexFetch {
+            url: url.to_string(),
+            source,
+        }
+    })
+}
+
+/// Fetch a LR2IR ranking, see [`fetch`]
+#[cfg(feature = "lr2ir")]
+pub(crate) fn fetch_ranking(
+    fetcher: &dyn Fetcher,
+    url: &str,
+    options: &ParseOptions,
+) -> Result<Fetched, ParseError> {
+    fetch(fetcher, url, options, None, None, |source| {
+        ParseError::RankingFetch {
+            url: url.to_string(),
+            source,
+        }
+    })
+}
+
+/// Raw response body of one fetched url
+#[derive(Debug)]
+pub(crate) struct Fetched {
+    pub(crate) raw: Vec<u8>,
+    /// `Content-Type` of the response, see [`decode`]
+    pub(crate) content_type: Option<String>,
+    /// Set when the url is gone and the body comes from the Internet Archive instead
+    pub(crate) capture: Option<ArchiveCapture>,
+    /// Url the response actually came from, see [`crate::DifficultTable::fetched_urls`]
+    pub(crate) url: String,
+}
+
+/// GET an url through a [`Fetcher`] following [`HttpPolicy`], request failures are reported through `on_error`
+///
+/// The url's scheme must be one of [`ParseOptions::allowed_schemes`], `file` urls are read from disk. With
+/// [`ParseOptions::wayback_fallback`], an url that is gone is fetched from its latest Internet Archive capture.
+/// Progress of a `stage` is reported to [`ParseOptions::progress`], reading stops with [`FetchError::TooLarge`]
+/// past [`ParseOptions::max_response_size`] and with [`FetchError::DeadlineExceeded`] past `deadline`
+fn fetch(
+    fetcher: &dyn Fetcher,
+    url: &str,
+    options: &ParseOptions,
+    stage: Option<Stage>,
+    deadline: Option<Instant>,
+    on_error: impl FnOnce(FetchError) -> ParseError,
+) -> Result<Fetched, ParseError> {
+    #[cfg(feature = "tracing")]
+    let started = std::time::Instant::now();
+    let urls = match target(url, options)? {
+        Target::File(path) => {
+            let read = || {
+                check_deadline(deadline)?;
+                let len = std::fs::metadata(&path)?.len();
+                options.check_response_size(len, Some(len))?;
+                Ok(std::fs::read(&path)?)
+            };
+            let raw = read().map_err(on_error)?;
+            if let Some(stage) = stage {
+                options.report_progress(stage, raw.len() as u64, Some(raw.len() as u64));
+            }
+            return Ok(Fetched {
+                raw,
+                content_type: None,
+                capture: None,
+                url: url.to_string(),
+            });
+        }
+        Target::Http(urls) => urls,
+    };
+    let mut progress = |done, total| {
+        check_deadline(deadline)?;
+        options.check_response_size(done, total)?;
+        if let Some(stage) = stage {
+            options.report_progress(stage, done, total);
+        }
+        Ok(())
+    };
+    let mut fetch_one = |url: &String| {
+        fetch_with_retry(fetcher, url, options, deadline, &mut progress)
+            .map(|(raw, content_type)| (raw, content_type, url.clone()))
+    };
+    let mut resp = fetch_one(&urls[0]);
+    for fallback in &urls[1..] {
+        if resp.is_err() {
+            resp = fetch_one(fallback);
+        }
+    }
+    if let Err(err) = &resp {
+        if let Some(flipped) = flipped_scheme(url, &urls, err, options) {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(url, flipped, "retrying with the scheme flipped");
+            if let Ok(flipped) = fetch_one(&flipped) {
+                resp = Ok(flipped);
+            }
+        }
+    }
+    #[cfg(feature = "tracing")]
+    match &resp {
+        Ok((raw, content_type, _)) => tracing::debug!(
+            bytes = raw.len(),
+            content_type,
+            elapsed = ?started.elapsed(),
+            "fetched"
+        ),
+        Err(err) => tracing::warn!(error = %err, elapsed = ?started.elapsed(), "fetch failed"),
+    }
+    match resp {
+        Ok((raw, content_type, url)) => Ok(Fetched {
+            raw,
+            content_type,
+            capture: None,
+            url,
+        }),
+        Err(err)
+            if options.wayback_fallback && err.is_gone() && check_deadline(deadline).is_ok() =>
+        {
+            let archived = wayback::find_capture(fetcher, url).and_then(|capture| {
+                let (raw, content_type) = fetcher
+                    .fetch_with_progress(&capture.raw_url(), &mut progress)
+                    .ok()?;
+                Some(Fetched {
+                    raw,
+                    content_type,
+                    url: capture.raw_url(),
+                    capture: Some(capture),
+                })
+            });
+            archived.ok_or_else(|| on_error(err))
+        }
+        Err(err) => Err(on_error(err)),
+    }
+}
+
+/// Fetch an url, retrying transient failures following [`ParseOptions::retry`] as long as `deadline` allows it
+fn fetch_with_retry(
+    fetcher: &dyn Fetcher,
+    url: &str,
+    options: &ParseOptions,
+    deadline: Option<Instant>,
+    progress: &mut Progress<'_>,
+) -> Result<(Vec<u8>, Option<String>), FetchError> {
+    let policy = &options.retry;
+    let mut retry = 0;
+    loop {
+        check_deadline(deadline)?;
+        match fetcher.fetch_with_progress(url, progress) {
+            Err(err)
+                if retry + 1 < policy.attempts
+                    && err.is_retryable(policy)
+                    && fits_deadline(deadline, policy.delay(retry)) =>
+            {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(url, error = %err, retry, "retrying");
+                std::thread::sleep(policy.delay(retry));
+                retry += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// The url to try after `err` under [`ParseOptions::scheme_fallback`]: `url` with its scheme flipped, unless it
+/// was already tried or [`HttpPolicy::Reject`] forbids it
+pub(crate) fn flipped_scheme(
+    url: &str,
+    tried: &[String],
+    err: &FetchError,
+    options: &ParseOptions,
+) -> Option<String> {
+    if !options.scheme_fallback || err.retry_class() != Some(RetryOn::Connect) {
+        return None;
+    }
+    let flipped = if let Some(rest) = url.strip_prefix("http://") {
+        format!("https://{rest}")
+    } else if let Some(rest) = url.strip_prefix("https://") {
+        if options.http_policy == HttpPolicy::Reject {
+            return None;
+        }
+        format!("http://{rest}")
+    } else {
+        return None;
+    };
+    (!tried.contains(&flipped)).then_some(flipped)
+}
+
+/// Fail with [`FetchError::DeadlineExceeded`] once `deadline` is passed
+pub(crate) fn check_deadline(deadline: Option<Instant>) -> Result<(), FetchError> {
+    match deadline {
+        Some(deadline) if Instant::now() >= deadline => Err(FetchError::DeadlineExceeded),
+        _ => Ok(()),
+    }
+}
+
+/// Whether waiting `delay` still leaves time before `deadline`
+pub(crate) fn fits_deadline(deadline: Option<Instant>, delay: Duration) -> bool {
+    deadline.is_none_or(|deadline| Instant::now() + delay < deadline)
+}
+
+/// Where an url is fetched from, see [`target`]
+pub(crate) enum Target {
+    File(PathBuf),
+    /// Urls to try in order, the first successful response wins
+    Http(Vec<String>),
+}
+
+/// Check an url against [`ParseOptions::allowed_schemes`] and [`HttpPolicy`]
+pub(crate) fn target(url: &str, options: &ParseOptions) -> Result<Target, ParseError> {
+    let scheme = url.split_once(':').map_or("", |(scheme, _)| scheme);
+    if !options.allows_scheme(scheme) {
+        return Err(ParseError::UnsupportedScheme(scheme.to_string()));
+    }
+    if scheme.eq_ignore_ascii_case("file") {
+        return Url::parse(url)
+            .ok()
+            .and_then(|url| url.to_file_path().ok())
+            .map(Target::File)
+            .ok_or_else(|| ParseError::CorruptedHeaderData(format!("Invalid file url {url}")));
+    }
+    let insecure = url.starts_with("http://");
+    match options.http_policy {
+        HttpPolicy::Reject if insecure => Err(ParseError::InsecureUrl(url.to_string())),
+        HttpPolicy::Upgrade if insecure => Ok(Target::Http(vec![
+            format!("https://{}", &url["http://".len()..]),
+            url.to_string(),
+        ])),
+        _ => Ok(Target::Http(vec![url.to_string()])),
+    }
+}
+
+/// Decode a fetched document into UTF-8
+///
+/// The encoding is taken from, in order: a BOM, the charset of `content_type`, the `<meta charset>` (or
+/// `http-equiv`) tag of an html page. Undeclared documents are expected in UTF-8, with Shift_JIS as a fallback
+/// since many older Japanese tables are served that way
+pub(crate) fn decode(raw: Vec<u8>, content_type: Option<&str>) -> Result<String, ParseError> {
+    let declared = Encoding::for_bom(&raw)
+        .map(|(encoding, _)| encoding)
+        .or_else(|| content_type.and_then(charset))
+        .or_else(|| html_charset(&raw));
+    if let Some(encoding) = declared {
+        return Ok(encoding.decode(&raw).0.into_owned());
+    }
+    String::from_utf8(raw).or_else(|err| {
+        SHIFT_JIS
+            .decode_without_bom_handling_and_without_replacement(err.as_bytes())
+            .map(String::from)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, err.utf8_error()).into())
+    })
+}
+
+/// Encoding named by the `charset=` parameter in `text`
+fn charset(text: &str) -> Option<&'static Encoding> {
+    let lower = text.to_ascii_lowercase();
+    let label = &lower[lower.find("charset=")? + "charset=".len()..];
+    let label = label.trim_start_matches(['"', '\'']);
+    let len = label
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+        .unwrap_or(label.len());
+    Encoding::for_label(&label.as_bytes()[..len])
+}
+
+/// Encoding declared in the head of an html page, which only needs to be ASCII compatible to be found
+fn html_charset(raw: &[u8]) -> Option<&'static Encoding> {
+    let head = String::from_utf8_lossy(&raw[..raw.len().min(1024)]);
+    if !head.trim_start().starts_with('<') {
+        return None;
+    }
+    charset(&head)
+}
+
+/// Deserialize difficult table body json into header's contents, then compute levels from them
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(bytes = body.len()))
+)]
+pub(crate) fn fill_contents(
+    header: &mut DifficultTable,
+    body: &str,
+    options: &ParseOptions,
+    quirks: &ActiveQuirks,
+) -> Result<(), ParseError> {
+    #[cfg(feature = "tracing")]
+    let started = std::time::Instant::now();
+    if options.strict {
+        strict::check_body(body)?;
+    }
+    // a header's own contents, if any, are not the table's
+    header.contents.clear();
+    if options.lenient {
+        let elements: Vec<serde_json::Value> = serde_json::from_str(body)?;
+        for (index, element) in elements.into_iter().enumerate() {
+            let title = element["title"].as_str().map(str::to_string);
+            match serde_json::from_value(element) {
+                Ok(mut element) => {
+                    validate_hashes(&mut element, index, options, &mut header.warnings)?;
+                    header.contents.push(element);
+                }
+                Err(err) => header.warnings.push(ParseWarning {
+                    index,
+                    title,
+                    message: err.to_string(),
+                }),
+            }
+        }
+    } else {
+        header.contents = serde_json::from_slice(body.as_bytes())?;
+        for (index, element) in header.contents.iter_mut().enumerate() {
+            validate_hashes(element, index, options, &mut header.warnings)?;
+        }
+    }
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        elements = header.contents.len(),
+        warnings = header.warnings.len(),
+        elapsed = ?started.elapsed(),
+        "deserialized body"
+    );
+    #[cfg(feature = "tracing")]
+    let started = std::time::Instant::now();
+    quirks.post_body(header);
+    header.refresh_levels(options.level_ordering);
+    if options.content_ordering == ContentOrdering::ByLevel {
+        header.sort_contents();
+    }
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        levels = header.levels.len(),
+        elapsed = ?started.elapsed(),
+        "computed levels"
+    );
+    Ok(())
+}
+
+/// Apply [`ParseOptions::hash_validation`] to one body element at `index`
+fn validate_hashes(
+    element: &mut DifficultTableElement,
+    index: usize,
+    options: &ParseOptions,
+    warnings: &mut Vec<ParseWarning>,
+) -> Result<(), ParseError> {
+    if options.hash_validation == HashValidation::Keep {
+        return Ok(());
+    }
+    let mut malformed = Vec::new();
+    if let Some(md5) = normalize_hash(&mut element.md5) {
+        malformed.extend(md5.parse::<Md5>().err());
+    }
+    if let Some(sha256) = normalize_hash(&mut element.sha256) {
+        malformed.extend(sha256.parse::<Sha256>().err());
+    }
+    for err in malformed {
+        if options.hash_validation == HashValidation::Reject {
+            return Err(err);
+        }
+        warnings.push(ParseWarning {
+            index,
+            title: Some(element.title.clone()),
+            message: err.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Trim and lowercase a hash in place, None if it's empty
+fn normalize_hash(hash: &mut String) -> Option<&str> {
+    if hash.trim().is_empty() {
+        return None;
+    }
+    *hash = hash.trim().to_ascii_lowercase();
+    Some(hash)
 }
 
 #[cfg(test)]
+#[allow(clippy::useless_vec, clippy::len_zero)]
 mod test {
+    use std::{cell::Cell, io, time::Duration};
 
-    use super::parse_from_json;
+    use super::{
+        decode, fetch_body, fetch_header, fill_contents, meta_content, parse_from_json,
+        parse_from_parts, parse_header, refresh_url, resolve_meta_url, script_header, Chain,
+        FetchError, ParseError, SHIFT_JIS,
+    };
+    use crate::{
+        level::Level,
+        options::{
+            ContentOrdering, HashValidation, HttpPolicy, LevelOrdering, ParseOptions, RetryPolicy,
+        },
+        quirk::ActiveQuirks,
+    };
+    use reqwest::blocking::Client;
 
     #[test]
     pub fn test_basic_header_deserialize() {
@@ -117,6 +880,181 @@ mod test {
         )
     }
 
+    #[test]
+    pub fn should_resolve_relative_data_url() {
+        let header_content = r#"{ "name": "Satellite", "symbol": "sl", "data_url": "body.json" }"#;
+        let header = parse_header(
+            Some("https://stellabms.xyz/sl".to_string()),
+            header_content,
+            &ParseOptions::default(),
+        )
+        .expect("parse failed");
+        assert_eq!(header.data_url, "https://stellabms.xyz/sl/body.json");
+        assert!(parse_header(None, header_content, &ParseOptions::default()).is_err());
+
+        let header_content =
+            r#"{ "name": "Satellite", "symbol": "sl", "data_url": "../body.json" }"#;
+        let header = parse_header(
+            Some("https://stellabms.xyz/sl/".to_string()),
+            header_content,
+            &ParseOptions::default(),
+        )
+        .expect("parse failed");
+        assert_eq!(header.data_url, "https://stellabms.xyz/body.json");
+    }
+
+    #[test]
+    pub fn should_explain_invalid_data_url() {
+        let reason = |prefix_url: Option<&str>, data_url: &str| {
+            let header_content =
+                format!(r#"{{ "name": "Satellite", "symbol": "sl", "data_url": "{data_url}" }}"#);
+            match parse_header(
+                prefix_url.map(str::to_string),
+                &header_content,
+                &ParseOptions::default(),
+            ) {
+                Err(ParseError::InvalidDataUrl(_, reason)) => reason,
+                other => panic!("expected InvalidDataUrl, got {other:?}"),
+            }
+        };
+        assert_eq!(reason(None, "body.json"), "relative path with no base");
+        assert_eq!(
+            reason(None, "ftp://example.com/body.json"),
+            "unsupported scheme ftp"
+        );
+        assert_eq!(
+            reason(Some("file:///tables/"), "body.json"),
+            "unsupported scheme file"
+        );
+        assert!(reason(None, "http://exa mple.com/body.json").contains("invalid"));
+    }
+
+    #[test]
+    pub fn should_order_contents() {
+        let header_content = r#"{ "name": "Satellite", "symbol": "sl", "data_url": "http://example.com/body.json" }"#;
+        let body = r#"[
+            { "title": "b", "artist": "", "md5": "", "level": "2" },
+            { "title": "c", "artist": "", "md5": "", "level": "10" },
+            { "title": "a", "artist": "", "md5": "", "level": "2" }
+        ]"#;
+        let titles = |options: ParseOptions| {
+            let mut header = parse_header(None, header_content, &options).unwrap();
+            fill_contents(&mut header, body, &options, &ActiveQuirks::default()).unwrap();
+            header
+                .contents
+                .into_iter()
+                .map(|content| content.title)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(titles(ParseOptions::default()), ["b", "c", "a"]);
+        let by_level = ParseOptions {
+            content_ordering: ContentOrdering::ByLevel,
+            ..Default::default()
+        };
+        assert_eq!(titles(by_level.clone()), ["a", "b", "c"]);
+        let by_appearance = ParseOptions {
+            level_ordering: LevelOrdering::Appearance,
+            ..by_level
+        };
+        assert_eq!(titles(by_appearance), ["a", "b", "c"]);
+    }
+
+    #[test]
+    pub fn should_follow_http_policy() {
+        let options = |http_policy| ParseOptions {
+            http_policy,
+            ..Default::default()
+        };
+        // nothing listens on port 1, whatever the scheme
+        let url = "http://127.0.0.1:1/header.json";
+        assert!(matches!(
+            fetch_header(&Client::new(), url, &options(HttpPolicy::Reject), None),
+            Err(ParseError::InsecureUrl(_))
+        ));
+        assert!(matches!(
+            fetch_header(
+                &Client::new(),
+                "https://127.0.0.1:1/header.json",
+                &options(HttpPolicy::Reject),
+                None
+            ),
+            Err(ParseError::HeaderFetch { .. })
+        ));
+        // falls back to plain http, and reports the original url
+        assert!(matches!(
+            fetch_header(&Client::new(), url, &options(HttpPolicy::Upgrade), None),
+            Err(ParseError::HeaderFetch { url: failed, .. }) if failed == url
+        ));
+    }
+
+    #[test]
+    pub fn should_bound_indirections() {
+        let options = ParseOptions {
+            max_indirections: 2,
+            ..Default::default()
+        };
+        let mut chain = Chain::new(&options);
+        for url in ["a.html", "b.html", "header.json"] {
+            chain.visit(url).unwrap();
+        }
+        assert!(matches!(
+            chain.visit("body.json"),
+            Err(ParseError::TooManyRedirections(urls)) if urls.len() == 4
+        ));
+
+        let mut chain = Chain::new(&options);
+        chain.visit("a.html").unwrap();
+        chain.visit("b.html").unwrap();
+        let err = chain.visit("a.html").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Too many header indirections: a.html -> b.html -> a.html"
+        );
+    }
+
+    #[test]
+    pub fn should_resolve_meta_content() {
+        let page = r#"
+        <head>
+            <meta name="bmstable" content="header.json" />
+        </head>
+        "#;
+        assert_eq!(meta_content(page).unwrap(), "header.json");
+        assert_eq!(
+            meta_content(r#"<meta name="bmstable" content='https://example.com/h.json'>"#).unwrap(),
+            "https://example.com/h.json"
+        );
+        assert!(meta_content("<html></html>").is_err());
+        // layouts of real world pages the old line based scan choked on
+        for page in [
+            "<html><head><title>t</title><meta name=\"bmstable\" content=\"header.json\"></head></html>",
+            "<meta content=\"header.json\" name=\"bmstable\">",
+            "<meta\n    name=\"bmstable\"\n    content=\"header.json\"\n>",
+            "<META NAME='bmstable' CONTENT='header.json'>",
+            "<meta name=bmstable content=header.json>",
+        ] {
+            assert_eq!(meta_content(page).unwrap(), "header.json", "{page}");
+        }
+        assert!(meta_content(r#"<meta name="bmstable">"#).is_err());
+
+        let page_url = "https://stellabms.xyz/sl/table.html";
+        let cases = [
+            ("header.json", "https://stellabms.xyz/sl/header.json"),
+            (
+                "http://zris.work/sl/header.json",
+                "http://zris.work/sl/header.json",
+            ),
+            (
+                "//example.com/header.json",
+                "https://example.com/header.json",
+            ),
+            ("/header.json", "https://stellabms.xyz/header.json"),
+        ];
+        for (content, expected) in cases {
+            assert_eq!(resolve_meta_url(page_url, content).unwrap(), expected);
+        }
+    }
+
     #[test]
     pub fn should_fail_on_garbage() {
         let garbage = "}not even a json{";
@@ -169,4 +1107,199 @@ mod test {
             .iter()
             .all(|data| parse_from_json(None, data.to_string()).is_err()))
     }
+
+    #[test]
+    pub fn should_parse_from_parts_offline() {
+        let header = r#"{ "name": "Satellite", "symbol": "sl", "data_url": "score.json" }"#;
+        let body = r#"[
+            { "title": "b", "artist": "", "md5": "", "level": "1" },
+            { "title": "a", "artist": "", "md5": "", "level": "0" }
+        ]"#;
+        let dth = parse_from_parts(header, body).unwrap();
+        assert_eq!(dth.data_url, "score.json");
+        assert_eq!(dth.contents.len(), 2);
+        assert_eq!(dth.levels, [Level::from("0"), Level::from("1")]);
+        assert!(parse_from_parts(r#"{ "name": "", "symbol": "sl" }"#, body).is_err());
+        assert!(parse_from_parts(header, "{}").is_err());
+    }
+
+    #[test]
+    pub fn should_retry_transient_failures() {
+        let attempts = Cell::new(0);
+        let flaky = |url: &str| -> Result<Vec<u8>, FetchError> {
+            attempts.set(attempts.get() + 1);
+            match attempts.get() {
+                1 | 2 => Err(io::Error::from(io::ErrorKind::ConnectionReset).into()),
+                _ => Ok(url.as_bytes().to_vec()),
+            }
+        };
+        let url = "https://example.com/header.json";
+        let options = |attempts| ParseOptions {
+            retry: RetryPolicy {
+                backoff: Duration::ZERO,
+                ..RetryPolicy::new(attempts)
+            },
+            ..Default::default()
+        };
+        assert!(fetch_header(&flaky, url, &options(2), None).is_err());
+        attempts.set(0);
+        assert_eq!(
+            fetch_header(&flaky, url, &options(3), None).unwrap().raw,
+            url.as_bytes()
+        );
+        assert_eq!(attempts.get(), 3);
+
+        let missing = |url: &str| Err(FetchError::NotFound(url.to_string()));
+        let err = fetch_header(&missing, url, &options(3), None).unwrap_err();
+        assert!(
+            matches!(err, ParseError::HeaderFetch { source, .. } if source.retry_class().is_none())
+        );
+    }
+
+    #[test]
+    pub fn should_refuse_oversized_responses() {
+        let endless = |_: &str| -> Result<Vec<u8>, FetchError> { Ok(vec![b' '; 1024]) };
+        let url = "https://example.com/body.json";
+        let options = |max_response_size| ParseOptions {
+            max_response_size,
+            ..Default::default()
+        };
+        let err = fetch_body(&endless, url, &options(Some(1000)), None).unwrap_err();
+        assert!(matches!(
+            err,
+            ParseError::BodyFetch {
+                source: FetchError::TooLarge(1000),
+                ..
+            }
+        ));
+        assert!(fetch_body(&endless, url, &options(Some(1024)), None).is_ok());
+        assert!(fetch_body(&endless, url, &options(None), None).is_ok());
+    }
+
+    #[test]
+    pub fn should_find_headers_embedded_in_scripts() {
+        let page = r#"<html><head>
+            <script src="jquery.js"></script>
+            <script>
+                var options = { "sort": true };
+                var header = { "name": "Embedded", "symbol": "e", "data_url": "body.json" };
+            </script>
+        </head></html>"#;
+        let header: serde_json::Value =
+            serde_json::from_str(&script_header(page).unwrap()).unwrap();
+        assert_eq!(header["name"], "Embedded");
+        let page = r#"<script type="application/json">{"name":"t","symbol":"t","data_url":"b.json"}</script>"#;
+        assert!(script_header(page).is_some());
+        assert!(script_header("<script>var a = {};</script>").is_none());
+    }
+
+    #[test]
+    pub fn should_find_meta_refresh_targets() {
+        for (page, target) in [
+            (
+                r#"<meta http-equiv="refresh" content="0;url=https://example.com/new/table.html">"#,
+                "https://example.com/new/table.html",
+            ),
+            (
+                r#"<META HTTP-EQUIV="Refresh" CONTENT="3; URL='../moved.html'">"#,
+                "../moved.html",
+            ),
+        ] {
+            assert_eq!(refresh_url(page).unwrap(), target);
+        }
+        assert!(refresh_url(r#"<meta http-equiv="refresh" content="30">"#).is_none());
+    }
+
+    #[test]
+    pub fn should_detect_charsets() {
+        let sjis = |text: &str| SHIFT_JIS.encode(text).0.into_owned();
+        let page =
+            "<html><head><meta charset=\"Shift_JIS\"><title>発狂BMS難易度表</title></head></html>";
+        assert_eq!(decode(sjis(page), None).unwrap(), page);
+        let page = "<meta http-equiv=\"Content-Type\" content=\"text/html; charset=EUC-JP\">難易度";
+        let (raw, _, _) = encoding_rs::EUC_JP.encode(page);
+        assert_eq!(decode(raw.into_owned(), None).unwrap(), page);
+        let body = r#"[{ "title": "ズンドコ", "level": "★1" }]"#;
+        assert_eq!(
+            decode(sjis(body), Some("application/json; charset=shift_jis")).unwrap(),
+            body
+        );
+        // undeclared, but not UTF-8
+        assert_eq!(decode(sjis(body), None).unwrap(), body);
+        assert_eq!(decode(body.as_bytes().to_vec(), None).unwrap(), body);
+        assert!(decode(vec![0xff, 0xff, 0xff], None).is_err());
+    }
+
+    #[test]
+    pub fn should_skip_malformed_elements_when_lenient() {
+        let fetcher = |url: &str| -> Result<Vec<u8>, FetchError> {
+            match url {
+                "https://example.com/header.json" => {
+                    Ok(br#"{ "name": "t", "symbol": "t", "data_url": "body.json" }"#.to_vec())
+                }
+                _ => Ok(br#"[
+                    { "title": "a", "artist": "", "md5": "", "level": "1" },
+                    { "title": "no level", "artist": "", "md5": "" },
+                    { "artist": "", "md5": "", "level": "2" }
+                ]"#
+                .to_vec()),
+            }
+        };
+        let url = "https://example.com/header.json";
+        assert!(matches!(
+            crate::parse_with_fetcher(&fetcher, url, &ParseOptions::default()),
+            Err(ParseError::SerdeError(_))
+        ));
+
+        let options = ParseOptions {
+            lenient: true,
+            ..Default::default()
+        };
+        let dth = crate::parse_with_fetcher(&fetcher, url, &options).unwrap();
+        assert_eq!(dth.contents.len(), 1);
+        assert_eq!(dth.levels, [Level::from("1")]);
+        assert_eq!(
+            dth.warnings
+                .iter()
+                .map(|warning| (warning.index, warning.title.as_deref()))
+                .collect::<Vec<_>>(),
+            [(1, Some("no level")), (2, None)]
+        );
+    }
+
+    #[test]
+    pub fn should_normalize_and_validate_hashes() {
+        let header =
+            r#"{ "name": "t", "symbol": "t", "data_url": "https://example.com/body.json" }"#;
+        let body = format!(
+            r#"[
+                {{ "title": "a", "artist": "", "md5": " {} ", "sha256": "", "level": "1" }},
+                {{ "title": "b", "artist": "", "md5": "abc", "level": "1" }}
+            ]"#,
+            "AB".repeat(16)
+        );
+        let parse = |hash_validation| {
+            let mut dth = serde_json::from_str(header)?;
+            let options = ParseOptions {
+                hash_validation,
+                ..Default::default()
+            };
+            fill_contents(&mut dth, &body, &options, &ActiveQuirks::default()).map(|_| dth)
+        };
+        let kept = parse(HashValidation::Keep).unwrap();
+        assert_eq!(kept.contents[0].md5, format!(" {} ", "AB".repeat(16)));
+        assert!(kept.warnings.is_empty());
+
+        let normalized = parse(HashValidation::Normalize).unwrap();
+        assert_eq!(normalized.contents[0].md5, "ab".repeat(16));
+        assert_eq!(normalized.contents[0].sha256, "");
+        assert_eq!(normalized.warnings.len(), 1);
+        assert_eq!(normalized.warnings[0].index, 1);
+        assert_eq!(normalized.warnings[0].title.as_deref(), Some("b"));
+
+        assert!(matches!(
+            parse(HashValidation::Reject),
+            Err(ParseError::InvalidHash(_))
+        ));
+    }
 }
@@ -0,0 +1,277 @@
+use std::{fmt, sync::Arc};
+
+use serde_json::Value;
+
+use crate::{
+    id::TableId,
+    modal::{DifficultTable, TableHeader},
+    registry::Registry,
+};
+
+/// Hooks working around one awkward table, see [`Quirks`]
+///
+/// Every hook does nothing by default. Raw hooks see the bytes as fetched (before being decoded as UTF-8),
+/// `post_body` runs before `levels` is computed, so editing levels there is fine
+pub trait Quirk: Send + Sync {
+    /// Name shown in [`Quirks`]' debug output
+    fn name(&self) -> &str;
+
+    /// Raw table page or header json
+    fn pre_header(&self, _raw: &mut Vec<u8>) {}
+
+    /// Parsed and validated header
+    fn post_header(&self, _header: &mut TableHeader) {}
+
+    /// Raw body json
+    fn pre_body(&self, _raw: &mut Vec<u8>) {}
+
+    /// Table with its contents deserialized
+    fn post_body(&self, _table: &mut DifficultTable) {}
+}
+
+/// Which tables a [`Quirk`] applies to, matched against the url passed to [`crate::parse`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuirkTarget {
+    /// The url contains this pattern
+    UrlContains(String),
+    /// The url is the one of this table in [`Registry::builtin`]
+    RegistryId(TableId),
+}
+
+/// Set of [`Quirk`]s with the tables they apply to, part of [`crate::ParseOptions`]
+///
+/// Defaults to [`Quirks::builtin`], users could add their own with [`Quirks::add`]
+///
+/// # Example:
+/// ```text
+/// struct StripComments;
+/// impl Quirk for StripComments { ... }
+///
+/// let mut options = ParseOptions::default();
+/// options.quirks.add(QuirkTarget::UrlContains("example.com".to_string()), StripComments);
+/// ```
+#[derive(Clone, Default)]
+pub struct Quirks {
+    quirks: Vec<(QuirkTarget, Arc<dyn Quirk>)>,
+}
+
+impl Quirks {
+    /// No quirk at all
+    pub fn none() -> Self {
+        Quirks::default()
+    }
+
+    /// Work arounds for known tables:
+    /// * Overjoy hosts its body as an array of per-level arrays, which is flattened
+    /// * legacy GENOCIDE (発狂BMS難易度表) mirrors have levels prefixed by the table symbol (`★12`), which is stripped
+    /// * nekokan hosted pages are served as Shift_JIS, which is transcoded to UTF-8
+    pub fn builtin() -> Self {
+        let mut quirks = Quirks::none();
+        quirks.add(QuirkTarget::UrlContains("overjoy".to_string()), NestedBody);
+        quirks.add(
+            QuirkTarget::UrlContains("genocide".to_string()),
+            SymbolPrefixedLevels,
+        );
+        quirks.add(
+            QuirkTarget::RegistryId(TableId::from("insane")),
+            SymbolPrefixedLevels,
+        );
+        quirks.add(
+            QuirkTarget::UrlContains("nekokan.dyndns.info".to_string()),
+            ShiftJis,
+        );
+        quirks
+    }
+
+    pub fn add(&mut self, target: QuirkTarget, quirk: impl Quirk + 'static) {
+        self.quirks.push((target, Arc::new(quirk)));
+    }
+
+    /// Quirks applying to `url`, in the order they were added
+    pub(crate) fn select(&self, url: &str) -> ActiveQuirks {
+        let registry = Registry::builtin();
+        let id = registry
+            .tables()
+            .iter()
+            .find(|table| table.url == url)
+            .map(|table| &table.id);
+        let quirks = self
+            .quirks
+            .iter()
+            .filter(|(target, _)| match target {
+                QuirkTarget::UrlContains(pattern) => url.contains(pattern.as_str()),
+                QuirkTarget::RegistryId(target) => id == Some(target),
+            })
+            .map(|(_, quirk)| Arc::clone(quirk))
+            .collect();
+        ActiveQuirks(quirks)
+    }
+}
+
+impl fmt::Debug for Quirks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(
+                self.quirks
+                    .iter()
+                    .map(|(target, quirk)| (target, quirk.name())),
+            )
+            .finish()
+    }
+}
+
+/// Quirks selected for one parse
+#[derive(Default)]
+pub(crate) struct ActiveQuirks(Vec<Arc<dyn Quirk>>);
+
+impl ActiveQuirks {
+    pub(crate) fn pre_header(&self, raw: &mut Vec<u8>) {
+        self.0.iter().for_each(|quirk| quirk.pre_header(raw));
+    }
+
+    pub(crate) fn post_header(&self, header: &mut TableHeader) {
+        self.0.iter().for_each(|quirk| quirk.post_header(header));
+    }
+
+    pub(crate) fn pre_body(&self, raw: &mut Vec<u8>) {
+        self.0.iter().for_each(|quirk| quirk.pre_body(raw));
+    }
+
+    pub(crate) fn post_body(&self, table: &mut DifficultTable) {
+        self.0.iter().for_each(|quirk| quirk.post_body(table));
+    }
+}
+
+/// Flatten a body of nested arrays
+struct NestedBody;
+
+impl Quirk for NestedBody {
+    fn name(&self) -> &str {
+        "nested body"
+    }
+
+    fn pre_body(&self, raw: &mut Vec<u8>) {
+        let Ok(Value::Array(groups)) = serde_json::from_slice::<Value>(raw) else {
+            return;
+        };
+        if !groups.iter().any(Value::is_array) {
+            return;
+        }
+        let flattened: Vec<Value> = groups
+            .into_iter()
+            .flat_map(|group| match group {
+                Value::Array(elements) => elements,
+                element => vec![element],
+            })
+            .collect();
+        if let Ok(flattened) = serde_json::to_vec(&flattened) {
+            *raw = flattened;
+        }
+    }
+}
+
+/// Strip the table symbol from levels like `★12`
+struct SymbolPrefixedLevels;
+
+impl Quirk for SymbolPrefixedLevels {
+    fn name(&self) -> &str {
+        "symbol prefixed levels"
+    }
+
+    fn post_body(&self, table: &mut DifficultTable) {
+        let symbol = table.symbol.clone();
+        if symbol.is_empty() {
+            return;
+        }
+        for content in &mut table.contents {
+            if let Some(level) = content.level.as_str().strip_prefix(symbol.as_str()) {
+                content.level = level.into();
+            }
+        }
+    }
+}
+
+/// Transcode Shift_JIS pages to UTF-8, pages already in valid UTF-8 are left untouched
+struct ShiftJis;
+
+impl ShiftJis {
+    fn transcode(raw: &mut Vec<u8>) {
+        if std::str::from_utf8(raw).is_ok() {
+            return;
+        }
+        let (decoded, _, _) = encoding_rs::SHIFT_JIS.decode(raw);
+        *raw = decoded.into_owned().into_bytes();
+    }
+}
+
+impl Quirk for ShiftJis {
+    fn name(&self) -> &str {
+        "shift_jis"
+    }
+
+    fn pre_header(&self, raw: &mut Vec<u8>) {
+        ShiftJis::transcode(raw);
+    }
+
+    fn pre_body(&self, raw: &mut Vec<u8>) {
+        ShiftJis::transcode(raw);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::level::Level;
+
+    #[test]
+    pub fn should_select_by_url_and_registry_id() {
+        let quirks = Quirks::builtin();
+        let names = |url: &str| -> Vec<String> {
+            let active = quirks.select(url);
+            active
+                .0
+                .iter()
+                .map(|quirk| quirk.name().to_string())
+                .collect()
+        };
+        assert_eq!(
+            names("http://zris.work/bmstable/insane/insane_header.json"),
+            ["symbol prefixed levels"]
+        );
+        assert_eq!(
+            names("http://nekokan.dyndns.info/~lobsak/genocide/insane.html"),
+            ["symbol prefixed levels", "shift_jis"]
+        );
+        assert!(names("https://stellabms.xyz/sl/table.html").is_empty());
+        assert!(Quirks::none()
+            .select("http://example.com/overjoy.html")
+            .0
+            .is_empty());
+    }
+
+    #[test]
+    pub fn builtin_quirks_should_fix_their_tables() {
+        let mut raw = br#"[[{ "level": "1" }], [{ "level": "2" }, { "level": "2" }]]"#.to_vec();
+        NestedBody.pre_body(&mut raw);
+        let body: Value = serde_json::from_slice(&raw).unwrap();
+        assert_eq!(body.as_array().unwrap().len(), 3);
+
+        let mut table: DifficultTable =
+            serde_json::from_value(json!({ "name": "発狂BMS難易度表", "symbol": "★" })).unwrap();
+        table.contents = serde_json::from_value(json!([
+            { "title": "", "artist": "", "md5": "", "level": "★12" },
+            { "title": "", "artist": "", "md5": "", "level": "???" },
+        ]))
+        .unwrap();
+        SymbolPrefixedLevels.post_body(&mut table);
+        assert_eq!(table.contents[0].level, Level::from("12"));
+        assert_eq!(table.contents[1].level, Level::from("???"));
+
+        let (raw, _, _) = encoding_rs::SHIFT_JIS.encode("発狂");
+        let mut raw = raw.into_owned();
+        ShiftJis.pre_header(&mut raw);
+        assert_eq!(String::from_utf8(raw).unwrap(), "発狂");
+    }
+}
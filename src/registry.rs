@@ -0,0 +1,254 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+
+use crate::{
+    bulk::parse_many_with_fetcher,
+    client::default_client,
+    fetcher::Fetcher,
+    hash::Sha256,
+    id::TableId,
+    modal::DifficultTable,
+    options::ParseOptions,
+    parser::{self, ParseError},
+};
+
+/// Represents one well-known difficult table
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct KnownTable {
+    /// Short identifier used to refer to the table, e.g. `satellite`
+    pub id: TableId,
+    /// BMS difficult table name
+    pub name: String,
+    /// BMS difficult table symbol
+    pub symbol: String,
+    /// BMS difficult table url, could be passed to [`crate::parse`] directly
+    pub url: String,
+}
+
+/// Json array of the tables this crate knows out of the box
+const BUILTIN_TABLES: &str = include_str!("registry.json");
+
+/// Tables fetched by [`Registry::parse_all`] at a time
+const PARSE_ALL_CONCURRENCY: usize = 8;
+
+/// Parse a known table, its [`DifficultTable::id`] is then its registry id
+///
+/// # Example:
+/// ```text
+/// let registry = Registry::builtin();
+/// let satellite = parse_known(registry.get("satellite").unwrap())?;
+/// ```
+pub fn parse_known(table: &KnownTable) -> Result<DifficultTable, ParseError> {
+    let mut dth =
+        crate::parse_with_fetcher(&default_client(), &table.url, &ParseOptions::default())?;
+    dth.set_id(table.id.clone());
+    Ok(dth)
+}
+
+/// Parse every table of [`Registry::builtin`], i.e. the standard set of tables, see [`Registry::parse_all`]
+///
+/// # Example:
+/// ```text
+/// for (known, dth) in parse_all_known() {
+///     match dth {
+///         Ok(dth) => println!("{}: {} charts", known.id, dth.contents.len()),
+///         Err(err) => eprintln!("{}: {err}", known.id),
+///     }
+/// }
+/// ```
+pub fn parse_all_known() -> Vec<(KnownTable, Result<DifficultTable, ParseError>)> {
+    Registry::builtin().parse_all(&ParseOptions::default())
+}
+
+/// A list of known difficult tables, looked up by id
+///
+/// The registry starts with the built-in tables (embedded from `registry.json`) and could be extended by a local
+/// json file, or refreshed from a published index with [`Registry::update_from`]. Both are an array of
+/// [`KnownTable`], and their tables replace the known ones with the same id.
+///
+/// # Example:
+/// ```text
+/// let mut registry = Registry::builtin();
+/// registry.load_extension("registry.json")?;
+/// let satellite = registry.get("satellite").unwrap();
+/// let dth = parse(satellite.url.clone())?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct Registry {
+    tables: Vec<KnownTable>,
+}
+
+impl Registry {
+    /// Registry containing only the built-in tables
+    pub fn builtin() -> Self {
+        let tables = serde_json::from_str(BUILTIN_TABLES).expect("built-in registry is valid");
+        Registry { tables }
+    }
+
+    /// All known tables, built-in ones first
+    pub fn tables(&self) -> &[KnownTable] {
+        &self.tables
+    }
+
+    /// Find a table by its id
+    pub fn get(&self, id: &str) -> Option<&KnownTable> {
+        self.tables.iter().find(|table| table.id == id)
+    }
+
+    /// Add a table, replacing the one with the same id if any
+    pub fn add(&mut self, table: KnownTable) {
+        match self.tables.iter_mut().find(|known| known.id == table.id) {
+            Some(known) => *known = table,
+            None => self.tables.push(table),
+        }
+    }
+
+    /// Parse every table of this registry at once, ids set as [`parse_known`] does, in registry order
+    pub fn parse_all(
+        &self,
+        options: &ParseOptions,
+    ) -> Vec<(KnownTable, Result<DifficultTable, ParseError>)> {
+        self.parse_all_with_fetcher(&default_client(), options)
+    }
+
+    /// Same as [`Registry::parse_all`], through a [`Fetcher`], see [`crate::parse_many_with_fetcher`]
+    pub fn parse_all_with_fetcher(
+        &self,
+        fetcher: &(dyn Fetcher + Sync),
+        options: &ParseOptions,
+    ) -> Vec<(KnownTable, Result<DifficultTable, ParseError>)> {
+        let urls: Vec<String> = self.tables.iter().map(|table| table.url.clone()).collect();
+        let results = parse_many_with_fetcher(fetcher, &urls, PARSE_ALL_CONCURRENCY, options);
+        self.tables
+            .iter()
+            .zip(results)
+            .map(|(known, result)| {
+                let result = result.map(|mut dth| {
+                    dth.set_id(known.id.clone());
+                    dth
+                });
+                (known.clone(), result)
+            })
+            .collect()
+    }
+
+    /// Merge tables from an extension file into this registry
+    ///
+    /// * `path` - json file containing an array of [`KnownTable`]
+    pub fn load_extension(&mut self, path: impl AsRef<Path>) -> Result<(), ParseError> {
+        let data = fs::read_to_string(path)?;
+        let tables: Vec<KnownTable> = serde_json::from_str(&data)?;
+        for table in tables {
+            self.add(table);
+        }
+        Ok(())
+    }
+
+    /// Merge tables from a published index into this registry
+    ///
+    /// * `url` - index url, the index has the same format as an extension file
+    /// * `fingerprint` - expected sha256 of the index, published along with it through another channel. Nothing
+    ///   is merged if the index doesn't match
+    pub fn update_from(&mut self, url: &str, fingerprint: &Sha256) -> Result<(), ParseError> {
+        let raw = parser::fetch_registry(&default_client(), url, &ParseOptions::default())?.raw;
+        self.merge_index(&raw, fingerprint)
+    }
+
+    fn merge_index(&mut self, raw: &[u8], fingerprint: &Sha256) -> Result<(), ParseError> {
+        let actual = Sha256::from_bytes(sha2::Sha256::digest(raw).into());
+        if actual != *fingerprint {
+            return Err(ParseError::FingerprintMismatch {
+                expected: *fingerprint,
+                actual,
+            });
+        }
+        let tables: Vec<KnownTable> = serde_json::from_slice(raw)?;
+        for table in tables {
+            self.add(table);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::FetchError;
+
+    #[test]
+    pub fn builtin_ids_should_be_unique() {
+        let registry = Registry::builtin();
+        for table in registry.tables() {
+            assert_eq!(registry.get(table.id.as_str()), Some(table));
+        }
+    }
+
+    #[test]
+    pub fn add_should_replace_same_id() {
+        let mut registry = Registry::builtin();
+        let len = registry.tables().len();
+        let mirror = KnownTable {
+            id: TableId::from("satellite"),
+            name: "Satellite".to_string(),
+            symbol: "sl".to_string(),
+            url: "http://zris.work/bmstable/satellite/header.json".to_string(),
+        };
+        registry.add(mirror.clone());
+        assert_eq!(registry.tables().len(), len);
+        assert_eq!(registry.get("satellite"), Some(&mirror));
+
+        registry.add(KnownTable {
+            id: TableId::from("mine"),
+            ..mirror
+        });
+        assert_eq!(registry.tables().len(), len + 1);
+    }
+
+    #[test]
+    pub fn should_parse_every_known_table() {
+        let known = |id: &str| KnownTable {
+            id: TableId::from(id),
+            name: id.to_string(),
+            symbol: id.to_string(),
+            url: format!("https://example.com/{id}/header.json"),
+        };
+        let registry = Registry {
+            tables: vec![known("up"), known("down")],
+        };
+        let fetcher = |url: &str| -> Result<Vec<u8>, FetchError> {
+            match url {
+                "https://example.com/up/header.json" => {
+                    Ok(br#"{ "name": "Up", "symbol": "u", "data_url": "body.json" }"#.to_vec())
+                }
+                "https://example.com/up/body.json" => Ok(b"[]".to_vec()),
+                _ => Err(FetchError::NotFound(url.to_string())),
+            }
+        };
+        let parsed = registry.parse_all_with_fetcher(&fetcher, &ParseOptions::default());
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].0.id, "up");
+        assert_eq!(parsed[0].1.as_ref().unwrap().id(), "up");
+        assert_eq!(parsed[1].0.id, "down");
+        assert!(parsed[1].1.is_err());
+    }
+
+    #[test]
+    pub fn index_should_match_its_fingerprint() {
+        let index = r#"[{ "id": "mine", "name": "Mine", "symbol": "m", "url": "https://example.com/table.html" }]"#;
+        let fingerprint = Sha256::from_bytes(sha2::Sha256::digest(index.as_bytes()).into());
+        let mut registry = Registry::builtin();
+        let tampered = index.replace("example.com", "example.org");
+        assert!(matches!(
+            registry.merge_index(tampered.as_bytes(), &fingerprint),
+            Err(ParseError::FingerprintMismatch { .. })
+        ));
+        assert!(registry.get("mine").is_none());
+
+        registry
+            .merge_index(index.as_bytes(), &fingerprint)
+            .unwrap();
+        assert_eq!(registry.get("mine").unwrap().name, "Mine");
+    }
+}
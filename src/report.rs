@@ -0,0 +1,387 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write as _,
+};
+
+use itertools::Itertools;
+use url::Url;
+
+use crate::{level::Level, modal::DifficultTable};
+
+/// Health report of one difficult table, see [`DifficultTable::report`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableReport {
+    pub name: String,
+    pub symbol: String,
+    /// Number of elements per level, in `levels` order
+    pub level_counts: Vec<(Level, usize)>,
+    pub elements: usize,
+    /// Elements without a sha256
+    pub missing_sha256: usize,
+    /// Lowercased md5s shared by more than one element, with how many elements share them
+    pub duplicate_md5s: Vec<(String, usize)>,
+    /// Header urls, then element urls, that look dead: not http(s), loopback/private hosts or dynamic DNS hosts
+    pub dead_looking_urls: Vec<DeadLookingUrl>,
+    pub courses: Vec<CourseCoverage>,
+}
+
+/// Counts of one difficult table, see [`DifficultTable::stats`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableStats {
+    /// Number of elements per level, in `levels` order
+    pub level_counts: Vec<(Level, usize)>,
+    pub elements: usize,
+    pub with_sha256: usize,
+    pub without_sha256: usize,
+    pub courses: usize,
+}
+
+/// Elements sharing one hash, see [`DifficultTable::duplicates`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Duplicate {
+    /// `md5` or `sha256`
+    pub field: &'static str,
+    /// Lowercased hash
+    pub hash: String,
+    /// Positions of the elements in `contents`, in order
+    pub positions: Vec<usize>,
+    /// Distinct levels of the elements, in position order
+    pub levels: Vec<Level>,
+}
+
+impl Duplicate {
+    /// Whether the same chart is listed at different levels, rather than merely twice
+    pub fn is_cross_level(&self) -> bool {
+        self.levels.len() > 1
+    }
+}
+
+/// One url that looks dead, see [`TableReport::dead_looking_urls`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeadLookingUrl {
+    /// `data_url`, `original_url`, or an element's `url`, `url_diff` or `url_pack`
+    pub field: &'static str,
+    /// Position of the element in `contents`, `None` for a header url
+    pub position: Option<usize>,
+    pub url: String,
+}
+
+/// How many charts of one course are part of the table
+#[derive(Debug, Clone, PartialEq)]
+pub struct CourseCoverage {
+    pub name: String,
+    pub charts: usize,
+    pub in_table: usize,
+}
+
+impl TableReport {
+    /// Ratio of elements without a sha256, 0 for an empty table
+    pub fn missing_sha256_ratio(&self) -> f64 {
+        if self.elements == 0 {
+            return 0.0;
+        }
+        self.missing_sha256 as f64 / self.elements as f64
+    }
+
+    /// Render the report as markdown
+    pub fn to_markdown(&self) -> String {
+        let mut md = format!("# {} ({})\n\n", self.name, self.symbol);
+        let _ = writeln!(
+            md,
+            "{} element(s), {} level(s), {} without sha256 ({:.1}%)\n",
+            self.elements,
+            self.level_counts.len(),
+            self.missing_sha256,
+            self.missing_sha256_ratio() * 100.0
+        );
+        md.push_str("## Levels\n\n| Level | Elements |\n| --- | --- |\n");
+        for (level, count) in &self.level_counts {
+            let _ = writeln!(md, "| {}{level} | {count} |", self.symbol);
+        }
+        if !self.duplicate_md5s.is_empty() {
+            md.push_str("\n## Duplicate md5s\n\n");
+            for (md5, count) in &self.duplicate_md5s {
+                let _ = writeln!(md, "- `{md5}` x{count}");
+            }
+        }
+        if !self.dead_looking_urls.is_empty() {
+            md.push_str("\n## Dead looking urls\n\n");
+            for dead in &self.dead_looking_urls {
+                let (field, url) = (dead.field, &dead.url);
+                match dead.position {
+                    Some(position) => {
+                        let _ = writeln!(md, "- {field} of element #{position}: `{url}`");
+                    }
+                    None => {
+                        let _ = writeln!(md, "- {field}: `{url}`");
+                    }
+                }
+            }
+        }
+        if !self.courses.is_empty() {
+            md.push_str("\n## Courses\n\n| Course | Charts in table |\n| --- | --- |\n");
+            for course in &self.courses {
+                let _ = writeln!(
+                    md,
+                    "| {} | {}/{} |",
+                    course.name, course.in_table, course.charts
+                );
+            }
+        }
+        md
+    }
+}
+
+/// Whether an url is unlikely to be reachable: not an http(s) url, a loopback or private address, or a dynamic
+/// DNS host (most of which are long gone for old tables)
+pub(crate) fn looks_dead(url: &str) -> bool {
+    let Ok(url) = Url::parse(url) else {
+        return true;
+    };
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return true;
+    }
+    match url.host() {
+        None => true,
+        Some(url::Host::Domain(domain)) => domain == "localhost" || domain.contains(".dyndns."),
+        Some(url::Host::Ipv4(ip)) => ip.is_loopback() || ip.is_private() || ip.is_unspecified(),
+        Some(url::Host::Ipv6(ip)) => ip.is_loopback() || ip.is_unspecified(),
+    }
+}
+
+impl DifficultTable {
+    /// Song counts per level and overall, sha256 coverage and number of courses
+    ///
+    /// Cheap compared to [`DifficultTable::report`], which also looks for problems
+    pub fn stats(&self) -> TableStats {
+        let mut level_counts: HashMap<&Level, usize> = HashMap::new();
+        for content in &self.contents {
+            *level_counts.entry(&content.level).or_default() += 1;
+        }
+        let without_sha256 = self
+            .contents
+            .iter()
+            .filter(|content| content.sha256.is_empty())
+            .count();
+        TableStats {
+            level_counts: self
+                .levels
+                .iter()
+                .map(|level| (level.clone(), level_counts.get(level).copied().unwrap_or(0)))
+                .collect(),
+            elements: self.contents.len(),
+            with_sha256: self.contents.len() - without_sha256,
+            without_sha256,
+            courses: self.courses.len(),
+        }
+    }
+
+    /// Elements sharing an md5 or a sha256 (case insensitively), empty hashes are ignored
+    ///
+    /// md5 duplicates come first, each kind ordered by first position
+    pub fn duplicates(&self) -> Vec<Duplicate> {
+        let mut duplicates = Vec::new();
+        for field in ["md5", "sha256"] {
+            let mut positions: HashMap<String, Vec<usize>> = HashMap::new();
+            for (pos, content) in self.contents.iter().enumerate() {
+                let hash = match field {
+                    "md5" => &content.md5,
+                    _ => &content.sha256,
+                };
+                if !hash.is_empty() {
+                    positions
+                        .entry(hash.to_ascii_lowercase())
+                        .or_default()
+                        .push(pos);
+                }
+            }
+            let mut found: Vec<Duplicate> = positions
+                .into_iter()
+                .filter(|(_, positions)| positions.len() > 1)
+                .map(|(hash, positions)| Duplicate {
+                    field,
+                    hash,
+                    levels: positions
+                        .iter()
+                        .map(|pos| &self.contents[*pos].level)
+                        .unique()
+                        .cloned()
+                        .collect(),
+                    positions,
+                })
+                .collect();
+            found.sort_by_key(|duplicate| duplicate.positions[0]);
+            duplicates.extend(found);
+        }
+        duplicates
+    }
+
+    /// One call health report: per level counts, missing sha256s, duplicate md5s, dead looking header and
+    /// element urls and course coverage
+    pub fn report(&self) -> TableReport {
+        let stats = self.stats();
+        let mut duplicate_md5s: Vec<(String, usize)> = self
+            .duplicates()
+            .into_iter()
+            .filter(|duplicate| duplicate.field == "md5")
+            .map(|duplicate| (duplicate.hash, duplicate.positions.len()))
+            .collect();
+        duplicate_md5s.sort();
+        let header_urls = [
+            ("data_url", &self.data_url),
+            ("original_url", &self.original_url),
+        ]
+        .map(|(field, url)| (None, field, url));
+        let element_urls = self.contents.iter().enumerate().flat_map(|(position, e)| {
+            [
+                ("url", &e.url),
+                ("url_diff", &e.url_diff),
+                ("url_pack", &e.url_pack),
+            ]
+            .map(|(field, url)| (Some(position), field, url))
+        });
+        let dead_looking_urls = header_urls
+            .into_iter()
+            .chain(element_urls)
+            .filter(|(_, _, url)| !url.is_empty() && looks_dead(url))
+            .map(|(position, field, url)| DeadLookingUrl {
+                field,
+                position,
+                url: url.clone(),
+            })
+            .collect();
+        let md5s: HashSet<String> = self
+            .contents
+            .iter()
+            .map(|content| content.md5.to_ascii_lowercase())
+            .collect();
+        let courses = self
+            .courses
+            .iter()
+            .map(|course| CourseCoverage {
+                name: course.name.clone(),
+                charts: course.md5.len(),
+                in_table: course
+                    .md5
+                    .iter()
+                    .filter(|md5| md5s.contains(&md5.to_ascii_lowercase()))
+                    .count(),
+            })
+            .collect();
+        TableReport {
+            name: self.name.clone(),
+            symbol: self.symbol.clone(),
+            level_counts: stats.level_counts,
+            elements: stats.elements,
+            missing_sha256: stats.without_sha256,
+            duplicate_md5s,
+            dead_looking_urls,
+            courses,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    pub fn should_report_table_health() {
+        let mut table: DifficultTable = serde_json::from_value(json!({
+            "name": "Satellite",
+            "symbol": "sl",
+            "data_url": "https://stellabms.xyz/sl/score.json",
+            "original_url": "http://nekokan.dyndns.info/~lobsak/genocide/insane.html",
+            "course": [[{ "name": "sl0", "constraint": [], "trophy": [], "md5": ["AA", "cc"] }]],
+        }))
+        .unwrap();
+        for (md5, sha256, level, url) in [
+            (
+                "aa",
+                "",
+                "0",
+                "https://manbow.nothing.sh/event/event.cgi?action=More_def&num=1",
+            ),
+            ("AA", "x", "0", ""),
+            ("bb", "", "1", "http://192.168.0.1/bms.zip"),
+        ] {
+            table.add_element(
+                serde_json::from_value(json!({
+                    "title": "", "artist": "", "md5": md5, "sha256": sha256, "level": level, "url": url
+                }))
+                .unwrap(),
+            );
+        }
+        let stats = table.stats();
+        assert_eq!(
+            (stats.elements, stats.with_sha256, stats.courses),
+            (3, 1, 1)
+        );
+        let report = table.report();
+        assert_eq!(
+            report.level_counts,
+            [(Level::from("0"), 2), (Level::from("1"), 1)]
+        );
+        assert_eq!(report.missing_sha256, 2);
+        assert!((report.missing_sha256_ratio() - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(report.duplicate_md5s, [("aa".to_string(), 2)]);
+        let duplicates = table.duplicates();
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].positions, [0, 1]);
+        assert!(!duplicates[0].is_cross_level());
+        let dead: Vec<_> = report
+            .dead_looking_urls
+            .iter()
+            .map(|dead| (dead.field, dead.position))
+            .collect();
+        assert_eq!(dead, [("original_url", None), ("url", Some(2))]);
+        assert_eq!(
+            report.courses,
+            [CourseCoverage {
+                name: "sl0".to_string(),
+                charts: 2,
+                in_table: 1
+            }]
+        );
+        let md = report.to_markdown();
+        assert!(md.starts_with("# Satellite (sl)"));
+        assert!(md.contains("| sl0 | 2 |"));
+        assert!(md.contains("| sl0 | 1/2 |"));
+        assert!(md.contains("- url of element #2: `http://192.168.0.1/bms.zip`"));
+    }
+
+    #[test]
+    pub fn should_find_cross_level_duplicates() {
+        let mut table: DifficultTable =
+            serde_json::from_value(json!({ "name": "t", "symbol": "t" })).unwrap();
+        for (md5, sha256, level) in [("aa", "S", "1"), ("bb", "", "2"), ("cc", "s", "3")] {
+            table.add_element(
+                serde_json::from_value(json!({
+                    "title": "", "artist": "", "md5": md5, "sha256": sha256, "level": level
+                }))
+                .unwrap(),
+            );
+        }
+        assert_eq!(
+            table.duplicates(),
+            [Duplicate {
+                field: "sha256",
+                hash: "s".to_string(),
+                positions: vec![0, 2],
+                levels: vec![Level::from("1"), Level::from("3")],
+            }]
+        );
+        assert!(table.duplicates()[0].is_cross_level());
+    }
+
+    #[test]
+    pub fn should_spot_dead_looking_urls() {
+        assert!(looks_dead("not an url"));
+        assert!(looks_dead("ftp://example.com/table.html"));
+        assert!(looks_dead("http://127.0.0.1:8080/table.html"));
+        assert!(looks_dead("http://192.168.1.2/table.html"));
+        assert!(!looks_dead("https://stellabms.xyz/sl/table.html"));
+    }
+}
@@ -0,0 +1,163 @@
+use std::time::{Instant, SystemTime};
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    modal::TableHeader,
+    options::ParseOptions,
+    parser::{self, Chain, Fetched, ParseError},
+    quirk::ActiveQuirks,
+    wayback::ArchiveCapture,
+};
+
+/// State of one url being resolved into its header and body, shared by the blocking and async drivers
+///
+/// Drivers fetch what the resolver asks for, starting with the table url as a header page:
+/// * a header page is fed to [`Resolver::header_page`], then [`Resolver::step`] tells what's next
+/// * a header's data_url is fetched as a body and fed to [`Resolver::body`], which either returns the body or
+///   hands it back as one more header page
+///
+/// Indirections are followed up to [`ParseOptions::max_indirections`] times: an html page may point at another
+/// html page, and a header's data_url may point at yet another header (or html page) instead of a body
+pub(crate) struct Resolver<'a> {
+    url: String,
+    options: &'a ParseOptions,
+    pub(crate) quirks: ActiveQuirks,
+    /// See [`ParseOptions::deadline`]
+    pub(crate) deadline: Option<Instant>,
+    chain: Chain,
+    archive: Option<ArchiveCapture>,
+    fetched_urls: Vec<String>,
+}
+
+/// What a driver should do after [`Resolver::step`]
+pub(crate) enum Step {
+    /// Fetch this header page
+    Follow(String),
+    /// The header is resolved, fetch its data_url as a body
+    Header(Box<TableHeader>),
+}
+
+/// Document fetched from a header's data_url, see [`Resolver::body`]
+pub(crate) enum Document {
+    Body(String),
+    /// Yet another header page, to be fed to [`Resolver::step`] as if it was fetched from the data_url
+    HeaderPage(String),
+}
+
+impl<'a> Resolver<'a> {
+    /// Check the table url before anything is fetched, whatever its suffix: documents are told apart by their
+    /// content
+    pub(crate) fn new(url: &str, options: &'a ParseOptions) -> Result<Self, ParseError> {
+        let scheme = url.split_once(':').map_or("", |(scheme, _)| scheme);
+        if !options.allows_scheme(scheme) {
+            return Err(ParseError::UnsupportedScheme(scheme.to_string()));
+        }
+        Ok(Resolver {
+            url: url.to_string(),
+            options,
+            quirks: options.quirks.select(url),
+            deadline: options.deadline_from_now(),
+            chain: Chain::new(options),
+            archive: None,
+            fetched_urls: Vec::new(),
+        })
+    }
+
+    pub(crate) fn options(&self) -> &'a ParseOptions {
+        self.options
+    }
+
+    /// Decode a fetched header page, passing it through quirks first
+    pub(crate) fn header_page(&mut self, fetched: Fetched) -> Result<String, ParseError> {
+        let (mut raw, content_type) = self.record(fetched);
+        self.quirks.pre_header(&mut raw);
+        parser::decode(raw, content_type.as_deref())
+    }
+
+    /// Turn a header page (either the table html or the header json) into the header, or into the url of the
+    /// next page for html pages' meta tags
+    pub(crate) fn step(&mut self, url: &str, page: &str) -> Result<Step, ParseError> {
+        self.chain.visit(url)?;
+        if page.is_empty() {
+            return Err(ParseError::CorruptedHeaderData(format!(
+                "Get nothing from {}",
+                url
+            )));
+        }
+        // urls of custom schemes may have no path at all (e.g. `ipfs:Qm...`), relative data_urls then fail to
+        // resolve against them
+        let prefix_url = url
+            .rfind('/')
+            .map_or(url, |slash| &url[..=slash])
+            .to_owned();
+        // A header json is used as is, whatever the url looks like
+        let page_start = page.trim_start();
+        if page_start.starts_with('{') || (url.ends_with(".json") && !page_start.starts_with('<')) {
+            return self.header(prefix_url, page);
+        }
+        if !page_start.starts_with('<') && !page.contains("<meta") {
+            return Err(ParseError::UnSupportedURLFormat);
+        }
+        // Otherwise, we need an extra step to get the header json content
+        // <meta name="bmstable" content="header.json">
+        //                                -----------> what we want
+        let content = match parser::meta_content(page) {
+            Ok(content) => content,
+            Err(err) => {
+                // a few pages embed the header in a script rather than pointing at it
+                if let Some(header) = parser::script_header(page) {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(url, "found header embedded in a script");
+                    return self.header(prefix_url, &header);
+                }
+                // and legacy ones only redirect to where the table moved, one more hop
+                parser::refresh_url(page).ok_or(err)?
+            }
+        };
+        let next = parser::resolve_meta_url(url, &content)?;
+        #[cfg(feature = "tracing")]
+        tracing::debug!(url, next, "following meta tag");
+        Ok(Step::Follow(next))
+    }
+
+    fn header(&self, prefix_url: String, json: &str) -> Result<Step, ParseError> {
+        let mut header = parser::parse_header(Some(prefix_url), json, self.options)?;
+        self.quirks.post_header(&mut header);
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            name = header.name,
+            symbol = header.symbol,
+            data_url = header.data_url,
+            "parsed header"
+        );
+        Ok(Step::Header(Box::new(header)))
+    }
+
+    /// Decode the document fetched from a header's data_url, which may be one more header page rather than a body
+    pub(crate) fn body(
+        &mut self,
+        header: &mut TableHeader,
+        fetched: Fetched,
+    ) -> Result<Document, ParseError> {
+        let (mut raw, content_type) = self.record(fetched);
+        self.quirks.pre_body(&mut raw);
+        let body = parser::decode(raw, content_type.as_deref())?;
+        // a body is an array, anything looking like an object or a page is one more indirection
+        if body.trim_start().starts_with(['{', '<']) {
+            return Ok(Document::HeaderPage(body));
+        }
+        header.archive = self.archive.take();
+        header.fetched_urls = std::mem::take(&mut self.fetched_urls);
+        header.source_url = self.url.clone();
+        header.fetched_at = Some(DateTime::<Utc>::from(SystemTime::now()));
+        Ok(Document::Body(body))
+    }
+
+    /// Keep the first Internet Archive capture used, and every url fetched
+    fn record(&mut self, fetched: Fetched) -> (Vec<u8>, Option<String>) {
+        self.archive = self.archive.take().or(fetched.capture);
+        self.fetched_urls.push(fetched.url);
+        (fetched.raw, fetched.content_type)
+    }
+}
@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::modal::DifficultTable;
+
+/// Romanized title and artist of one element, filled by [`DifficultTable::romanize`]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Romanized {
+    pub title: String,
+    pub artist: String,
+}
+
+/// Source of kanji readings used by [`romanize`], enabled by the `romaji` feature
+///
+/// Kana is romanized on its own, kanji needs a reading source (a dictionary, a morphological analyzer...).
+/// `HashMap<String, String>` works as a simple dictionary, words are matched longest first
+pub trait KanjiReadings {
+    /// Kana reading of a run of kanji, `None` leaves the run untouched
+    fn reading(&self, kanji: &str) -> Option<String>;
+}
+
+/// No reading at all, kanji is left as is
+pub struct NoReadings;
+
+impl KanjiReadings for NoReadings {
+    fn reading(&self, _kanji: &str) -> Option<String> {
+        None
+    }
+}
+
+impl KanjiReadings for HashMap<String, String> {
+    fn reading(&self, kanji: &str) -> Option<String> {
+        let mut reading = String::new();
+        let mut rest = kanji;
+        let mut found = false;
+        while let Some(first) = rest.chars().next() {
+            let word = rest
+                .char_indices()
+                .map(|(i, c)| &rest[..i + c.len_utf8()])
+                .rev()
+                .find_map(|word| self.get(word).map(|kana| (word, kana)));
+            match word {
+                Some((word, kana)) => {
+                    reading.push_str(kana);
+                    rest = &rest[word.len()..];
+                    found = true;
+                }
+                None => {
+                    reading.push(first);
+                    rest = &rest[first.len_utf8()..];
+                }
+            }
+        }
+        found.then_some(reading)
+    }
+}
+
+impl DifficultTable {
+    /// Attach the romanized title and artist to every element, see [`romanize`]
+    pub fn romanize(&mut self, readings: &impl KanjiReadings) {
+        for content in &mut self.contents {
+            content.romanized = Some(Romanized {
+                title: romanize(&content.title, readings),
+                artist: romanize(&content.artist, readings),
+            });
+        }
+    }
+}
+
+/// Romanize the Japanese parts of a text (Hepburn), anything else is kept as is
+///
+/// # Example:
+/// ```text
+/// assert_eq!(romanize("ぶっちぎりトレイン", &NoReadings), "butchigiritorein");
+/// ```
+pub fn romanize(text: &str, readings: &impl KanjiReadings) -> String {
+    let mut romanized = String::new();
+    let mut rest = text;
+    while let Some(first) = rest.chars().next() {
+        if is_kanji(first) {
+            let end = rest.find(|c| !is_kanji(c)).unwrap_or(rest.len());
+            let (kanji, tail) = rest.split_at(end);
+            match readings.reading(kanji) {
+                Some(reading) => romanized.push_str(&romanize_kana(&reading)),
+                None => romanized.push_str(kanji),
+            }
+            rest = tail;
+        } else {
+            let end = rest.find(is_kanji).unwrap_or(rest.len());
+            let (kana, tail) = rest.split_at(end);
+            romanized.push_str(&romanize_kana(kana));
+            rest = tail;
+        }
+    }
+    romanized
+}
+
+fn is_kanji(c: char) -> bool {
+    matches!(c, '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' | '々')
+}
+
+/// Katakana is read as hiragana
+fn to_hiragana(c: char) -> char {
+    match c {
+        'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+        _ => c,
+    }
+}
+
+fn syllable(c: char) -> Option<&'static str> {
+    Some(match c {
+        'あ' | 'ぁ' => "a",
+        'い' | 'ぃ' | 'ゐ' => "i",
+        'う' | 'ぅ' => "u",
+        'え' | 'ぇ' | 'ゑ' => "e",
+        'お' | 'ぉ' | 'を' => "o",
+        'か' | 'ゕ' => "ka",
+        'き' => "ki",
+        'く' => "ku",
+        'け' | 'ゖ' => "ke",
+        'こ' => "ko",
+        'が' => "ga",
+        'ぎ' => "gi",
+        'ぐ' => "gu",
+        'げ' => "ge",
+        'ご' => "go",
+        'さ' => "sa",
+        'し' => "shi",
+        'す' => "su",
+        'せ' => "se",
+        'そ' => "so",
+        'ざ' => "za",
+        'じ' | 'ぢ' => "ji",
+        'ず' | 'づ' => "zu",
+        'ぜ' => "ze",
+        'ぞ' => "zo",
+        'た' => "ta",
+        'ち' => "chi",
+        'つ' => "tsu",
+        'て' => "te",
+        'と' => "to",
+        'だ' => "da",
+        'で' => "de",
+        'ど' => "do",
+        'な' => "na",
+        'に' => "ni",
+        'ぬ' => "nu",
+        'ね' => "ne",
+        'の' => "no",
+        'は' => "ha",
+        'ひ' => "hi",
+        'ふ' => "fu",
+        'へ' => "he",
+        'ほ' => "ho",
+        'ば' => "ba",
+        'び' => "bi",
+        'ぶ' => "bu",
+        'べ' => "be",
+        'ぼ' => "bo",
+        'ぱ' => "pa",
+        'ぴ' => "pi",
+        'ぷ' => "pu",
+        'ぺ' => "pe",
+        'ぽ' => "po",
+        'ま' => "ma",
+        'み' => "mi",
+        'む' => "mu",
+        'め' => "me",
+        'も' => "mo",
+        'や' | 'ゃ' => "ya",
+        'ゆ' | 'ゅ' => "yu",
+        'よ' | 'ょ' => "yo",
+        'ら' => "ra",
+        'り' => "ri",
+        'る' => "ru",
+        'れ' => "re",
+        'ろ' => "ro",
+        'わ' | 'ゎ' => "wa",
+        'ん' => "n",
+        'ゔ' => "vu",
+        _ => return None,
+    })
+}
+
+/// Romanize kana, the small ones combine with the previous syllable (`きゃ`, `ファ`), `っ` doubles the next
+/// consonant and `ー` repeats the previous vowel
+fn romanize_kana(text: &str) -> String {
+    let mut romanized = String::new();
+    // romaji of the previous syllable, small kana rewrite it
+    let mut previous = String::new();
+    let mut double_next = false;
+    for c in text.chars() {
+        let c = to_hiragana(c);
+        let small_y = matches!(c, 'ゃ' | 'ゅ' | 'ょ');
+        let small_vowel = matches!(c, 'ぁ' | 'ぃ' | 'ぅ' | 'ぇ' | 'ぉ');
+        match syllable(c) {
+            Some(kana) if (small_y || small_vowel) && previous.len() > 1 => {
+                let stem = previous.strip_suffix('i').filter(|_| small_y);
+                previous = match stem {
+                    // しゃ, ちゃ, じゃ
+                    Some(stem) if stem.ends_with("sh") || stem.ends_with("ch") || stem == "j" => {
+                        format!("{stem}{}", &kana[1..])
+                    }
+                    Some(stem) => format!("{stem}{kana}"),
+                    None => format!("{}{kana}", &previous[..previous.len() - 1]),
+                };
+            }
+            Some(kana) => {
+                romanized.push_str(&previous);
+                previous = kana.to_string();
+                if std::mem::take(&mut double_next) {
+                    let first = if kana.starts_with("ch") {
+                        't'
+                    } else {
+                        kana.as_bytes()[0] as char
+                    };
+                    if !"aiueon".contains(first) {
+                        previous.insert(0, first);
+                    }
+                }
+            }
+            None if c == 'っ' => {
+                romanized.push_str(&std::mem::take(&mut previous));
+                double_next = true;
+            }
+            None if c == 'ー' => {
+                if let Some(vowel) = previous.chars().last().filter(|c| "aiueo".contains(*c)) {
+                    previous.push(vowel);
+                }
+            }
+            None => {
+                romanized.push_str(&std::mem::take(&mut previous));
+                double_next = false;
+                romanized.push(c);
+            }
+        }
+    }
+    romanized.push_str(&previous);
+    romanized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn should_romanize_kana_and_known_kanji() {
+        assert_eq!(
+            romanize("ぶっちぎりトレイン", &NoReadings),
+            "butchigiritorein"
+        );
+        assert_eq!(romanize("キャッシュ", &NoReadings), "kyasshu");
+        assert_eq!(romanize("ファンタジー", &NoReadings), "fantajii");
+        assert_eq!(romanize("Ice (ティアーズ)", &NoReadings), "Ice (tiaazu)");
+        assert_eq!(romanize("発狂", &NoReadings), "発狂");
+
+        let readings: HashMap<String, String> = [("発狂", "はっきょう"), ("難易度", "なんいど")]
+            .into_iter()
+            .map(|(kanji, kana)| (kanji.to_string(), kana.to_string()))
+            .collect();
+        assert_eq!(romanize("発狂BMS難易度表", &readings), "hakkyouBMSnanido表");
+    }
+}
@@ -0,0 +1,90 @@
+use std::borrow::Cow;
+
+use schemars::{json_schema, schema_for, JsonSchema, Schema, SchemaGenerator};
+use serde_json::Value;
+
+use crate::{
+    level::Level,
+    modal::{DifficultTable, DifficultTableElement},
+};
+
+/// JSON Schema of a header json, as this crate understands the format, enabled by the `schemars` feature
+///
+/// Unknown fields are allowed, as they are by the parser. Meant for editors and CI of table authors
+///
+/// # Example:
+/// ```text
+/// fs::write("header.schema.json", serde_json::to_string_pretty(&header_schema())?)?;
+/// ```
+pub fn header_schema() -> Value {
+    let mut schema = schema_for!(DifficultTable);
+    schema.insert("title".to_owned(), "bmstable header".into());
+    // data_url defaults to empty for loading tables back, the parser rejects a header without one
+    if let Some(Value::Array(required)) = schema.get_mut("required") {
+        required.push("data_url".into());
+    }
+    schema.to_value()
+}
+
+/// JSON Schema of a body json (the document data_url points at), see [`header_schema`]
+pub fn body_schema() -> Value {
+    let mut schema = schema_for!(Vec<DifficultTableElement>);
+    schema.insert("title".to_owned(), "bmstable body".into());
+    schema.to_value()
+}
+
+/// Levels are strings, some tables write them as numbers
+impl JsonSchema for Level {
+    fn inline_schema() -> bool {
+        true
+    }
+
+    fn schema_name() -> Cow<'static, str> {
+        "Level".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        string_or_number(generator)
+    }
+}
+
+pub(crate) fn string_or_number(_: &mut SchemaGenerator) -> Schema {
+    json_schema!({ "type": ["string", "number"] })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::de::DeserializeOwned;
+    use serde_json::json;
+
+    use super::*;
+    use crate::modal::DifficultTableCourse;
+
+    /// Every field the schema requires is one the parser can't do without
+    fn assert_required<T: DeserializeOwned>(schema: &Value, complete: Value) {
+        assert!(serde_json::from_value::<T>(complete.clone()).is_ok());
+        for field in schema["required"].as_array().unwrap() {
+            let mut partial = complete.clone();
+            partial
+                .as_object_mut()
+                .unwrap()
+                .shift_remove(field.as_str().unwrap());
+            assert!(
+                serde_json::from_value::<T>(partial).is_err(),
+                "{field} is required by the schema only"
+            );
+        }
+    }
+
+    #[test]
+    pub fn required_fields_should_match_the_parser() {
+        assert_required::<DifficultTableElement>(
+            &body_schema()["$defs"]["DifficultTableElement"],
+            json!({ "title": "", "artist": "", "md5": "", "level": 1 }),
+        );
+        assert_required::<DifficultTableCourse>(
+            &header_schema()["$defs"]["DifficultTableCourse"],
+            json!({ "name": "", "constraint": [], "trophy": [], "md5": [] }),
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use serde::Serialize;
+
+use crate::{
+    diff::{diff_contents, TableDiff},
+    id::TableId,
+    level::Level,
+    modal::{DifficultTable, SharedTable},
+    options::ParseOptions,
+    parser::ParseError,
+    subscription::TableSubscriptions,
+};
+
+/// Latest (and previous) version of every served table, enabled by the `serve` feature
+///
+/// Tables are keyed by [`DifficultTable::id`]. Inserting a table with a known id keeps the replaced one, so
+/// that `/tables/{id}/diff` could tell what changed. Re-inserting an unchanged table (same
+/// [`DifficultTable::content_hash`]) keeps the previous version as is
+#[derive(Debug, Default)]
+pub struct TableStore {
+    tables: RwLock<HashMap<TableId, StoredTable>>,
+}
+
+#[derive(Debug)]
+struct StoredTable {
+    current: SharedTable,
+    previous: Option<SharedTable>,
+}
+
+impl TableStore {
+    pub fn new() -> Self {
+        TableStore::default()
+    }
+
+    /// Add or replace a table
+    pub fn insert(&self, table: DifficultTable) {
+        let table = table.into_shared();
+        let mut tables = self.tables.write().expect("table store is poisoned");
+        let previous = match tables.remove(&table.id()) {
+            // A periodic refresh of an unchanged table would otherwise empty its diff
+            Some(stored) if stored.current.content_hash() == table.content_hash() => {
+                stored.previous
+            }
+            stored => stored.map(|stored| stored.current),
+        };
+        tables.insert(
+            table.id(),
+            StoredTable {
+                current: table,
+                previous,
+            },
+        );
+    }
+
+    pub fn get(&self, id: &str) -> Option<SharedTable> {
+        let tables = self.tables.read().expect("table store is poisoned");
+        tables
+            .get(&TableId::from(id))
+            .map(|stored| SharedTable::clone(&stored.current))
+    }
+
+    /// Every stored table, ordered by id
+    pub fn tables(&self) -> Vec<SharedTable> {
+        let tables = self.tables.read().expect("table store is poisoned");
+        let mut tables: Vec<SharedTable> = tables
+            .values()
+            .map(|stored| SharedTable::clone(&stored.current))
+            .collect();
+        tables.sort_by_key(|table| table.id());
+        tables
+    }
+
+    /// Parse every enabled subscription into the store, returns the failures by subscription label
+    ///
+    /// This blocks on network, call it from a blocking context (e.g. `tokio::task::spawn_blocking`)
+    pub fn refresh(
+        &self,
+        subscriptions: &TableSubscriptions,
+        options: &ParseOptions,
+    ) -> Vec<(String, ParseError)> {
+        let mut failures = Vec::new();
+        for subscription in subscriptions.enabled() {
+            match subscription.parse(options) {
+                Ok(table) => self.insert(table),
+                Err(err) => failures.push((subscription.label().to_string(), err)),
+            }
+        }
+        failures
+    }
+
+    fn diff(&self, id: &str) -> Option<TableDiff> {
+        let tables = self.tables.read().expect("table store is poisoned");
+        let stored = tables.get(&TableId::from(id))?;
+        let current = &stored.current.contents;
+        let previous = stored
+            .previous
+            .as_ref()
+            .map_or(&[][..], |previous| &previous.contents[..]);
+        Some(diff_contents(previous, current))
+    }
+}
+
+/// Summary of one table as listed by `/tables`
+#[derive(Serialize)]
+struct TableSummary {
+    id: TableId,
+    name: String,
+    symbol: String,
+    last_update: String,
+    levels: Vec<Level>,
+    elements: usize,
+}
+
+/// Router serving the tables of a [`TableStore`], enabled by the `serve` feature
+///
+/// * `GET /tables` - summary of every table
+/// * `GET /tables/{id}` - one table, header and contents
+/// * `GET /tables/{id}/diff` - elements added, removed and moved since the table was last replaced
+///
+/// # Example:
+/// ```text
+/// let store = Arc::new(TableStore::new());
+/// let subscriptions = TableSubscriptions::from_config("tables.toml")?;
+/// let refresher = Arc::clone(&store);
+/// tokio::task::spawn_blocking(move || refresher.refresh(&subscriptions, &ParseOptions::default()));
+/// let listener = tokio::net::TcpListener::bind("127.0.0.1:3000").await?;
+/// axum::serve(listener, router(store)).await?;
+/// ```
+pub fn router(store: Arc<TableStore>) -> Router {
+    Router::new()
+        .route("/tables", get(list_tables))
+        .route("/tables/{id}", get(get_table))
+        .route("/tables/{id}/diff", get(get_diff))
+        .with_state(store)
+}
+
+async fn list_tables(State(store): State<Arc<TableStore>>) -> Json<Vec<TableSummary>> {
+    let summaries = store
+        .tables()
+        .iter()
+        .map(|table| TableSummary {
+            id: table.id(),
+            name: table.name.clone(),
+            symbol: table.symbol.clone(),
+            last_update: table.last_update.clone(),
+            levels: table.levels.clone(),
+            elements: table.contents.len(),
+        })
+        .collect();
+    Json(summaries)
+}
+
+async fn get_table(
+    State(store): State<Arc<TableStore>>,
+    Path(id): Path<String>,
+) -> Result<Json<SharedTable>, StatusCode> {
+    store.get(&id).map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn get_diff(
+    State(store): State<Arc<TableStore>>,
+    Path(id): Path<String>,
+) -> Result<Json<TableDiff>, StatusCode> {
+    store.diff(&id).map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::{to_bytes, Body},
+        http::Request,
+    };
+    use serde_json::{json, Value};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn table(elements: &[(&str, &str)]) -> DifficultTable {
+        let mut table: DifficultTable =
+            serde_json::from_value(json!({ "name": "Satellite", "symbol": "sl" })).unwrap();
+        table.set_id("satellite");
+        for (md5, level) in elements {
+            table.add_element(
+                serde_json::from_value(
+                    json!({ "title": md5, "artist": "", "md5": md5, "level": level }),
+                )
+                .unwrap(),
+            );
+        }
+        table
+    }
+
+    async fn get_json(store: &Arc<TableStore>, uri: &str) -> (StatusCode, Value) {
+        let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let response = router(Arc::clone(store)).oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn should_serve_tables_and_diffs() {
+        let store = Arc::new(TableStore::new());
+        store.insert(table(&[("a", "1"), ("b", "1")]));
+        store.insert(table(&[("a", "2"), ("c", "1")]));
+        // unchanged, the diff is still against the first version
+        store.insert(table(&[("a", "2"), ("c", "1")]));
+
+        let (status, tables) = get_json(&store, "/tables").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(tables[0]["id"], "satellite");
+        assert_eq!(tables[0]["elements"], 2);
+
+        let (_, table) = get_json(&store, "/tables/satellite").await;
+        assert_eq!(table["name"], "Satellite");
+        assert_eq!(table["contents"].as_array().unwrap().len(), 2);
+
+        let (_, diff) = get_json(&store, "/tables/satellite/diff").await;
+        assert_eq!(diff["added"][0]["md5"], "c");
+        assert_eq!(diff["removed"][0]["md5"], "b");
+        assert_eq!(diff["moved"][0]["from"], "1");
+        assert_eq!(diff["moved"][0]["level"], "2");
+
+        let (status, _) = get_json(&store, "/tables/unknown").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
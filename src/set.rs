@@ -0,0 +1,185 @@
+use crate::{
+    bulk::parse_many_with_fetcher,
+    client::default_client,
+    fetcher::Fetcher,
+    hash::{Md5, Sha256},
+    level::Level,
+    modal::{DifficultTable, DifficultTableElement},
+    options::ParseOptions,
+    parser::ParseError,
+};
+
+/// Tables fetched by [`TableSet::refresh`] at a time
+const REFRESH_CONCURRENCY: usize = 8;
+
+/// Many parsed tables, each keyed by the url it's parsed from, in insertion order
+///
+/// # Example:
+/// ```text
+/// let mut set = TableSet::new();
+/// set.insert(url, parse(url)?);
+/// for (dth, element) in set.find_by_hash(sha256.parse().ok(), md5.parse().ok()) {
+///     println!("{}{}", dth.symbol, element.level);
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TableSet {
+    tables: Vec<(String, DifficultTable)>,
+}
+
+impl TableSet {
+    pub fn new() -> Self {
+        TableSet::default()
+    }
+
+    /// Add a table, replacing (and returning) the one parsed from the same url if any
+    pub fn insert(
+        &mut self,
+        url: impl Into<String>,
+        table: DifficultTable,
+    ) -> Option<DifficultTable> {
+        let url = url.into();
+        match self.tables.iter_mut().find(|(listed, _)| *listed == url) {
+            Some((_, listed)) => Some(std::mem::replace(listed, table)),
+            None => {
+                self.tables.push((url, table));
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, url: &str) -> Option<DifficultTable> {
+        let pos = self.tables.iter().position(|(listed, _)| listed == url)?;
+        Some(self.tables.remove(pos).1)
+    }
+
+    pub fn get(&self, url: &str) -> Option<&DifficultTable> {
+        self.tables
+            .iter()
+            .find(|(listed, _)| listed == url)
+            .map(|(_, table)| table)
+    }
+
+    /// (url, table) pairs, in insertion order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &DifficultTable)> {
+        self.tables.iter().map(|(url, table)| (url.as_str(), table))
+    }
+
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    /// Tables listing a chart with this md5
+    pub fn tables_with_md5(&self, md5: Md5) -> Vec<&DifficultTable> {
+        self.tables
+            .iter()
+            .filter(|(_, table)| table.find_by_md5(md5).is_some())
+            .map(|(_, table)| table)
+            .collect()
+    }
+
+    /// Element of every table listing a chart, matched as [`DifficultTable::find_by_hash`] does
+    pub fn find_by_hash(
+        &self,
+        sha256: Option<Sha256>,
+        md5: Option<Md5>,
+    ) -> Vec<(&DifficultTable, &DifficultTableElement)> {
+        self.tables
+            .iter()
+            .filter_map(|(_, table)| Some((table, table.find_by_hash(sha256, md5)?)))
+            .collect()
+    }
+
+    /// Levels of every table, in insertion then `levels` order, e.g. to list `sl0 ... sl12 ★1 ... ★25`
+    pub fn levels(&self) -> Vec<(&DifficultTable, &Level)> {
+        self.tables
+            .iter()
+            .flat_map(|(_, table)| table.levels.iter().map(move |level| (table, level)))
+            .collect()
+    }
+
+    /// Parse every table again from its url, see [`TableSet::refresh_with_fetcher`]
+    pub fn refresh(&mut self, options: &ParseOptions) -> Vec<(String, ParseError)> {
+        self.refresh_with_fetcher(&default_client(), options)
+    }
+
+    /// Parse every table again from its url through a [`Fetcher`], returns the failures by url
+    ///
+    /// Tables failing to parse are kept as they were
+    pub fn refresh_with_fetcher(
+        &mut self,
+        fetcher: &(dyn Fetcher + Sync),
+        options: &ParseOptions,
+    ) -> Vec<(String, ParseError)> {
+        let urls: Vec<String> = self.tables.iter().map(|(url, _)| url.clone()).collect();
+        let results = parse_many_with_fetcher(fetcher, &urls, REFRESH_CONCURRENCY, options);
+        let mut failures = Vec::new();
+        for ((url, table), result) in self.tables.iter_mut().zip(results) {
+            match result {
+                Ok(refreshed) => *table = refreshed,
+                Err(err) => failures.push((url.clone(), err)),
+            }
+        }
+        failures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::FetchError;
+
+    #[test]
+    pub fn should_look_up_and_refresh_tables() {
+        let fetcher = |url: &str| {
+            match url {
+            "https://example.com/sl/header.json" => {
+                Ok(br#"{ "name": "Satellite", "symbol": "sl", "data_url": "body.json" }"#.to_vec())
+            }
+            "https://example.com/sl/body.json" => Ok(br#"[
+                { "title": "a", "artist": "", "md5": "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA", "level": "0" },
+                { "title": "b", "artist": "", "md5": "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb", "level": "1" }
+            ]"#
+            .to_vec()),
+            _ => Err(FetchError::NotFound(url.to_string())),
+        }
+        };
+        let options = ParseOptions::default();
+        let mut set = TableSet::new();
+        let satellite = "https://example.com/sl/header.json";
+        let gone = "https://example.com/st/header.json";
+        set.insert(
+            satellite,
+            crate::parse_with_fetcher(&fetcher, satellite, &options).unwrap(),
+        );
+        let mut stella = set.get(satellite).unwrap().clone();
+        stella.symbol = "st".to_string();
+        stella.retain_elements(|element| element.title == "a");
+        set.insert(gone, stella);
+
+        assert_eq!(
+            set.tables_with_md5("aa".repeat(16).parse().unwrap()).len(),
+            2
+        );
+        let found = set.find_by_hash(None, "BB".repeat(16).parse().ok());
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0.symbol, "sl");
+        let levels: Vec<String> = set
+            .levels()
+            .into_iter()
+            .map(|(table, level)| format!("{}{level}", table.symbol))
+            .collect();
+        assert_eq!(levels, ["sl0", "sl1", "st0"]);
+
+        let failures = set.refresh_with_fetcher(&fetcher, &options);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, gone);
+        assert_eq!(set.get(gone).unwrap().symbol, "st");
+        assert_eq!(set.remove(gone).unwrap().symbol, "st");
+        assert_eq!(set.len(), 1);
+    }
+}
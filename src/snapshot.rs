@@ -0,0 +1,139 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    level::Level,
+    modal::{DifficultTable, DifficultTableElement},
+    parser::ParseError,
+};
+
+/// Version written by [`DifficultTable::to_snapshot`], snapshots of any other version are refused
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// Document written by [`DifficultTable::to_snapshot`]
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    version: u32,
+    #[serde(default)]
+    source_url: String,
+    /// RFC 3339
+    #[serde(default)]
+    fetched_at: Option<String>,
+    #[serde(default)]
+    fetched_urls: Vec<String>,
+    #[serde(default)]
+    mirror: Option<String>,
+    /// As written by [`DifficultTable::to_header_json`], data_url kept as is
+    header: Value,
+    contents: Vec<DifficultTableElement>,
+    levels: Vec<Level>,
+}
+
+impl DifficultTable {
+    /// The whole resolved table as one json document, to be loaded back by [`DifficultTable::from_snapshot`]
+    ///
+    /// Unlike the table's own serialization, everything needed to get an equivalent table back is kept: header
+    /// (unknown fields included), contents, levels in their order, courses, [`DifficultTable::source_url`],
+    /// [`DifficultTable::fetched_at`], [`DifficultTable::fetched_urls`] and [`DifficultTable::mirror`]
+    ///
+    /// # Example:
+    /// ```text
+    /// fs::write("satellite.snapshot.json", dth.to_snapshot())?;
+    /// let dth = DifficultTable::from_snapshot(&fs::read_to_string("satellite.snapshot.json")?)?;
+    /// ```
+    pub fn to_snapshot(&self) -> String {
+        let snapshot = Snapshot {
+            version: SNAPSHOT_VERSION,
+            source_url: self.source_url.clone(),
+            fetched_at: self.fetched_at.map(|time| time.to_rfc3339()),
+            fetched_urls: self.fetched_urls.clone(),
+            mirror: self.mirror.clone(),
+            header: serde_json::from_str(&self.header_json(&self.data_url))
+                .expect("header json is valid"),
+            contents: self.contents.clone(),
+            levels: self.levels.clone(),
+        };
+        serde_json::to_string(&snapshot).expect("snapshot is serializable")
+    }
+
+    /// Load a table written by [`DifficultTable::to_snapshot`]
+    ///
+    /// Fails with [`ParseError::UnsupportedSnapshot`] for snapshots written by an incompatible version of this crate
+    pub fn from_snapshot(raw: &str) -> Result<DifficultTable, ParseError> {
+        let snapshot: Snapshot = serde_json::from_str(raw)?;
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(ParseError::UnsupportedSnapshot(snapshot.version));
+        }
+        let mut header: DifficultTable = serde_json::from_value(snapshot.header)?;
+        let courses = std::mem::take(&mut header.courses);
+        let mut table = DifficultTable::from_parts(header, snapshot.contents, courses);
+        table.levels = snapshot.levels;
+        table.source_url = snapshot.source_url;
+        table.fetched_at = snapshot
+            .fetched_at
+            .and_then(|time| DateTime::parse_from_rfc3339(&time).ok())
+            .map(|time| time.with_timezone(&Utc));
+        table.fetched_urls = snapshot.fetched_urls;
+        table.mirror = snapshot.mirror;
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::parser::FetchError;
+
+    #[test]
+    pub fn should_round_trip_snapshots() {
+        let fetcher = |url: &str| -> Result<Vec<u8>, FetchError> {
+            let page: &str = match url {
+                "https://example.com/header.json" => {
+                    r#"{ "name": "Satellite", "symbol": "sl", "data_url": "body.json", "proposer": "someone",
+                         "course": [[{ "name": "sl0", "constraint": [], "trophy": [], "md5": ["aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"] }]] }"#
+                }
+                "https://example.com/body.json" => {
+                    r#"[{ "title": "b", "artist": "", "md5": "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb", "level": "10" },
+                        { "title": "a", "artist": "", "md5": "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", "level": "2" }]"#
+                }
+                _ => return Err(FetchError::NotFound(url.to_string())),
+            };
+            Ok(page.as_bytes().to_vec())
+        };
+        let options = crate::ParseOptions {
+            level_ordering: crate::LevelOrdering::Appearance,
+            ..Default::default()
+        };
+        let table =
+            crate::parse_with_fetcher(&fetcher, "https://example.com/header.json", &options)
+                .unwrap();
+        assert_eq!(table.source_url, "https://example.com/header.json");
+        assert!(table.fetched_at.is_some());
+
+        let loaded = DifficultTable::from_snapshot(&table.to_snapshot()).unwrap();
+        assert_eq!(loaded.content_hash(), table.content_hash());
+        assert_eq!(loaded.levels, ["10", "2"].map(Level::from));
+        assert_eq!(
+            loaded
+                .find_by_md5("a".repeat(32).parse().unwrap())
+                .unwrap()
+                .title,
+            "a"
+        );
+        assert_eq!(loaded.courses[0].name, "sl0");
+        assert_eq!(loaded.extra["proposer"], "someone");
+        assert_eq!(loaded.source_url, table.source_url);
+        assert_eq!(loaded.fetched_at, table.fetched_at);
+        assert_eq!(loaded.fetched_urls, table.fetched_urls);
+
+        let future =
+            json!({ "version": 2, "header": {}, "contents": [], "levels": [] }).to_string();
+        assert!(matches!(
+            DifficultTable::from_snapshot(&future),
+            Err(ParseError::UnsupportedSnapshot(2))
+        ));
+    }
+}
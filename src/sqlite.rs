@@ -0,0 +1,242 @@
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::modal::DifficultTable;
+
+/// Tables created by [`DifficultTable::write_sqlite`], every row refers to its table by `table_id`
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS difficult_tables (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    last_update TEXT NOT NULL,
+    tag TEXT NOT NULL,
+    data_url TEXT NOT NULL,
+    original_url TEXT NOT NULL,
+    mode TEXT NOT NULL,
+    content_hash TEXT NOT NULL DEFAULT ''
+);
+CREATE TABLE IF NOT EXISTS elements (
+    table_id TEXT NOT NULL REFERENCES difficult_tables(id),
+    position INTEGER NOT NULL,
+    level TEXT NOT NULL,
+    title TEXT NOT NULL,
+    artist TEXT NOT NULL,
+    md5 TEXT NOT NULL,
+    sha256 TEXT NOT NULL,
+    mode TEXT NOT NULL,
+    diff_name TEXT NOT NULL,
+    info TEXT NOT NULL,
+    bms_id TEXT NOT NULL,
+    url TEXT NOT NULL,
+    url_diff TEXT NOT NULL,
+    url_pack TEXT NOT NULL,
+    PRIMARY KEY (table_id, position)
+);
+CREATE INDEX IF NOT EXISTS elements_md5 ON elements(md5);
+CREATE INDEX IF NOT EXISTS elements_sha256 ON elements(sha256);
+CREATE TABLE IF NOT EXISTS courses (
+    table_id TEXT NOT NULL REFERENCES difficult_tables(id),
+    position INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    PRIMARY KEY (table_id, position)
+);
+CREATE TABLE IF NOT EXISTS course_constraints (
+    table_id TEXT NOT NULL,
+    course INTEGER NOT NULL,
+    constraint_name TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS course_charts (
+    table_id TEXT NOT NULL,
+    course INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    md5 TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS course_trophies (
+    table_id TEXT NOT NULL,
+    course INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    miss_rate REAL NOT NULL,
+    score_rate REAL NOT NULL
+);
+";
+
+/// Tables holding rows of one difficult table, children first
+const ROW_TABLES: [(&str, &str); 6] = [
+    ("course_trophies", "table_id"),
+    ("course_charts", "table_id"),
+    ("course_constraints", "table_id"),
+    ("courses", "table_id"),
+    ("elements", "table_id"),
+    ("difficult_tables", "id"),
+];
+
+impl DifficultTable {
+    /// Write this table into a SQLite database, enabled by the `sqlite` feature
+    ///
+    /// The schema (`difficult_tables`, `elements`, `courses`, `course_constraints`, `course_charts` and
+    /// `course_trophies`) is created if missing. Rows are keyed by [`DifficultTable::id`], writing a table again
+    /// replaces its previous rows, unless its [`DifficultTable::content_hash`] is the same as theirs. Everything is
+    /// written in one transaction
+    ///
+    /// # Example:
+    /// ```text
+    /// let mut conn = rusqlite::Connection::open("tables.db")?;
+    /// dth.write_sqlite(&mut conn)?;
+    /// ```
+    pub fn write_sqlite(&self, conn: &mut Connection) -> rusqlite::Result<()> {
+        let id = self.id();
+        let id = id.as_str();
+        let content_hash = self.content_hash().to_string();
+        let tx = conn.transaction()?;
+        tx.execute_batch(SCHEMA)?;
+        let has_hash_column: bool = tx.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('difficult_tables') WHERE name = 'content_hash'",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_hash_column {
+            // databases written before content_hash existed
+            tx.execute_batch(
+                "ALTER TABLE difficult_tables ADD COLUMN content_hash TEXT NOT NULL DEFAULT ''",
+            )?;
+        }
+        let stored: Option<String> = tx
+            .query_row(
+                "SELECT content_hash FROM difficult_tables WHERE id = ?1",
+                [id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if stored.as_ref() == Some(&content_hash) {
+            return tx.commit();
+        }
+        for (table, column) in ROW_TABLES {
+            tx.execute(&format!("DELETE FROM {table} WHERE {column} = ?1"), [id])?;
+        }
+        tx.execute(
+            "INSERT INTO difficult_tables VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                id,
+                self.name,
+                self.symbol,
+                self.last_update,
+                self.tag,
+                self.data_url,
+                self.original_url,
+                self.mode,
+                content_hash
+            ],
+        )?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO elements
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            )?;
+            for (position, element) in self.contents.iter().enumerate() {
+                insert.execute(params![
+                    id,
+                    position,
+                    element.level.as_str(),
+                    element.title,
+                    element.artist,
+                    element.md5,
+                    element.sha256,
+                    element.mode,
+                    element.diff_name,
+                    element.info,
+                    element.bms_id,
+                    element.url,
+                    element.url_diff,
+                    element.url_pack
+                ])?;
+            }
+            for (course_pos, course) in self.courses.iter().enumerate() {
+                tx.execute(
+                    "INSERT INTO courses VALUES (?1, ?2, ?3)",
+                    params![id, course_pos, course.name],
+                )?;
+                for constraint in &course.constraints {
+                    tx.execute(
+                        "INSERT INTO course_constraints VALUES (?1, ?2, ?3)",
+                        params![id, course_pos, constraint],
+                    )?;
+                }
+                for (position, md5) in course.md5.iter().enumerate() {
+                    tx.execute(
+                        "INSERT INTO course_charts VALUES (?1, ?2, ?3, ?4)",
+                        params![id, course_pos, position, md5],
+                    )?;
+                }
+                for trophy in &course.trophy {
+                    tx.execute(
+                        "INSERT INTO course_trophies VALUES (?1, ?2, ?3, ?4, ?5)",
+                        params![
+                            id,
+                            course_pos,
+                            trophy.name,
+                            trophy.miss_rate,
+                            trophy.score_rate
+                        ],
+                    )?;
+                }
+            }
+        }
+        tx.commit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    pub fn should_write_tables_into_sqlite() {
+        let mut table: DifficultTable = serde_json::from_value(json!({
+            "name": "Satellite",
+            "symbol": "sl",
+            "data_url": "body.json",
+            "course": [[{
+                "name": "sl0",
+                "constraint": ["grade_mirror", "gauge_lr2"],
+                "trophy": [{ "name": "goldmedal", "missrate": 1.0, "scorerate": 70.0 }],
+                "md5": ["aa", "bb"],
+            }]],
+        }))
+        .unwrap();
+        table.contents = serde_json::from_value(json!([
+            { "title": "a", "artist": "", "md5": "aa", "level": "0" },
+            { "title": "b", "artist": "", "md5": "bb", "level": "1" },
+        ]))
+        .unwrap();
+        let mut conn = Connection::open_in_memory().unwrap();
+        table.write_sqlite(&mut conn).unwrap();
+        table.contents.pop();
+        table.write_sqlite(&mut conn).unwrap();
+        // unchanged, its rows are left alone
+        conn.execute("UPDATE elements SET title = 'untouched'", [])
+            .unwrap();
+        table.write_sqlite(&mut conn).unwrap();
+
+        let count = |sql: &str| -> i64 { conn.query_row(sql, [], |row| row.get(0)).unwrap() };
+        assert_eq!(count("SELECT COUNT(*) FROM difficult_tables"), 1);
+        assert_eq!(count("SELECT COUNT(*) FROM elements"), 1);
+        assert_eq!(count("SELECT COUNT(*) FROM course_constraints"), 2);
+        assert_eq!(count("SELECT COUNT(*) FROM course_charts"), 2);
+        let level: String = conn
+            .query_row(
+                "SELECT e.level FROM course_charts c JOIN elements e USING (table_id, md5)",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(level, "0");
+        assert_eq!(
+            conn.query_row("SELECT title FROM elements", [], |row| row
+                .get::<_, String>(0))
+                .unwrap(),
+            "untouched"
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use serde_json::{Map, Value};
+
+use crate::{
+    modal::{DifficultTable, DifficultTableElement},
+    parser::ParseError,
+};
+
+/// Reject unknown and empty optional fields of a header json, see [`crate::ParseOptions::strict`]
+pub(crate) fn check_header(json: &str) -> Result<(), ParseError> {
+    let header: DifficultTable = serde_json::from_str(json)?;
+    check_unknown(&header.extra, "")?;
+    check_empty(
+        &serde_json::from_str(json)?,
+        "",
+        &["name", "symbol", "data_url"],
+    )
+}
+
+/// Same as [`check_header`], for each element of a body json
+pub(crate) fn check_body(json: &str) -> Result<(), ParseError> {
+    let body: Vec<DifficultTableElement> = serde_json::from_str(json)?;
+    for (index, element) in body.iter().enumerate() {
+        check_unknown(&element.extra, &format!("[{index}]."))?;
+    }
+    let Value::Array(elements) = serde_json::from_str(json)? else {
+        return Ok(());
+    };
+    for (index, element) in elements.iter().enumerate() {
+        check_empty(
+            element,
+            &format!("[{index}]."),
+            &["title", "artist", "md5", "level"],
+        )?;
+    }
+    Ok(())
+}
+
+/// Fields that are only kept in `extra`
+fn check_unknown(extra: &Map<String, Value>, prefix: &str) -> Result<(), ParseError> {
+    let unknown: Vec<String> = extra.keys().map(|key| format!("{prefix}{key}")).collect();
+    if unknown.is_empty() {
+        return Ok(());
+    }
+    Err(ParseError::Strict(format!(
+        "unknown fields {}",
+        unknown.join(", ")
+    )))
+}
+
+/// Optional fields that are present, but empty or null, and thus would be silently defaulted
+fn check_empty(object: &Value, prefix: &str, required: &[&str]) -> Result<(), ParseError> {
+    let Value::Object(object) = object else {
+        return Ok(());
+    };
+    let empty: Vec<String> = object
+        .iter()
+        .filter(|(key, value)| {
+            !required.contains(&key.as_str())
+                && (value.is_null() || value.as_str().is_some_and(str::is_empty))
+        })
+        .map(|(key, _)| format!("{prefix}{key}"))
+        .collect();
+    if empty.is_empty() {
+        return Ok(());
+    }
+    Err(ParseError::Strict(format!(
+        "empty fields {}",
+        empty.join(", ")
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn should_reject_unknown_and_empty_fields() {
+        let header = r#"{ "name": "t", "symbol": "t", "data_url": "body.json", "tag": "x" }"#;
+        assert!(check_header(header).is_ok());
+        let typo = r#"{ "name": "t", "symbol": "t", "data_url": "body.json", "last_updat": "x" }"#;
+        assert!(
+            matches!(check_header(typo), Err(ParseError::Strict(msg)) if msg.contains("last_updat"))
+        );
+        let empty = r#"{ "name": "t", "symbol": "t", "data_url": "body.json", "tag": "" }"#;
+        assert!(matches!(check_header(empty), Err(ParseError::Strict(msg)) if msg.contains("tag")));
+
+        let body = r#"[
+            { "title": "a", "artist": "", "md5": "", "level": "1", "comment": "kept quiet" },
+            { "title": "b", "artist": "", "md5": "", "level": "1", "sha256": "" }
+        ]"#;
+        assert!(
+            matches!(check_body(body), Err(ParseError::Strict(msg)) if msg == "empty fields [1].sha256")
+        );
+        let body = r#"[{ "title": "a", "artist": "", "md5": "", "level": "1", "lvl": "1" }]"#;
+        assert!(matches!(check_body(body), Err(ParseError::Strict(msg)) if msg.contains("lvl")));
+    }
+}
@@ -0,0 +1,174 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::{id::TableId, modal::DifficultTable, options::ParseOptions, parser::ParseError};
+
+/// A set of subscribed difficult tables, usually loaded from a config file
+///
+/// # Example:
+/// ```text
+/// [[tables]]
+/// url = "https://stellabms.xyz/sl/table.html"
+///
+/// [[tables]]
+/// name = "insane"
+/// url = "http://zris.work/bmstable/insane/insane_header.json"
+/// # defaults to true
+/// enabled = false
+/// # 90s, 30m, 6h or 1d, none means the consumer's default
+/// refresh = "1d"
+/// # replace what the table itself says
+/// overrides = { id = "insane", symbol = "★" }
+/// ```
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct TableSubscriptions {
+    #[serde(default)]
+    pub tables: Vec<Subscription>,
+}
+
+/// One subscribed table, see [`TableSubscriptions`]
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Subscription {
+    /// Label of this subscription, e.g. for logging, the url is used when absent
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Table url, passed to [`crate::parse_with_options`]
+    pub url: String,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    /// How often the table should be fetched again
+    #[serde(default, deserialize_with = "interval")]
+    pub refresh: Option<Duration>,
+    #[serde(default)]
+    pub overrides: Overrides,
+}
+
+/// Header fields replacing the parsed ones, see [`Subscription::parse`]
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Overrides {
+    pub id: Option<TableId>,
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+fn interval<'de, D>(d: D) -> Result<Option<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(d)?
+        .map(|s| parse_interval(&s).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+/// Parse an interval like `90s`, `30m`, `6h` or `1d`
+pub fn parse_interval(s: &str) -> Result<Duration, String> {
+    let (value, unit) = s.split_at(s.len() - s.trim_start_matches(char::is_numeric).len());
+    let value: u64 = value
+        .parse()
+        .map_err(|_| format!("`{s}` doesn't start with a number"))?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(format!("unknown unit `{unit}`, expected one of s, m, h, d")),
+    };
+    Ok(Duration::from_secs(value * seconds))
+}
+
+impl TableSubscriptions {
+    /// Read subscriptions from a toml file, enabled by the `config` feature
+    #[cfg(feature = "config")]
+    pub fn from_config(path: impl AsRef<std::path::Path>) -> Result<Self, ParseError> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+
+    /// Read subscriptions from toml text, enabled by the `config` feature
+    #[cfg(feature = "config")]
+    pub fn from_toml(data: &str) -> Result<Self, ParseError> {
+        Ok(toml::from_str(data)?)
+    }
+
+    /// Subscriptions that are not disabled
+    pub fn enabled(&self) -> impl Iterator<Item = &Subscription> {
+        self.tables.iter().filter(|table| table.enabled)
+    }
+}
+
+impl Subscription {
+    /// [`Subscription::name`], or the url when absent
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.url)
+    }
+
+    /// Parse the subscribed table, then apply the overrides
+    pub fn parse(&self, options: &ParseOptions) -> Result<DifficultTable, ParseError> {
+        let mut table = crate::parse_with_options(self.url.clone(), options)?;
+        self.overrides.apply(&mut table);
+        Ok(table)
+    }
+}
+
+impl Overrides {
+    pub fn apply(&self, table: &mut DifficultTable) {
+        if let Some(name) = &self.name {
+            table.name = name.clone();
+        }
+        if let Some(symbol) = &self.symbol {
+            table.symbol = symbol.clone();
+        }
+        if let Some(id) = &self.id {
+            table.set_id(id.clone());
+        }
+    }
+}
+
+#[cfg(all(test, feature = "config"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn should_load_subscriptions() {
+        let subscriptions = TableSubscriptions::from_toml(
+            r#"
+            [[tables]]
+            url = "https://stellabms.xyz/sl/table.html"
+
+            [[tables]]
+            name = "insane"
+            url = "http://zris.work/bmstable/insane/insane_header.json"
+            enabled = false
+            refresh = "1d"
+            overrides = { id = "insane", symbol = "★" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(subscriptions.tables.len(), 2);
+        let enabled: Vec<_> = subscriptions.enabled().collect();
+        assert_eq!(enabled.len(), 1);
+        assert_eq!(enabled[0].label(), "https://stellabms.xyz/sl/table.html");
+        assert_eq!(enabled[0].refresh, None);
+
+        let insane = &subscriptions.tables[1];
+        assert_eq!(insane.refresh, Some(Duration::from_secs(24 * 60 * 60)));
+        let mut table: DifficultTable =
+            serde_json::from_value(serde_json::json!({ "name": "発狂BMS難易度表", "symbol": "" }))
+                .unwrap();
+        insane.overrides.apply(&mut table);
+        assert_eq!(table.id(), "insane");
+        assert_eq!(table.symbol, "★");
+
+        assert!(TableSubscriptions::from_toml("[[tables]]\nrefresh = \"1h\"").is_err());
+        assert!(
+            TableSubscriptions::from_toml("[[tables]]\nurl = \"x\"\nrefresh = \"1w\"").is_err()
+        );
+    }
+}
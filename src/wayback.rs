@@ -0,0 +1,88 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde_json::Value;
+use url::Url;
+
+use crate::fetcher::Fetcher;
+
+const AVAILABILITY_API: &str = "https://archive.org/wayback/available";
+
+/// Internet Archive capture a table was parsed from, see [`crate::ParseOptions::wayback_fallback`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveCapture {
+    /// Url that was archived
+    pub url: String,
+    pub captured_at: DateTime<Utc>,
+}
+
+impl ArchiveCapture {
+    /// Url of the capture as it was served, without the Wayback Machine's toolbar and link rewriting
+    pub fn raw_url(&self) -> String {
+        format!(
+            "https://web.archive.org/web/{}id_/{}",
+            self.captured_at.format("%Y%m%d%H%M%S"),
+            self.url
+        )
+    }
+}
+
+/// Ask the Wayback Machine for the latest capture of `url`, any failure means there is none
+pub(crate) fn find_capture(fetcher: &dyn Fetcher, url: &str) -> Option<ArchiveCapture> {
+    let raw = fetcher.fetch(availability_url(url)?.as_str()).ok()?;
+    parse_availability(url, std::str::from_utf8(&raw).ok()?)
+}
+
+/// Availability API query for `url`
+pub(crate) fn availability_url(url: &str) -> Option<Url> {
+    Url::parse_with_params(AVAILABILITY_API, [("url", url)]).ok()
+}
+
+/// Read the closest capture out of an availability API response
+pub(crate) fn parse_availability(url: &str, response: &str) -> Option<ArchiveCapture> {
+    let response: Value = serde_json::from_str(response).ok()?;
+    let closest = &response["archived_snapshots"]["closest"];
+    if closest["available"] != Value::Bool(true) {
+        return None;
+    }
+    let timestamp = closest["timestamp"].as_str()?;
+    let captured_at = NaiveDateTime::parse_from_str(timestamp, "%Y%m%d%H%M%S").ok()?;
+    Some(ArchiveCapture {
+        url: url.to_string(),
+        captured_at: captured_at.and_utc(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    pub fn should_read_the_closest_capture() {
+        let url = "http://example.com/table.html";
+        let response = r#"{
+            "url": "example.com/table.html",
+            "archived_snapshots": {
+                "closest": {
+                    "status": "200",
+                    "available": true,
+                    "url": "http://web.archive.org/web/20150908052343/http://example.com/table.html",
+                    "timestamp": "20150908052343"
+                }
+            }
+        }"#;
+        let capture = parse_availability(url, response).unwrap();
+        assert_eq!(
+            capture.captured_at,
+            Utc.with_ymd_and_hms(2015, 9, 8, 5, 23, 43).unwrap()
+        );
+        assert_eq!(
+            capture.raw_url(),
+            "https://web.archive.org/web/20150908052343id_/http://example.com/table.html"
+        );
+        assert_eq!(
+            parse_availability(url, r#"{ "archived_snapshots": {} }"#),
+            None
+        );
+    }
+}
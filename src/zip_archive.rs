@@ -0,0 +1,147 @@
+use std::{
+    io::{Read, Seek, Write},
+    time::SystemTime,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::{
+    export::{DEFAULT_DATA_URL, HEADER_FILE},
+    modal::DifficultTable,
+    parser::{self, ParseError},
+};
+
+/// Describes the table of a zip written by [`DifficultTable::write_zip`]
+const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    /// Url the table was parsed from, see [`DifficultTable::source_url`]
+    #[serde(default)]
+    source_url: String,
+    /// The header's own data_url, header.json points at body.json instead
+    #[serde(default)]
+    data_url: String,
+    /// RFC 3339, see [`DifficultTable::fetched_at`]
+    #[serde(default)]
+    fetched_at: Option<String>,
+    /// RFC 3339, when the zip was written
+    #[serde(default)]
+    archived_at: Option<String>,
+}
+
+impl DifficultTable {
+    /// Write this table into a zip archive holding `header.json`, `body.json` and a `manifest.json`, enabled by
+    /// the `zip` feature
+    ///
+    /// As with [`DifficultTable::write_table_dir`], the written header's data_url is `body.json`. The manifest keeps
+    /// the original data_url, [`DifficultTable::source_url`], [`DifficultTable::fetched_at`] and when the zip was
+    /// written
+    ///
+    /// # Example:
+    /// ```text
+    /// dth.write_zip(File::create("satellite-2024-06-01.zip")?)?;
+    /// let dth = DifficultTable::read_zip(File::open("satellite-2024-06-01.zip")?)?;
+    /// ```
+    pub fn write_zip(&self, writer: impl Write + Seek) -> Result<(), ParseError> {
+        let manifest = Manifest {
+            source_url: self.source_url.clone(),
+            data_url: self.data_url.clone(),
+            fetched_at: self.fetched_at.map(|time| time.to_rfc3339()),
+            archived_at: Some(DateTime::<Utc>::from(SystemTime::now()).to_rfc3339()),
+        };
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        let mut zip = ZipWriter::new(writer);
+        for (name, content) in [
+            (HEADER_FILE, self.header_json(DEFAULT_DATA_URL)),
+            (DEFAULT_DATA_URL, self.to_body_json()),
+            (
+                MANIFEST_FILE,
+                serde_json::to_string_pretty(&manifest).expect("manifest is serializable"),
+            ),
+        ] {
+            zip.start_file(name, options)?;
+            zip.write_all(content.as_bytes())?;
+        }
+        zip.finish()?;
+        Ok(())
+    }
+
+    /// Read a table written by [`DifficultTable::write_zip`]
+    ///
+    /// The header and body are parsed as [`crate::parse_from_parts`] does, the manifest is optional
+    pub fn read_zip(reader: impl Read + Seek) -> Result<DifficultTable, ParseError> {
+        let mut zip = ZipArchive::new(reader)?;
+        let mut read = |name| -> Result<String, ParseError> {
+            let mut content = String::new();
+            zip.by_name(name)?.read_to_string(&mut content)?;
+            Ok(content)
+        };
+        let header = read(HEADER_FILE)?;
+        let body = read(DEFAULT_DATA_URL)?;
+        let manifest = match read(MANIFEST_FILE) {
+            Ok(manifest) => Some(serde_json::from_str::<Manifest>(&manifest)?),
+            Err(ParseError::ZipError(zip::result::ZipError::FileNotFound)) => None,
+            Err(err) => return Err(err),
+        };
+        let mut table = parser::parse_from_parts(&header, &body)?;
+        if let Some(manifest) = manifest {
+            if !manifest.data_url.is_empty() {
+                table.data_url = manifest.data_url;
+            }
+            table.source_url = manifest.source_url;
+            table.fetched_at = manifest
+                .fetched_at
+                .and_then(|time| DateTime::parse_from_rfc3339(&time).ok())
+                .map(|time| time.with_timezone(&Utc));
+        }
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    pub fn should_round_trip_zip_archives() {
+        let mut table: DifficultTable = serde_json::from_value(json!({
+            "name": "Satellite",
+            "symbol": "sl",
+            "data_url": "https://example.com/sl/score.json",
+            "course": [[{ "name": "sl0", "constraint": [], "trophy": [], "md5": ["aa"] }]],
+        }))
+        .unwrap();
+        table.add_element(
+            serde_json::from_value(
+                json!({ "title": "a", "artist": "", "md5": "aa", "level": "0" }),
+            )
+            .unwrap(),
+        );
+        table.source_url = "https://example.com/sl/table.html".to_string();
+        table.fetched_at = Some(DateTime::from_timestamp(1_700_000_000, 0).unwrap());
+
+        let mut zip = Cursor::new(Vec::new());
+        table.write_zip(&mut zip).unwrap();
+        let read = DifficultTable::read_zip(Cursor::new(zip.get_ref())).unwrap();
+        assert_eq!(read.content_hash(), table.content_hash());
+        assert_eq!(read.source_url, table.source_url);
+        assert_eq!(read.fetched_at, table.fetched_at);
+
+        let mut archive = ZipArchive::new(Cursor::new(zip.into_inner())).unwrap();
+        let mut header = String::new();
+        archive
+            .by_name(HEADER_FILE)
+            .unwrap()
+            .read_to_string(&mut header)
+            .unwrap();
+        assert!(header.contains(r#""data_url": "body.json""#));
+        assert!(DifficultTable::read_zip(Cursor::new(b"not a zip".to_vec())).is_err());
+    }
+}